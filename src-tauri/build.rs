@@ -1,3 +1,46 @@
+// Listing the commands gives each one `allow-<command>`/`deny-<command>` permissions
+// and turns on ACL checks for app commands, so a page can only invoke what a
+// capability grants it (see capabilities/overlay.json).
+const COMMANDS: &[&str] = &[
+  "open_external",
+  "complete_lastfm",
+  "get_lastfm_status",
+  "disconnect_lastfm",
+  "report_playback",
+  "get_settings",
+  "update_settings",
+  "take_toasts",
+  "get_status",
+  "get_control_api",
+  "regenerate_control_token",
+  "reconnect_mqtt",
+  "verify_scrobbles",
+  "get_missing_scrobbles",
+  "resubmit_scrobbles",
+  "dismiss_missing_scrobbles",
+  "prepare_backfill",
+  "submit_backfill",
+  "manual_scrobble",
+  "get_lastfm_profile",
+  "refresh_lastfm_profile",
+  "get_artist_info",
+  "get_similar_artists",
+  "open_soundcloud_search",
+  "get_love_sync_status",
+  "sync_loves",
+  "get_track_tags",
+  "update_track_tags",
+  "get_auth_options",
+  "begin_lastfm_auth",
+  "start_desktop_auth",
+  "await_desktop_auth",
+  "cancel_desktop_auth",
+  "login_lastfm_mobile",
+];
+
 fn main() {
-  tauri_build::build()
+  tauri_build::try_build(
+    tauri_build::Attributes::new().app_manifest(tauri_build::AppManifest::new().commands(COMMANDS)),
+  )
+  .expect("failed to run tauri-build");
 }
@@ -0,0 +1,80 @@
+// Guards the browser callback flow. "Connect in browser" creates a pending request
+// whose nonce travels in the callback URL's `state` parameter; only a callback that
+// echoes the current, unexpired nonce may complete sign-in.
+
+// Last.fm tokens are valid for 60 minutes, but a sign-in should not stay open that long.
+pub const PENDING_AUTH_TTL_MS: u64 = 10 * 60 * 1000;
+
+/// Hex string from 16 bytes of OS randomness.
+pub fn random_token() -> Result<String, String> {
+  let mut bytes = [0u8; 16];
+  getrandom::getrandom(&mut bytes).map_err(|e| e.to_string())?;
+  Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingAuth {
+  pub nonce: String,
+  pub expires_at: u64,
+}
+
+impl PendingAuth {
+  pub fn new(now_ms: u64) -> Result<Self, String> {
+    Ok(Self {
+      nonce: random_token()?,
+      expires_at: now_ms + PENDING_AUTH_TTL_MS,
+    })
+  }
+}
+
+/// Consumes the pending request if `state` matches it. Expired requests are dropped;
+/// a mismatch leaves the real request in place so a stray callback can't cancel it.
+pub fn take_pending(pending: &mut Option<PendingAuth>, state: Option<&str>, now_ms: u64) -> Result<(), String> {
+  match pending.as_ref() {
+    None => Err("no sign-in is in progress".to_string()),
+    Some(p) if p.expires_at <= now_ms => {
+      *pending = None;
+      Err("the sign-in request expired; connect again".to_string())
+    }
+    Some(p) if state != Some(p.nonce.as_str()) => Err("callback does not match the pending sign-in".to_string()),
+    Some(_) => {
+      *pending = None;
+      Ok(())
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const NOW: u64 = 1_700_000_000_000;
+
+  #[test]
+  fn tokens_are_random_hex() {
+    let a = random_token().unwrap();
+    let b = random_token().unwrap();
+    assert_eq!(a.len(), 32);
+    assert!(a.chars().all(|c| c.is_ascii_hexdigit()));
+    assert_ne!(a, b);
+  }
+
+  #[test]
+  fn accepts_matching_nonce_once() {
+    let mut pending = Some(PendingAuth::new(NOW).unwrap());
+    let nonce = pending.as_ref().unwrap().nonce.clone();
+    assert!(take_pending(&mut pending, Some("forged"), NOW).is_err());
+    assert!(take_pending(&mut pending, None, NOW).is_err());
+    assert!(pending.is_some());
+    assert_eq!(take_pending(&mut pending, Some(&nonce), NOW + 1000), Ok(()));
+    assert!(take_pending(&mut pending, Some(&nonce), NOW + 2000).is_err());
+  }
+
+  #[test]
+  fn rejects_and_clears_expired_requests() {
+    let mut pending = Some(PendingAuth::new(NOW).unwrap());
+    let nonce = pending.as_ref().unwrap().nonce.clone();
+    assert!(take_pending(&mut pending, Some(&nonce), NOW + PENDING_AUTH_TTL_MS).is_err());
+    assert!(pending.is_none());
+  }
+}
@@ -0,0 +1,182 @@
+// Candidate plays for backfilling Last.fm from SoundCloud's listening history
+// (`/me/play-history/tracks` on api-v2, walked by the overlay with the page's own auth).
+
+use crate::lastfm::RecentTrack;
+use crate::scrobble_log::{SubmittedScrobble, MAX_SCROBBLE_AGE_SECS};
+
+// track.scrobble accepts at most 50 plays per request.
+pub const BATCH_SIZE: usize = 50;
+// A Last.fm scrobble of the same artist and title within this window counts as the same play.
+const DUPLICATE_WINDOW_SECS: u64 = 5 * 60;
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryPlay {
+  pub track_id: String,
+  pub title: String,
+  pub artist: String,
+  #[serde(default)]
+  pub album: Option<String>,
+  #[serde(default)]
+  pub duration_ms: u64,
+  // SoundCloud's `played_at`, unix milliseconds.
+  pub played_at: u64,
+}
+
+impl HistoryPlay {
+  pub fn timestamp(&self) -> u64 {
+    self.played_at / 1000
+  }
+}
+
+pub fn normalize(s: &str) -> String {
+  s.chars()
+    .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+    .collect::<String>()
+    .split_whitespace()
+    .collect::<Vec<_>>()
+    .join(" ")
+    .to_lowercase()
+}
+
+fn near(a: u64, b: u64, window: u64) -> bool {
+  a.abs_diff(b) <= window
+}
+
+/// Filters history down to plays worth submitting: inside Last.fm's 14-day window,
+/// with usable metadata, and not already present on Last.fm or in our submission log.
+/// Output is oldest first, the order Last.fm expects.
+pub fn build_candidates(
+  plays: Vec<HistoryPlay>,
+  recent: &[RecentTrack],
+  submitted: &[SubmittedScrobble],
+  now_ms: u64,
+) -> Vec<HistoryPlay> {
+  let oldest_allowed = (now_ms / 1000).saturating_sub(MAX_SCROBBLE_AGE_SECS);
+  let now_secs = now_ms / 1000;
+  let known: Vec<(String, String, u64, u64)> = recent
+    .iter()
+    .filter_map(|t| Some((normalize(&t.artist), normalize(&t.title), t.timestamp?, DUPLICATE_WINDOW_SECS)))
+    .chain(submitted.iter().map(|s| {
+      let window = DUPLICATE_WINDOW_SECS.max(s.duration_ms / 1000);
+      (normalize(&s.artist), normalize(&s.title), s.timestamp, window)
+    }))
+    .collect();
+
+  let mut out: Vec<HistoryPlay> = Vec::new();
+  let mut plays = plays;
+  plays.sort_by_key(|p| p.played_at);
+  for play in plays {
+    let ts = play.timestamp();
+    if ts <= oldest_allowed || ts > now_secs {
+      continue;
+    }
+    if play.title.trim().is_empty() || play.artist.trim().is_empty() {
+      continue;
+    }
+    let (artist, title) = (normalize(&play.artist), normalize(&play.title));
+    if known
+      .iter()
+      .any(|(a, t, k_ts, window)| *a == artist && *t == title && near(*k_ts, ts, *window))
+    {
+      continue;
+    }
+    if out
+      .iter()
+      .any(|o| o.track_id == play.track_id && near(o.timestamp(), ts, DUPLICATE_WINDOW_SECS))
+    {
+      continue;
+    }
+    out.push(play);
+  }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const NOW_MS: u64 = 1_700_000_000_000;
+  const NOW: u64 = NOW_MS / 1000;
+
+  fn play(id: &str, title: &str, secs_ago: u64) -> HistoryPlay {
+    HistoryPlay {
+      track_id: id.to_string(),
+      title: title.to_string(),
+      artist: "Bicep".to_string(),
+      album: None,
+      duration_ms: 240_000,
+      played_at: (NOW - secs_ago) * 1000,
+    }
+  }
+
+  fn recent(title: &str, ts: u64) -> RecentTrack {
+    RecentTrack {
+      artist: "Bicep".to_string(),
+      title: title.to_string(),
+      album: None,
+      timestamp: Some(ts),
+      now_playing: false,
+    }
+  }
+
+  #[test]
+  fn normalizes_case_punctuation_and_spacing() {
+    assert_eq!(normalize("  Glue (Original  Mix)! "), "glue original mix");
+  }
+
+  #[test]
+  fn drops_plays_outside_the_window() {
+    let plays = vec![
+      play("/a", "Too Old", MAX_SCROBBLE_AGE_SECS + 10),
+      play("/b", "Fine", 3600),
+      HistoryPlay { played_at: (NOW + 60) * 1000, ..play("/c", "Future", 0) },
+    ];
+    let out = build_candidates(plays, &[], &[], NOW_MS);
+    assert_eq!(out.len(), 1);
+    assert_eq!(out[0].title, "Fine");
+  }
+
+  #[test]
+  fn dedupes_against_lastfm_and_submission_log() {
+    let plays = vec![
+      play("/a", "Glue", 7200),
+      play("/b", "Atlas", 3600),
+      play("/c", "Apricots", 1800),
+      play("/d", "Saku", 600),
+    ];
+    let submitted = vec![SubmittedScrobble {
+      artist: "Bicep".into(),
+      title: "Apricots".into(),
+      timestamp: NOW - 1800 + 90,
+      duration_ms: 300_000,
+      ..Default::default()
+    }];
+    let out = build_candidates(
+      plays,
+      &[recent("GLUE", NOW - 7200 + 30), recent("Atlas", NOW - 100_000)],
+      &submitted,
+      NOW_MS,
+    );
+    let titles: Vec<&str> = out.iter().map(|p| p.title.as_str()).collect();
+    assert_eq!(titles, vec!["Atlas", "Saku"]);
+  }
+
+  #[test]
+  fn same_title_by_another_artist_is_not_a_duplicate() {
+    let plays = vec![play("/a", "Intro", 3600)];
+    let other = RecentTrack { artist: "The xx".to_string(), ..recent("Intro", NOW - 3600 + 30) };
+    let out = build_candidates(plays.clone(), &[other], &[], NOW_MS);
+    assert_eq!(out.len(), 1);
+    let same = RecentTrack { artist: "BICEP".to_string(), ..recent("intro", NOW - 3600 + 30) };
+    assert!(build_candidates(plays, &[same], &[], NOW_MS).is_empty());
+  }
+
+  #[test]
+  fn sorts_oldest_first_and_collapses_repeats() {
+    let plays = vec![play("/b", "Saku", 600), play("/a", "Glue", 7200), play("/a", "Glue", 7190)];
+    let out = build_candidates(plays, &[], &[], NOW_MS);
+    let titles: Vec<&str> = out.iter().map(|p| p.title.as_str()).collect();
+    assert_eq!(titles, vec!["Glue", "Saku"]);
+  }
+}
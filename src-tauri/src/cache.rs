@@ -0,0 +1,96 @@
+// Small JSON file cache for Last.fm lookups, stored next to the exe like the main
+// store. Entries carry their fetch time; readers pass the TTL they accept.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+const MAX_ENTRIES: usize = 300;
+// Entries this old are dropped on write regardless of the reader's TTL.
+const MAX_AGE_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Entry {
+  fetched_at: u64,
+  value: serde_json::Value,
+}
+
+pub struct DiskCache {
+  path: PathBuf,
+}
+
+impl DiskCache {
+  pub fn new(path: PathBuf) -> Self {
+    Self { path }
+  }
+
+  fn load(&self) -> HashMap<String, Entry> {
+    fs::read_to_string(&self.path)
+      .ok()
+      .and_then(|text| serde_json::from_str(&text).ok())
+      .unwrap_or_default()
+  }
+
+  /// Returns the cached value and its fetch time if it is younger than `ttl_ms`.
+  pub fn get<T: DeserializeOwned>(&self, key: &str, ttl_ms: u64, now_ms: u64) -> Option<(T, u64)> {
+    let entry = self.load().remove(key)?;
+    if now_ms.saturating_sub(entry.fetched_at) > ttl_ms {
+      return None;
+    }
+    let value = serde_json::from_value(entry.value).ok()?;
+    Some((value, entry.fetched_at))
+  }
+
+  pub fn put<T: Serialize>(&self, key: &str, value: &T, now_ms: u64) -> Result<(), String> {
+    let mut entries = self.load();
+    let value = serde_json::to_value(value).map_err(|e| e.to_string())?;
+    entries.insert(key.to_string(), Entry { fetched_at: now_ms, value });
+    entries.retain(|_, e| now_ms.saturating_sub(e.fetched_at) <= MAX_AGE_MS);
+    if entries.len() > MAX_ENTRIES {
+      let mut by_age: Vec<(String, u64)> = entries.iter().map(|(k, e)| (k.clone(), e.fetched_at)).collect();
+      by_age.sort_by_key(|(_, at)| *at);
+      for (k, _) in by_age.into_iter().take(entries.len() - MAX_ENTRIES) {
+        entries.remove(&k);
+      }
+    }
+    let payload = serde_json::to_string(&entries).map_err(|e| e.to_string())?;
+    fs::write(&self.path, payload).map_err(|e| e.to_string())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn temp_cache(name: &str) -> DiskCache {
+    let path = std::env::temp_dir().join(format!("mscd-cache-{}-{}.json", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    DiskCache::new(path)
+  }
+
+  #[test]
+  fn returns_fresh_entries_only() {
+    let cache = temp_cache("ttl");
+    cache.put("artist:bicep", &vec!["house".to_string()], 1_000).unwrap();
+    let hit: Option<(Vec<String>, u64)> = cache.get("artist:bicep", 500, 1_400);
+    assert_eq!(hit, Some((vec!["house".to_string()], 1_000)));
+    let expired: Option<(Vec<String>, u64)> = cache.get("artist:bicep", 500, 1_600);
+    assert_eq!(expired, None);
+    let missing: Option<(Vec<String>, u64)> = cache.get("artist:other", 500, 1_400);
+    assert_eq!(missing, None);
+    let _ = fs::remove_file(&cache.path);
+  }
+
+  #[test]
+  fn drops_old_entries_on_write() {
+    let cache = temp_cache("prune");
+    cache.put("old", &1u32, 0).unwrap();
+    cache.put("new", &2u32, MAX_AGE_MS + 1).unwrap();
+    assert!(!cache.load().contains_key("old"));
+    assert!(cache.load().contains_key("new"));
+    let _ = fs::remove_file(&cache.path);
+  }
+}
@@ -0,0 +1,259 @@
+// Loopback server for the browser sign-in flow. It is started per sign-in on an
+// ephemeral port (passed to Last.fm as `cb`), answers with a small HTML page, and
+// stops once a callback completes sign-in or the pending request times out.
+
+use std::future::Future;
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+pub const CALLBACK_PATH: &str = "/callback";
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+const MAX_HEADERS: usize = 32;
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Parsed {
+  Partial,
+  Request {
+    method: String,
+    path: String,
+    query: Option<String>,
+  },
+  // HTTP status to answer with.
+  Invalid(u16),
+}
+
+pub fn parse_request(buf: &[u8]) -> Parsed {
+  let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+  let mut req = httparse::Request::new(&mut headers);
+  match req.parse(buf) {
+    Ok(httparse::Status::Partial) => Parsed::Partial,
+    Ok(httparse::Status::Complete(_)) => {
+      let (method, target) = match (req.method, req.path) {
+        (Some(m), Some(t)) => (m, t),
+        _ => return Parsed::Invalid(400),
+      };
+      let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path, Some(query.to_string())),
+        None => (target, None),
+      };
+      Parsed::Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query,
+      }
+    }
+    Err(httparse::Error::TooManyHeaders) => Parsed::Invalid(431),
+    Err(_) => Parsed::Invalid(400),
+  }
+}
+
+pub fn escape_html(s: &str) -> String {
+  s.replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+    .replace('\'', "&#39;")
+}
+
+pub fn page(ok: bool, heading: &str, detail: &str) -> String {
+  let accent = if ok { "#3ccf8e" } else { "#ff6b6b" };
+  format!(
+    r#"<!doctype html>
+<html><head><meta charset="utf-8"><title>MinimalSoundCloudDesktop</title>
+<style>
+  body {{ margin: 0; min-height: 100vh; display: flex; align-items: center; justify-content: center;
+    background: #0f131c; color: #e9ecf5; font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif; }}
+  .card {{ max-width: 420px; padding: 24px 28px; border-radius: 14px; background: rgba(255,255,255,0.04);
+    border: 1px solid rgba(255,255,255,0.12); border-top: 4px solid {accent}; box-shadow: 0 16px 30px rgba(0,0,0,0.35); }}
+  h1 {{ margin: 0 0 8px 0; font-size: 20px; }}
+  p {{ margin: 0; color: #b7bfd3; line-height: 1.4; }}
+</style></head>
+<body><div class="card"><h1>{heading}</h1><p>{detail}</p></div></body></html>
+"#,
+    accent = accent,
+    heading = escape_html(heading),
+    detail = escape_html(detail),
+  )
+}
+
+fn reason(status: u16) -> &'static str {
+  match status {
+    200 => "OK",
+    400 => "Bad Request",
+    403 => "Forbidden",
+    404 => "Not Found",
+    405 => "Method Not Allowed",
+    408 => "Request Timeout",
+    431 => "Request Header Fields Too Large",
+    _ => "Error",
+  }
+}
+
+fn response(status: u16, body: &str) -> String {
+  let allow = if status == 405 { "Allow: GET\r\n" } else { "" };
+  format!(
+    "HTTP/1.1 {} {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\n{}Cache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+    status,
+    reason(status),
+    body.len(),
+    allow,
+    body
+  )
+}
+
+async fn read_request(stream: &mut TcpStream) -> Parsed {
+  let mut buf = Vec::with_capacity(1024);
+  let mut chunk = [0u8; 1024];
+  loop {
+    let n = match tokio::time::timeout(READ_TIMEOUT, stream.read(&mut chunk)).await {
+      Ok(Ok(n)) => n,
+      Ok(Err(_)) => return Parsed::Invalid(400),
+      Err(_) => return Parsed::Invalid(408),
+    };
+    if n == 0 {
+      return Parsed::Invalid(400);
+    }
+    buf.extend_from_slice(&chunk[..n]);
+    match parse_request(&buf) {
+      Parsed::Partial if buf.len() >= MAX_REQUEST_BYTES => return Parsed::Invalid(431),
+      Parsed::Partial => continue,
+      parsed => return parsed,
+    }
+  }
+}
+
+/// Serves until `on_callback` succeeds (returns true) or `ttl` elapses (false).
+/// `on_callback` gets the full callback URL and returns the signed-in username.
+/// Failed callbacks get an error page but keep the server up, so a stray request
+/// can't end the real sign-in.
+pub async fn serve<F, Fut>(listener: TcpListener, ttl: Duration, on_callback: F) -> bool
+where
+  F: Fn(String) -> Fut,
+  Fut: Future<Output = Result<String, String>>,
+{
+  let base = match listener.local_addr() {
+    Ok(addr) => format!("http://{}{}", addr, CALLBACK_PATH),
+    Err(_) => return false,
+  };
+  let deadline = tokio::time::Instant::now() + ttl;
+  loop {
+    let mut stream = match tokio::time::timeout_at(deadline, listener.accept()).await {
+      Err(_) => return false,
+      Ok(Err(err)) => {
+        log::warn!("[Last.fm] Callback server accept failed: {}", err);
+        continue;
+      }
+      Ok(Ok((stream, _))) => stream,
+    };
+
+    let (status, body, done) = match read_request(&mut stream).await {
+      Parsed::Request { method, .. } if method != "GET" => {
+        (405, page(false, "Method not allowed", "This address only accepts the Last.fm redirect."), false)
+      }
+      Parsed::Request { path, .. } if path != CALLBACK_PATH => {
+        (404, page(false, "Not found", "This address only accepts the Last.fm redirect."), false)
+      }
+      Parsed::Request { query, .. } => {
+        let query = query.filter(|q| q.split('&').any(|pair| pair.starts_with("token=")));
+        match query {
+          None => (400, page(false, "Missing token", "Last.fm did not send a token. Try connecting again."), false),
+          Some(query) => match on_callback(format!("{}?{}", base, query)).await {
+            Ok(username) => (
+              200,
+              page(true, "Connected to Last.fm", &format!("Signed in as {}. You can close this tab.", username)),
+              true,
+            ),
+            Err(err) => (403, page(false, "Sign-in failed", &err), false),
+          },
+        }
+      }
+      Parsed::Invalid(status) => (status, page(false, "Bad request", "The request could not be read."), false),
+      Parsed::Partial => (400, page(false, "Bad request", "The request could not be read."), false),
+    };
+    let _ = stream.write_all(response(status, &body).as_bytes()).await;
+    let _ = stream.shutdown().await;
+    if done {
+      return true;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_complete_and_partial_requests() {
+    assert_eq!(
+      parse_request(b"GET /callback?state=abc&token=xyz HTTP/1.1\r\nHost: 127.0.0.1\r\n\r\n"),
+      Parsed::Request {
+        method: "GET".into(),
+        path: "/callback".into(),
+        query: Some("state=abc&token=xyz".into()),
+      }
+    );
+    assert_eq!(parse_request(b"GET /callback HTTP/1.1\r\nHost: 127."), Parsed::Partial);
+    assert_eq!(parse_request(b"\x00\x01garbage\r\n\r\n"), Parsed::Invalid(400));
+  }
+
+  #[test]
+  fn rejects_too_many_headers() {
+    let mut req = String::from("GET /callback HTTP/1.1\r\n");
+    for i in 0..(MAX_HEADERS + 1) {
+      req.push_str(&format!("X-H{}: v\r\n", i));
+    }
+    req.push_str("\r\n");
+    assert_eq!(parse_request(req.as_bytes()), Parsed::Invalid(431));
+  }
+
+  #[test]
+  fn pages_escape_messages() {
+    let html = page(false, "Sign-in failed", "<script>alert(1)</script>");
+    assert!(html.contains("&lt;script&gt;"));
+    assert!(!html.contains("<script>"));
+  }
+
+  async fn request(addr: std::net::SocketAddr, raw: &str) -> String {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(raw.as_bytes()).await.unwrap();
+    let mut out = String::new();
+    stream.read_to_string(&mut out).await.unwrap();
+    out
+  }
+
+  #[tokio::test]
+  async fn serves_until_a_callback_succeeds() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(serve(listener, Duration::from_secs(10), |url: String| async move {
+      if url.contains("state=good") {
+        Ok("alice".to_string())
+      } else {
+        Err("callback does not match the pending sign-in".to_string())
+      }
+    }));
+
+    let res = request(addr, "POST /callback HTTP/1.1\r\n\r\n").await;
+    assert!(res.starts_with("HTTP/1.1 405"));
+    let res = request(addr, "GET /favicon.ico HTTP/1.1\r\n\r\n").await;
+    assert!(res.starts_with("HTTP/1.1 404"));
+    let res = request(addr, "GET /callback?state=good HTTP/1.1\r\n\r\n").await;
+    assert!(res.starts_with("HTTP/1.1 400"));
+    let res = request(addr, "GET /callback?state=bad&token=t HTTP/1.1\r\n\r\n").await;
+    assert!(res.starts_with("HTTP/1.1 403"));
+    let res = request(addr, "GET /callback?state=good&token=t HTTP/1.1\r\nHost: x\r\n\r\n").await;
+    assert!(res.starts_with("HTTP/1.1 200"));
+    assert!(res.contains("Signed in as alice"));
+    assert!(server.await.unwrap());
+  }
+
+  #[tokio::test]
+  async fn stops_after_ttl() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let done = serve(listener, Duration::from_millis(50), |_url: String| async move { Ok(String::new()) }).await;
+    assert!(!done);
+  }
+}
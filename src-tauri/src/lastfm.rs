@@ -0,0 +1,688 @@
+// Last.fm API client and helpers for reading its JSON responses. The API wraps
+// single results in an object instead of a one-element array and nests text under
+// "#text", so parsing works on `serde_json::Value` rather than strict structs.
+
+use serde_json::Value;
+
+pub const API_URL: &str = "https://ws.audioscrobbler.com/2.0/";
+pub const AUTH_URL: &str = "https://www.last.fm/api/auth/";
+
+// Error codes callers act on; see https://www.last.fm/api/errorcodes.
+pub const ERROR_INVALID_SESSION: u64 = 9;
+pub const ERROR_TOKEN_UNAUTHORIZED: u64 = 14;
+
+/// Shared client for every Last.fm request: unsigned reads via `get`, signed
+/// writes via `call`. Cheap to clone; the underlying HTTP pool is shared.
+#[derive(Clone)]
+pub struct LastfmClient {
+  http: reqwest::Client,
+  api_key: String,
+  api_secret: Option<String>,
+}
+
+impl LastfmClient {
+  pub fn new(http: reqwest::Client, api_key: String, api_secret: Option<String>) -> Self {
+    Self { http, api_key, api_secret }
+  }
+
+  pub fn can_sign(&self) -> bool {
+    self.api_secret.is_some()
+  }
+
+  /// Adds method/api_key (and `sk` when given), then `api_sig` and `format`.
+  pub fn signed_params<'a>(
+    &self,
+    method: &str,
+    params: Vec<(&'a str, String)>,
+    sk: Option<&str>,
+  ) -> Result<Vec<(&'a str, String)>, String> {
+    let secret = self.api_secret.as_deref().ok_or("LASTFM_API_SECRET not set")?;
+    let mut params = params;
+    params.push(("method", method.to_string()));
+    params.push(("api_key", self.api_key.clone()));
+    if let Some(sk) = sk {
+      params.push(("sk", sk.to_string()));
+    }
+    let api_sig = sign(&params, secret);
+    params.push(("api_sig", api_sig));
+    params.push(("format", "json".to_string()));
+    Ok(params)
+  }
+
+  pub async fn get(&self, method: &str, params: Vec<(&str, String)>) -> Result<Value, String> {
+    let mut query = params;
+    query.push(("method", method.to_string()));
+    query.push(("api_key", self.api_key.clone()));
+    query.push(("format", "json".to_string()));
+    self.send(method, self.http.get(API_URL).query(&query)).await
+  }
+
+  // Signed read without a session (auth.getSession).
+  pub async fn get_signed(&self, method: &str, params: Vec<(&str, String)>) -> Result<Value, String> {
+    let query = self.signed_params(method, params, None)?;
+    self.send(method, self.http.get(API_URL).query(&query)).await
+  }
+
+  // Like `get_signed`, but hands Last.fm error bodies back so the caller can act on
+  // the error code. Only transport failures are errors.
+  pub async fn get_signed_raw(
+    &self,
+    method: &str,
+    params: Vec<(&str, String)>,
+    sk: Option<&str>,
+  ) -> Result<Value, String> {
+    let query = self.signed_params(method, params, sk)?;
+    let res = self
+      .http
+      .get(API_URL)
+      .query(&query)
+      .send()
+      .await
+      .map_err(|e| e.to_string())?;
+    res.json().await.map_err(|e| e.to_string())
+  }
+
+  /// Page where the user approves a desktop-flow token (`auth.getToken`).
+  pub fn desktop_auth_url(&self, token: &str) -> String {
+    let mut url = url::Url::parse(AUTH_URL).expect("valid auth url");
+    url
+      .query_pairs_mut()
+      .append_pair("api_key", &self.api_key)
+      .append_pair("token", token);
+    url.to_string()
+  }
+
+  // Signed POST without a session (auth.getMobileSession), keeping credentials out of the URL.
+  pub async fn post_signed(&self, method: &str, params: Vec<(&str, String)>) -> Result<Value, String> {
+    let form = self.signed_params(method, params, None)?;
+    self.send(method, self.http.post(API_URL).form(&form)).await
+  }
+
+  pub async fn call(&self, method: &str, params: Vec<(&str, String)>, sk: &str) -> Result<Value, String> {
+    let form = self.signed_params(method, params, Some(sk))?;
+    self.send(method, self.http.post(API_URL).form(&form)).await
+  }
+
+  async fn send(&self, method: &str, request: reqwest::RequestBuilder) -> Result<Value, String> {
+    let res = request.send().await.map_err(|e| e.to_string())?;
+    let status = res.status();
+    let body: Value = res.json().await.unwrap_or(Value::Null);
+    if let Some(message) = error_message(&body) {
+      return Err(format!("Last.fm call {} failed: {}", method, message));
+    }
+    if !status.is_success() {
+      return Err(format!("Last.fm call {} failed: {}", method, status));
+    }
+    Ok(body)
+  }
+}
+
+/// md5 over the sorted `keyvalue` pairs followed by the secret. Callers must leave
+/// `format` and `callback` out, as Last.fm does.
+pub fn sign(params: &[(&str, String)], api_secret: &str) -> String {
+  let mut sorted: Vec<&(&str, String)> = params.iter().collect();
+  sorted.sort_by_key(|(k, _)| *k);
+  let mut base = String::new();
+  for (k, v) in sorted {
+    base.push_str(k);
+    base.push_str(v);
+  }
+  base.push_str(api_secret);
+  format!("{:x}", md5::compute(base.as_bytes()))
+}
+
+/// One play in a `track.scrobble` batch.
+pub struct ScrobblePlay<'a> {
+  pub artist: &'a str,
+  pub title: &'a str,
+  pub album: Option<&'a str>,
+  pub duration_ms: u64,
+  // Unix seconds.
+  pub timestamp: u64,
+}
+
+/// Indexed `track.scrobble` params (`artist[i]`, `track[i]`, ...). Last.fm takes
+/// durations in seconds; `album[i]` is sent only when known.
+pub fn scrobble_params(plays: &[ScrobblePlay]) -> Vec<(String, String)> {
+  let mut owned: Vec<(String, String)> = Vec::new();
+  for (i, play) in plays.iter().enumerate() {
+    owned.push((format!("track[{}]", i), play.title.to_string()));
+    owned.push((format!("artist[{}]", i), play.artist.to_string()));
+    owned.push((format!("duration[{}]", i), (play.duration_ms / 1000).to_string()));
+    owned.push((format!("timestamp[{}]", i), play.timestamp.to_string()));
+    if let Some(album) = play.album.filter(|a| !a.is_empty()) {
+      owned.push((format!("album[{}]", i), album.to_string()));
+    }
+  }
+  owned
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RecentTrack {
+  pub artist: String,
+  pub title: String,
+  #[serde(default)]
+  pub album: Option<String>,
+  // Absent for the "now playing" entry.
+  #[serde(default)]
+  pub timestamp: Option<u64>,
+  #[serde(default)]
+  pub now_playing: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct UserInfo {
+  pub name: String,
+  pub realname: Option<String>,
+  pub playcount: u64,
+  // Unix seconds.
+  pub registered: Option<u64>,
+  pub avatar: Option<String>,
+  pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct TopArtist {
+  pub name: String,
+  pub playcount: u64,
+  #[serde(default)]
+  pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ArtistInfo {
+  pub name: String,
+  pub url: Option<String>,
+  pub image: Option<String>,
+  pub listeners: u64,
+  pub playcount: u64,
+  // Only present when the request names a user.
+  pub user_playcount: Option<u64>,
+  pub bio_summary: Option<String>,
+  pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct LovedTrack {
+  pub artist: String,
+  pub title: String,
+  // Unix seconds.
+  #[serde(default)]
+  pub loved_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SimilarArtist {
+  pub name: String,
+  // Last.fm's similarity score, 0.0..=1.0.
+  #[serde(default)]
+  pub score: f64,
+  #[serde(default)]
+  pub url: Option<String>,
+}
+
+/// Returns the `message` of a Last.fm error body (`{"error": 9, "message": "..."}`).
+pub fn error_message(body: &Value) -> Option<String> {
+  body.get("error")?;
+  Some(
+    body
+      .get("message")
+      .and_then(Value::as_str)
+      .unwrap_or("unknown error")
+      .to_string(),
+  )
+}
+
+pub fn error_code(body: &Value) -> Option<u64> {
+  number(body.get("error"))
+}
+
+pub fn as_list(value: Option<&Value>) -> Vec<&Value> {
+  match value {
+    Some(Value::Array(items)) => items.iter().collect(),
+    Some(v @ Value::Object(_)) => vec![v],
+    _ => Vec::new(),
+  }
+}
+
+// Text fields appear either as plain strings or as {"#text": "..."} objects.
+pub fn text(value: Option<&Value>) -> Option<String> {
+  let s = match value? {
+    Value::String(s) => s.as_str(),
+    Value::Object(map) => map
+      .get("#text")
+      .or_else(|| map.get("name"))
+      .and_then(Value::as_str)?,
+    _ => return None,
+  };
+  let s = s.trim();
+  if s.is_empty() {
+    None
+  } else {
+    Some(s.to_string())
+  }
+}
+
+// Numbers are usually serialized as strings ("playcount": "123").
+pub fn number(value: Option<&Value>) -> Option<u64> {
+  match value? {
+    Value::String(s) => s.trim().parse().ok(),
+    Value::Number(n) => n.as_u64(),
+    _ => None,
+  }
+}
+
+pub fn total_pages(container: Option<&Value>) -> u32 {
+  container
+    .and_then(|c| number(c.get("@attr").and_then(|a| a.get("totalPages"))))
+    .unwrap_or(1) as u32
+}
+
+// Image lists run small → extralarge; take the largest one that is set.
+pub fn largest_image(value: Option<&Value>) -> Option<String> {
+  as_list(value).into_iter().rev().find_map(|img| text(Some(img)))
+}
+
+pub fn parse_user_info(body: &Value) -> Option<UserInfo> {
+  let user = body.get("user")?;
+  Some(UserInfo {
+    name: text(user.get("name"))?,
+    realname: text(user.get("realname")),
+    playcount: number(user.get("playcount")).unwrap_or(0),
+    registered: user
+      .get("registered")
+      .and_then(|r| number(r.get("unixtime")).or_else(|| number(r.get("#text")))),
+    avatar: largest_image(user.get("image")),
+    url: text(user.get("url")),
+  })
+}
+
+pub fn parse_top_artists(body: &Value) -> Vec<TopArtist> {
+  as_list(body.get("topartists").and_then(|c| c.get("artist")))
+    .into_iter()
+    .filter_map(|a| {
+      Some(TopArtist {
+        name: text(a.get("name"))?,
+        playcount: number(a.get("playcount")).unwrap_or(0),
+        url: text(a.get("url")),
+      })
+    })
+    .collect()
+}
+
+// Bio summaries are HTML with a trailing "Read more on Last.fm" link.
+pub fn strip_html(html: &str) -> String {
+  let mut out = String::with_capacity(html.len());
+  let mut in_tag = false;
+  for c in html.chars() {
+    match c {
+      '<' => in_tag = true,
+      '>' if in_tag => in_tag = false,
+      _ if !in_tag => out.push(c),
+      _ => {}
+    }
+  }
+  let out = out
+    .replace("&amp;", "&")
+    .replace("&quot;", "\"")
+    .replace("&#39;", "'")
+    .replace("&lt;", "<")
+    .replace("&gt;", ">");
+  let out = out.trim();
+  out
+    .strip_suffix("Read more on Last.fm")
+    .unwrap_or(out)
+    .trim_end()
+    .to_string()
+}
+
+/// Combines `artist.getInfo` and `artist.getTopTags`; tags fall back to the ones
+/// embedded in getInfo when the top-tags call returned nothing.
+pub fn parse_artist_info(info: &Value, top_tags: &Value, max_tags: usize) -> Option<ArtistInfo> {
+  let artist = info.get("artist")?;
+  let stats = artist.get("stats");
+  let mut tags: Vec<String> = as_list(top_tags.get("toptags").and_then(|t| t.get("tag")))
+    .into_iter()
+    .filter_map(|t| text(t.get("name")))
+    .take(max_tags)
+    .collect();
+  if tags.is_empty() {
+    tags = as_list(artist.get("tags").and_then(|t| t.get("tag")))
+      .into_iter()
+      .filter_map(|t| text(t.get("name")))
+      .take(max_tags)
+      .collect();
+  }
+  Some(ArtistInfo {
+    name: text(artist.get("name"))?,
+    url: text(artist.get("url")),
+    image: largest_image(artist.get("image")),
+    listeners: number(stats.and_then(|s| s.get("listeners"))).unwrap_or(0),
+    playcount: number(stats.and_then(|s| s.get("playcount"))).unwrap_or(0),
+    user_playcount: number(stats.and_then(|s| s.get("userplaycount"))),
+    bio_summary: text(artist.get("bio").and_then(|b| b.get("summary")))
+      .map(|s| strip_html(&s))
+      .filter(|s| !s.is_empty()),
+    tags,
+  })
+}
+
+/// Tag names from `track.getTags` (`tags.tag`) or any `*.getTopTags` (`toptags.tag`).
+pub fn parse_tags(body: &Value) -> Vec<String> {
+  let container = body.get("tags").or_else(|| body.get("toptags"));
+  as_list(container.and_then(|c| c.get("tag")))
+    .into_iter()
+    .filter_map(|t| text(t.get("name")))
+    .collect()
+}
+
+/// Trims, lowercases and dedupes user-entered tags. Commas would split a tag in
+/// `track.addTags`, so they are dropped.
+pub fn clean_tags<I, S>(tags: I) -> Vec<String>
+where
+  I: IntoIterator<Item = S>,
+  S: AsRef<str>,
+{
+  let mut out: Vec<String> = Vec::new();
+  for tag in tags {
+    let tag = tag
+      .as_ref()
+      .replace(',', " ")
+      .split_whitespace()
+      .collect::<Vec<_>>()
+      .join(" ")
+      .to_lowercase();
+    if !tag.is_empty() && !out.contains(&tag) {
+      out.push(tag);
+    }
+  }
+  out
+}
+
+/// Parses a `user.getLovedTracks` page into tracks and the page count.
+pub fn parse_loved_tracks(body: &Value) -> (Vec<LovedTrack>, u32) {
+  let container = body.get("lovedtracks");
+  let tracks = as_list(container.and_then(|c| c.get("track")))
+    .into_iter()
+    .filter_map(|t| {
+      Some(LovedTrack {
+        artist: text(t.get("artist"))?,
+        title: text(t.get("name"))?,
+        loved_at: number(t.get("date").and_then(|d| d.get("uts"))),
+      })
+    })
+    .collect();
+  (tracks, total_pages(container))
+}
+
+/// Parses `artist.getSimilar`, best match first.
+pub fn parse_similar_artists(body: &Value) -> Vec<SimilarArtist> {
+  let mut similar: Vec<SimilarArtist> = as_list(body.get("similarartists").and_then(|c| c.get("artist")))
+    .into_iter()
+    .filter_map(|a| {
+      Some(SimilarArtist {
+        name: text(a.get("name"))?,
+        score: match a.get("match") {
+          Some(Value::String(s)) => s.trim().parse().unwrap_or(0.0),
+          Some(Value::Number(n)) => n.as_f64().unwrap_or(0.0),
+          _ => 0.0,
+        },
+        url: text(a.get("url")),
+      })
+    })
+    .collect();
+  similar.sort_by(|a, b| b.score.total_cmp(&a.score));
+  similar
+}
+
+/// Parses a `user.getRecentTracks` response into tracks (newest first) and the page count.
+pub fn parse_recent_tracks(body: &Value) -> (Vec<RecentTrack>, u32) {
+  let container = body.get("recenttracks");
+  let tracks = as_list(container.and_then(|c| c.get("track")))
+    .into_iter()
+    .filter_map(|t| {
+      Some(RecentTrack {
+        artist: text(t.get("artist"))?,
+        title: text(t.get("name"))?,
+        album: text(t.get("album")),
+        timestamp: number(t.get("date").and_then(|d| d.get("uts"))),
+        now_playing: t
+          .get("@attr")
+          .and_then(|a| a.get("nowplaying"))
+          .and_then(Value::as_str)
+          == Some("true"),
+      })
+    })
+    .collect();
+  (tracks, total_pages(container))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  #[test]
+  fn builds_desktop_auth_url() {
+    let client = LastfmClient::new(reqwest::Client::new(), "key".into(), None);
+    assert_eq!(
+      client.desktop_auth_url("tok en"),
+      "https://www.last.fm/api/auth/?api_key=key&token=tok+en"
+    );
+  }
+
+  #[test]
+  fn signs_sorted_params() {
+    let params = vec![("method", "track.love".to_string()), ("api_key", "k".to_string()), ("sk", "s".to_string())];
+    let expected = format!("{:x}", md5::compute("api_keykmethodtrack.loveskssecret".as_bytes()));
+    assert_eq!(sign(&params, "secret"), expected);
+  }
+
+  #[test]
+  fn builds_indexed_scrobble_params() {
+    let plays = [
+      ScrobblePlay { artist: "Bicep", title: "Glue", album: Some("Bicep"), duration_ms: 269_500, timestamp: 1_700_000_000 },
+      ScrobblePlay { artist: "Bicep", title: "Atlas", album: Some(""), duration_ms: 0, timestamp: 1_700_000_300 },
+    ];
+    let params = scrobble_params(&plays);
+    let get = |k: &str| params.iter().find(|(key, _)| key == k).map(|(_, v)| v.as_str());
+    assert_eq!(get("track[0]"), Some("Glue"));
+    assert_eq!(get("duration[0]"), Some("269"));
+    assert_eq!(get("timestamp[0]"), Some("1700000000"));
+    assert_eq!(get("album[0]"), Some("Bicep"));
+    assert_eq!(get("track[1]"), Some("Atlas"));
+    assert_eq!(get("duration[1]"), Some("0"));
+    assert_eq!(get("album[1]"), None);
+  }
+
+  #[test]
+  fn signed_params_exclude_format_from_signature() {
+    let client = LastfmClient::new(reqwest::Client::new(), "k".into(), Some("secret".into()));
+    let params = client.signed_params("track.love", vec![("artist", "A".to_string())], Some("s")).unwrap();
+    let sig = params.iter().find(|(k, _)| *k == "api_sig").unwrap().1.clone();
+    let expected = format!("{:x}", md5::compute("api_keykartistAmethodtrack.loveskssecret".as_bytes()));
+    assert_eq!(sig, expected);
+    assert_eq!(params.last().unwrap(), &("format", "json".to_string()));
+
+    let unsigned = LastfmClient::new(reqwest::Client::new(), "k".into(), None);
+    assert!(unsigned.signed_params("track.love", Vec::new(), Some("s")).is_err());
+  }
+
+  #[test]
+  fn parses_recent_tracks_array_and_now_playing() {
+    let body = json!({
+      "recenttracks": {
+        "track": [
+          {
+            "artist": { "mbid": "", "#text": "Bicep" },
+            "name": "Glue",
+            "album": { "#text": "" },
+            "@attr": { "nowplaying": "true" }
+          },
+          {
+            "artist": { "#text": "Overmono" },
+            "name": "So U Kno",
+            "album": { "#text": "Good Lies" },
+            "date": { "uts": "1700000000", "#text": "14 Nov 2023, 22:13" }
+          }
+        ],
+        "@attr": { "user": "someone", "totalPages": "3", "page": "1" }
+      }
+    });
+    let (tracks, pages) = parse_recent_tracks(&body);
+    assert_eq!(pages, 3);
+    assert_eq!(tracks.len(), 2);
+    assert!(tracks[0].now_playing);
+    assert_eq!(tracks[0].timestamp, None);
+    assert_eq!(tracks[0].album, None);
+    assert_eq!(tracks[1].timestamp, Some(1_700_000_000));
+    assert_eq!(tracks[1].album.as_deref(), Some("Good Lies"));
+  }
+
+  #[test]
+  fn parses_single_object_track() {
+    let body = json!({
+      "recenttracks": {
+        "track": { "artist": { "#text": "A" }, "name": "B", "date": { "uts": "5" } },
+        "@attr": { "totalPages": "1" }
+      }
+    });
+    let (tracks, pages) = parse_recent_tracks(&body);
+    assert_eq!(pages, 1);
+    assert_eq!(tracks[0].title, "B");
+  }
+
+  #[test]
+  fn parses_user_info() {
+    let body = json!({
+      "user": {
+        "name": "someone",
+        "realname": "",
+        "playcount": "48213",
+        "registered": { "unixtime": "1100000000", "#text": 1100000000 },
+        "image": [
+          { "size": "small", "#text": "https://img/s.png" },
+          { "size": "extralarge", "#text": "https://img/xl.png" }
+        ],
+        "url": "https://www.last.fm/user/someone"
+      }
+    });
+    let info = parse_user_info(&body).unwrap();
+    assert_eq!(info.name, "someone");
+    assert_eq!(info.realname, None);
+    assert_eq!(info.playcount, 48_213);
+    assert_eq!(info.registered, Some(1_100_000_000));
+    assert_eq!(info.avatar.as_deref(), Some("https://img/xl.png"));
+  }
+
+  #[test]
+  fn parses_top_artists() {
+    let body = json!({
+      "topartists": {
+        "artist": [
+          { "name": "Bicep", "playcount": "31", "url": "https://www.last.fm/music/Bicep", "@attr": { "rank": "1" } },
+          { "name": "Overmono", "playcount": "12" }
+        ]
+      }
+    });
+    let artists = parse_top_artists(&body);
+    assert_eq!(artists.len(), 2);
+    assert_eq!(artists[0].playcount, 31);
+    assert_eq!(artists[1].url, None);
+  }
+
+  #[test]
+  fn parses_artist_info_with_top_tags() {
+    let info = json!({
+      "artist": {
+        "name": "Bicep",
+        "url": "https://www.last.fm/music/Bicep",
+        "stats": { "listeners": "812345", "playcount": "21000000", "userplaycount": "412" },
+        "bio": { "summary": "Bicep are a Belfast-born duo &amp; DJs. <a href=\"https://www.last.fm/music/Bicep\">Read more on Last.fm</a>" },
+        "tags": { "tag": [{ "name": "fallback" }] }
+      }
+    });
+    let tags = json!({ "toptags": { "tag": [{ "name": "electronic", "count": 100 }, { "name": "house" }, { "name": "idm" }] } });
+    let artist = parse_artist_info(&info, &tags, 2).unwrap();
+    assert_eq!(artist.listeners, 812_345);
+    assert_eq!(artist.user_playcount, Some(412));
+    assert_eq!(artist.bio_summary.as_deref(), Some("Bicep are a Belfast-born duo & DJs."));
+    assert_eq!(artist.tags, vec!["electronic", "house"]);
+
+    let artist = parse_artist_info(&info, &json!({ "toptags": {} }), 5).unwrap();
+    assert_eq!(artist.tags, vec!["fallback"]);
+  }
+
+  #[test]
+  fn parses_user_and_top_tags() {
+    let user_tags = json!({
+      "tags": { "tag": { "name": "deep house", "url": "https://www.last.fm/tag/deep+house" }, "@attr": {} }
+    });
+    assert_eq!(parse_tags(&user_tags), vec!["deep house"]);
+    let top = json!({ "toptags": { "tag": [{ "name": "rock", "count": 100 }, { "name": "electronic" }] } });
+    assert_eq!(parse_tags(&top), vec!["rock", "electronic"]);
+    // Untagged tracks come back as a bare "#text" node.
+    assert!(parse_tags(&json!({ "tags": { "#text": "\n", "@attr": {} } })).is_empty());
+  }
+
+  #[test]
+  fn cleans_user_tags() {
+    assert_eq!(
+      clean_tags(["  Deep   House ", "deep house", "", "uk, garage"]),
+      vec!["deep house", "uk garage"]
+    );
+  }
+
+  #[test]
+  fn parses_loved_tracks() {
+    let body = json!({
+      "lovedtracks": {
+        "track": {
+          "name": "Glue",
+          "artist": { "name": "Bicep", "url": "https://www.last.fm/music/Bicep" },
+          "date": { "uts": "1700000000", "#text": "14 Nov 2023, 22:13" }
+        },
+        "@attr": { "page": "1", "totalPages": "3" }
+      }
+    });
+    let (tracks, pages) = parse_loved_tracks(&body);
+    assert_eq!(pages, 3);
+    assert_eq!(
+      tracks,
+      vec![LovedTrack { artist: "Bicep".into(), title: "Glue".into(), loved_at: Some(1_700_000_000) }]
+    );
+  }
+
+  #[test]
+  fn parses_similar_artists_sorted_by_match() {
+    let body = json!({
+      "similarartists": {
+        "artist": [
+          { "name": "Overmono", "match": "0.71", "url": "https://www.last.fm/music/Overmono" },
+          { "name": "Ross From Friends", "match": "1" },
+          { "name": "", "match": "0.9" }
+        ],
+        "@attr": { "artist": "Bicep" }
+      }
+    });
+    let similar = parse_similar_artists(&body);
+    let names: Vec<&str> = similar.iter().map(|a| a.name.as_str()).collect();
+    assert_eq!(names, vec!["Ross From Friends", "Overmono"]);
+    assert_eq!(similar[1].score, 0.71);
+    assert!(parse_similar_artists(&json!({ "similarartists": { "artist": [] } })).is_empty());
+  }
+
+  #[test]
+  fn reads_error_bodies() {
+    let body = json!({ "error": 9, "message": "Invalid session key - Please re-authenticate" });
+    assert_eq!(
+      error_message(&body).as_deref(),
+      Some("Invalid session key - Please re-authenticate")
+    );
+    assert_eq!(error_code(&body), Some(9));
+    assert_eq!(error_message(&json!({ "recenttracks": {} })), None);
+    assert_eq!(error_code(&json!({ "token": "abc" })), None);
+  }
+}
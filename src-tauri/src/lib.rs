@@ -1,9 +1,10 @@
 use std::fs;
 use std::sync::Arc;
 use std::sync::Mutex;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use tauri::Manager;
+use tauri::webview::PageLoadEvent;
+use tauri::{Emitter, Manager};
 use tauri_plugin_notification::NotificationExt;
 use serde::Deserialize;
 use tauri_plugin_deep_link::DeepLinkExt;
@@ -11,9 +12,70 @@ use tauri_plugin_opener::OpenerExt;
 use std::path::PathBuf;
 use url::Url;
 
+mod auth;
+mod backfill;
+mod cache;
+mod callback_server;
+mod http;
+mod lastfm;
+mod local_server;
+mod love_sync;
+mod mqtt;
+mod now_playing;
+mod scrobble_log;
+mod tracklist;
+mod webhooks;
+
+use auth::PendingAuth;
+use backfill::HistoryPlay;
+use cache::DiskCache;
+use hyper::body::Bytes;
+use lastfm::{ArtistInfo, LastfmClient, LovedTrack, SimilarArtist};
+use local_server::{Api, Control, EventHub, Reply, Route, ServerEvent};
+use love_sync::LikedTrack;
+use mqtt::{MqttConfig, NowState, PlayerState};
+use now_playing::{Playing, WidgetTheme};
+use scrobble_log::{SubmissionStatus, SubmittedScrobble};
+use tracklist::MixProgress;
+use webhooks::{Webhook, WebhookEvent};
+
 const STORE_PATH: &str = "lastfm.json";
-const DEV_CALLBACK_URL: &str = "http://127.0.0.1:35729/callback";
+const CACHE_PATH: &str = "lastfm-cache.json";
 const DEFAULT_THRESHOLD: f32 = 0.5;
+const DESKTOP_AUTH_POLL_SECS: u64 = 3;
+const DESKTOP_AUTH_TIMEOUT_SECS: u64 = 5 * 60;
+const VERIFY_INTERVAL_SECS: u64 = 10 * 60;
+const SESSION_CHECK_INTERVAL_SECS: u64 = 6 * 60 * 60;
+// Toasts kept while no overlay is connected to the event stream.
+const TOAST_BACKLOG_LIMIT: usize = 20;
+// The overlay reports every 2 s while the page is open.
+const OVERLAY_ALIVE_MS: u64 = 15 * 1000;
+// Fixed so scripts and stream decks can be set up once; changeable in settings.
+const DEFAULT_CONTROL_PORT: u16 = 41530;
+// Catches the overlay going silent, which no playback report announces.
+const MQTT_REFRESH_SECS: u64 = 5;
+// How long a stopping client gets to publish "offline" and disconnect.
+const MQTT_STOP_SECS: u64 = 5;
+const VERIFY_MAX_PAGES: u32 = 5;
+const BACKFILL_MAX_PAGES: u32 = 10;
+const PROFILE_MAX_AGE_MS: u64 = 15 * 60 * 1000;
+const PROFILE_RECENT_LIMIT: usize = 20;
+const ARTIST_CACHE_TTL_MS: u64 = 12 * 60 * 60 * 1000;
+const ARTIST_MAX_TAGS: usize = 8;
+const SIMILAR_LIMIT: usize = 12;
+const RECENT_SUGGESTIONS_LIMIT: usize = 20;
+const TAG_SUGGESTION_LIMIT: usize = 20;
+const TOP_TAGS_TTL_MS: u64 = 24 * 60 * 60 * 1000;
+// track.addTags accepts at most 10 tags per request.
+const MAX_TAGS_PER_CALL: usize = 10;
+const LOVED_PAGE_SIZE: u32 = 500;
+const LOVED_MAX_PAGES: u32 = 20;
+// track.love is one request per track; spread a first sync over several runs.
+const LOVE_SYNC_MAX_PER_RUN: usize = 100;
+const LOVE_SYNC_DELAY_MS: u64 = 250;
+const LOVE_SYNC_SYNCED_LIMIT: usize = 5000;
+// Catch Last.fm plays stamped slightly before the oldest SoundCloud play.
+const BACKFILL_MARGIN_SECS: u64 = 10 * 60;
 
 #[derive(Debug, Deserialize)]
 struct LocalLastfmConfig {
@@ -24,6 +86,11 @@ struct LocalLastfmConfig {
 }
 
 fn load_lastfm_config() -> Option<LocalLastfmConfig> {
+  find_lastfm_config().map(|(_, cfg)| cfg)
+}
+
+// The first readable lastfm.local.json and where it was found.
+fn find_lastfm_config() -> Option<(PathBuf, LocalLastfmConfig)> {
   let mut candidates = Vec::new();
 
   if let Ok(p) = std::env::var("LASTFM_CONFIG") {
@@ -45,7 +112,7 @@ fn load_lastfm_config() -> Option<LocalLastfmConfig> {
     if path.exists() {
       if let Ok(text) = std::fs::read_to_string(&path) {
         if let Ok(cfg) = serde_json::from_str::<LocalLastfmConfig>(&text) {
-          return Some(cfg);
+          return Some((path, cfg));
         }
       }
     }
@@ -70,24 +137,101 @@ fn lastfm_secret() -> Option<String> {
   })
 }
 
-fn lastfm_callback() -> String {
+// Where an effective Last.fm setting came from, in lookup order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ConfigSource {
+  Env,
+  LocalFile,
+  CompiledIn,
+  Default,
+  Missing,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct ConfigSources {
+  api_key: ConfigSource,
+  api_secret: ConfigSource,
+  callback: ConfigSource,
+  local_file: Option<String>,
+}
+
+// Mirrors lastfm_key/lastfm_secret/lastfm_callback without exposing the values.
+fn config_sources() -> ConfigSources {
+  let local = find_lastfm_config();
+  let source = |var: &str, in_file: bool, compiled: bool| {
+    if std::env::var(var).is_ok() {
+      ConfigSource::Env
+    } else if in_file {
+      ConfigSource::LocalFile
+    } else if compiled {
+      ConfigSource::CompiledIn
+    } else {
+      ConfigSource::Missing
+    }
+  };
+  let file_callback = local.as_ref().is_some_and(|(_, c)| c.callback.is_some());
+  let callback = if cfg!(debug_assertions) {
+    if file_callback {
+      ConfigSource::LocalFile
+    } else {
+      ConfigSource::Default
+    }
+  } else {
+    match source("LASTFM_CALLBACK", file_callback, option_env!("LASTFM_CALLBACK").is_some()) {
+      ConfigSource::Missing => ConfigSource::Default,
+      other => other,
+    }
+  };
+  ConfigSources {
+    api_key: source("LASTFM_API_KEY", local.is_some(), option_env!("LASTFM_API_KEY").is_some()),
+    api_secret: source("LASTFM_API_SECRET", local.is_some(), option_env!("LASTFM_API_SECRET").is_some()),
+    callback,
+    local_file: local.map(|(path, _)| path.display().to_string()),
+  }
+}
+
+fn lastfm_client(app: &tauri::AppHandle) -> Result<LastfmClient, String> {
+  let api_key = lastfm_key().ok_or("LASTFM_API_KEY not set")?;
+  let http = app.state::<reqwest::Client>().inner().clone();
+  Ok(LastfmClient::new(http, api_key, lastfm_secret()))
+}
+
+// Client for the configured proxy. An invalid proxy is logged and ignored so the
+// app stays online through the environment's proxy settings instead.
+fn build_http_client(app: &tauri::AppHandle, proxy: Option<&str>) -> Result<reqwest::Client, String> {
+  let version = app.package_info().version.to_string();
+  http::build_client(&version, proxy).or_else(|err| {
+    log::warn!("[HTTP] Ignoring proxy setting: {}", err);
+    http::build_client(&version, None)
+  })
+}
+
+// Configured callback URL. `None` (debug builds without one) means the loopback
+// callback server is used instead.
+fn lastfm_callback() -> Option<String> {
   #[cfg(debug_assertions)]
   {
-    load_lastfm_config()
-      .and_then(|c| c.callback)
-      .unwrap_or_else(|| DEV_CALLBACK_URL.to_string())
+    load_lastfm_config().and_then(|c| c.callback)
   }
   #[cfg(not(debug_assertions))]
   {
-    std::env::var("LASTFM_CALLBACK").ok().or_else(|| {
+    Some(std::env::var("LASTFM_CALLBACK").ok().or_else(|| {
       load_lastfm_config()
         .and_then(|c| c.callback)
         .or_else(|| option_env!("LASTFM_CALLBACK").map(|s| s.to_string()))
-    }).unwrap_or_else(|| "mscd://lastfm-callback".to_string())
+    }).unwrap_or_else(|| "mscd://lastfm-callback".to_string()))
   }
 }
 
-fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str, playback_url: &str, initial_settings: &str) -> String {
+fn build_overlay_script(
+  lastfm_key: &str,
+  lastfm_callback: &str,
+  version: &str,
+  playback_url: &str,
+  server_token: &str,
+  initial_settings: &str,
+) -> String {
   let auth_url = format!(
     "https://www.last.fm/api/auth/?api_key={}&cb={}",
     lastfm_key, lastfm_callback
@@ -115,6 +259,8 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
         try {
         console.info('[MSCD] Injecting overlay');
         const endpoint = '{playback_url}';
+        // Per-launch secret the local server requires on every request.
+        const serverHeaders = { 'X-MSCD-Token': '{server_token}' };
         const host = document.createElement('div');
         host.id = 'mscd-overlay-host';
         host.style.position = 'fixed';
@@ -209,6 +355,99 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
           .modal header { display: flex; justify-content: space-between; align-items: center; gap: 8px; }
           .close { height: 32px; padding: 0 10px; }
           .warning { color: #ffb95f; font-size: 12px; }
+          .scroll-list {
+            max-height: 220px;
+            overflow-y: auto;
+            display: flex;
+            flex-direction: column;
+            gap: 4px;
+            margin-top: 8px;
+          }
+          .check-row { display: flex; align-items: center; gap: 8px; font-size: 13px; }
+          .check-row input { accent-color: #3c57ff; }
+          .profile { display: flex; flex-direction: column; gap: 8px; margin-top: 10px; }
+          .profile-head { display: flex; align-items: center; gap: 10px; }
+          .avatar {
+            width: 48px;
+            height: 48px;
+            border-radius: 50%;
+            object-fit: cover;
+            background: #1b202b;
+            flex: none;
+          }
+          .profile-cols { display: grid; grid-template-columns: 1fr 1fr; gap: 10px; }
+          .profile-cols h4 { margin: 0 0 4px 0; font-size: 13px; }
+          .list-item { font-size: 12px; white-space: nowrap; overflow: hidden; text-overflow: ellipsis; }
+          .side-panel {
+            position: fixed;
+            top: 60px;
+            right: 16px;
+            width: 300px;
+            max-height: calc(100vh - 90px);
+            overflow-y: auto;
+            pointer-events: auto;
+            background: rgba(15, 19, 28, 0.97);
+            border: 1px solid rgba(255,255,255,0.12);
+            border-radius: 12px;
+            box-shadow: 0 16px 30px rgba(0,0,0,0.35);
+            color: #e9ecf5;
+            font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif;
+            font-size: 13px;
+          }
+          .side-panel header {
+            display: flex;
+            justify-content: space-between;
+            align-items: center;
+            gap: 8px;
+            padding: 10px 12px;
+          }
+          .side-panel .panel-body { padding: 0 12px 12px 12px; display: flex; flex-direction: column; gap: 8px; }
+          .side-panel a { color: #9fb0ff; text-decoration: none; font-weight: 700; }
+          .tags { display: flex; flex-wrap: wrap; gap: 4px; }
+          .suggestion {
+            cursor: pointer;
+            display: flex;
+            justify-content: space-between;
+            gap: 8px;
+          }
+          .suggestion:hover { color: #9fb0ff; }
+          .tag.clickable { cursor: pointer; }
+          .tag.clickable:hover { border-color: rgba(159, 176, 255, 0.8); }
+          .side-panel input[type="text"] {
+            background: rgba(255,255,255,0.06);
+            color: inherit;
+            border: 1px solid rgba(255,255,255,0.16);
+            border-radius: 8px;
+            padding: 6px 8px;
+          }
+          .tag {
+            padding: 2px 8px;
+            border-radius: 999px;
+            background: rgba(60, 87, 255, 0.18);
+            border: 1px solid rgba(60, 87, 255, 0.35);
+            font-size: 11px;
+          }
+          .field {
+            height: 30px;
+            min-width: 180px;
+            padding: 4px 8px;
+            border-radius: 8px;
+            border: 1px solid rgba(255,255,255,0.16);
+            background: #1b202b;
+            color: #e9ecf5;
+          }
+          .code {
+            margin: 8px 0 0 0;
+            padding: 8px;
+            max-height: 180px;
+            overflow: auto;
+            border-radius: 8px;
+            background: rgba(0,0,0,0.35);
+            color: #c9d1e6;
+            font-size: 12px;
+            white-space: pre-wrap;
+            word-break: break-all;
+          }
           .toast-container {
             position: fixed;
             top: 60px;
@@ -257,6 +496,26 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
         let settingsOpen = false;
         let darkMode = true;
 
+        // client_id / Authorization the page itself uses for api-v2, captured by the interceptors.
+        const scApi = { clientId: null, authorization: null };
+        const noteScApiRequest = (url, headers) => {
+          try {
+            const u = new URL(String(url), window.location.href);
+            if (u.hostname !== 'api-v2.soundcloud.com') return;
+            const clientId = u.searchParams.get('client_id');
+            if (clientId) scApi.clientId = clientId;
+            const auth = headers instanceof Headers
+              ? headers.get('Authorization')
+              : headers && (headers.Authorization || headers.authorization);
+            if (auth) scApi.authorization = auth;
+          } catch (_) {}
+        };
+        const scAuthorization = () => {
+          if (scApi.authorization) return scApi.authorization;
+          const m = document.cookie.match(/(?:^|; )oauth_token=([^;]+)/);
+          return m ? `OAuth ${decodeURIComponent(m[1])}` : null;
+        };
+
         const shell = document.createElement('div');
         shell.className = 'shell';
 
@@ -279,8 +538,10 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
         btnDark.textContent = 'Dark mode';
         const btnTray = document.createElement('button');
         btnTray.textContent = 'Minimize to tray';
+        const btnArtist = document.createElement('button');
+        btnArtist.textContent = 'Artist info';
 
-        actions.append(btnSettings, btnDark, btnTray);
+        actions.append(btnArtist, btnSettings, btnDark, btnTray);
         shell.append(brand, actions);
 
         const backdrop = document.createElement('div');
@@ -364,6 +625,19 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
           return { row, select };
         };
 
+        const makeInputRow = (labelText, type = 'text', placeholder = '') => {
+          const row = document.createElement('div');
+          row.className = 'row';
+          const label = document.createElement('span');
+          label.textContent = labelText;
+          const input = document.createElement('input');
+          input.type = type;
+          input.className = 'field';
+          input.placeholder = placeholder;
+          row.append(label, input);
+          return { row, input };
+        };
+
         const makeLastfmRow = (authUrl, keyMissing, warnText) => {
           const row = document.createElement('div');
           row.className = 'row';
@@ -404,7 +678,12 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
           authInfo.textContent = `Auth URL: ${authUrl}`;
 
           const setStatus = (session) => {
-            if (session && session.username) {
+            if (session && session.username && session.expired) {
+              statusValue.textContent = `Session expired for ${session.username}; reconnect`;
+              connectBtn.disabled = !!keyMissing;
+              disconnectBtn.disabled = false;
+              disconnectBtn.style.display = '';
+            } else if (session && session.username) {
               statusValue.textContent = `Connected as ${session.username}`;
               connectBtn.disabled = true;
               disconnectBtn.disabled = false;
@@ -436,12 +715,13 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
         s2Title.textContent = 'Scrobbling';
         const scrobbleToggle = makeToggleRow('Enable scrobbling');
         const thresholdRow = makeSliderRow();
+        const mixRow = makeToggleRow('Split DJ mixes by tracklist');
         const notifyRow = makeToggleRow('Show scrobble notifications');
         const notifyModeRow = makeSelectRow('Notification style', [
           { label: 'In-app toast', value: 'in_app' },
           { label: 'System notification', value: 'system' },
         ]);
-        secScrobble.append(s2Title, scrobbleToggle.row, thresholdRow.row, notifyRow.row, notifyModeRow.row);
+        secScrobble.append(s2Title, scrobbleToggle.row, thresholdRow.row, mixRow.row, notifyRow.row, notifyModeRow.row);
 
         const secLastfm = document.createElement('div');
         secLastfm.className = 'section';
@@ -451,11 +731,263 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
         const authUrl = '{auth_url}';
         const warnText = keyMissing ? 'Set LASTFM_API_KEY & LASTFM_CALLBACK to enable auth.' : '';
         const lf = makeLastfmRow(authUrl, keyMissing, warnText);
-        secLastfm.append(s3Title, lf.row);
+        const authFlowRow = makeSelectRow('Sign-in method', [
+          { label: 'Browser callback', value: 'callback' },
+          { label: 'Local callback server', value: 'loopback' },
+          { label: 'Desktop (no callback)', value: 'desktop' },
+          { label: 'Username & password', value: 'mobile' },
+        ]);
+        const mobileUserRow = makeInputRow('Last.fm username', 'text', 'username');
+        mobileUserRow.input.autocomplete = 'username';
+        const mobilePassRow = makeInputRow('Password', 'password', 'never stored');
+        mobilePassRow.input.autocomplete = 'current-password';
+        // The credentials rows only show for the mobile flow, where Connect submits them.
+        const updateAuthFlowRows = () => {
+          const mobile = authFlowRow.select.value === 'mobile';
+          mobileUserRow.row.style.display = mobile ? '' : 'none';
+          mobilePassRow.row.style.display = mobile ? '' : 'none';
+          if (lf.connectBtn) lf.connectBtn.textContent = mobile ? 'Sign in' : 'Connect in browser';
+        };
+        updateAuthFlowRows();
+        // Read only: page scripts must not route Last.fm traffic. Set "proxy" in lastfm.json.
+        const proxyRow = makeInputRow('Proxy (lastfm.json)', 'text', 'none: system settings');
+        proxyRow.input.readOnly = true;
+        const localServerRow = makeToggleRow('Use local HTTP server instead of IPC (after restart)');
+        secLastfm.append(
+          s3Title,
+          lf.row,
+          authFlowRow.row,
+          mobileUserRow.row,
+          mobilePassRow.row,
+          proxyRow.row,
+          localServerRow.row,
+        );
         if (lf.warnNode) secLastfm.append(lf.warnNode);
         secLastfm.append(lf.authInfo);
 
-        modal.append(header, secPlayback, secScrobble, secLastfm);
+        const profile = document.createElement('div');
+        profile.className = 'profile';
+        profile.style.display = 'none';
+        const profileHead = document.createElement('div');
+        profileHead.className = 'profile-head';
+        const profileAvatar = document.createElement('img');
+        profileAvatar.className = 'avatar';
+        profileAvatar.alt = '';
+        const profileText = document.createElement('div');
+        profileText.style.flex = '1';
+        const profileName = document.createElement('strong');
+        const profileStats = document.createElement('div');
+        profileStats.className = 'muted';
+        const profileStale = document.createElement('div');
+        profileStale.className = 'warning';
+        profileText.append(profileName, profileStats, profileStale);
+        const profileRefreshBtn = document.createElement('button');
+        profileRefreshBtn.textContent = 'Refresh';
+        profileHead.append(profileAvatar, profileText, profileRefreshBtn);
+        const profileCols = document.createElement('div');
+        profileCols.className = 'profile-cols';
+        const recentCol = document.createElement('div');
+        const recentTitle = document.createElement('h4');
+        recentTitle.textContent = 'Recent tracks';
+        const recentList = document.createElement('div');
+        recentList.className = 'scroll-list';
+        recentCol.append(recentTitle, recentList);
+        const topCol = document.createElement('div');
+        const topTitle = document.createElement('h4');
+        topTitle.textContent = 'Top artists this week';
+        const topList = document.createElement('div');
+        topList.className = 'scroll-list';
+        topCol.append(topTitle, topList);
+        profileCols.append(recentCol, topCol);
+        profile.append(profileHead, profileCols);
+        secLastfm.append(profile);
+
+        const missingRow = document.createElement('div');
+        missingRow.className = 'row';
+        missingRow.style.display = 'none';
+        const missingLabel = document.createElement('span');
+        missingLabel.className = 'warning';
+        const missingActions = document.createElement('div');
+        missingActions.className = 'toggle';
+        const resubmitBtn = document.createElement('button');
+        resubmitBtn.textContent = 'Resubmit';
+        const dismissBtn = document.createElement('button');
+        dismissBtn.textContent = 'Dismiss';
+        missingActions.append(resubmitBtn, dismissBtn);
+        missingRow.append(missingLabel, missingActions);
+        const missingList = document.createElement('div');
+        missingList.className = 'muted';
+        missingList.style.display = 'none';
+        secLastfm.append(missingRow, missingList);
+
+        const backfillRow = document.createElement('div');
+        backfillRow.className = 'row';
+        const backfillLabel = document.createElement('span');
+        backfillLabel.textContent = 'Backfill from SoundCloud history';
+        const backfillBtn = document.createElement('button');
+        backfillBtn.textContent = 'Find plays';
+        backfillRow.append(backfillLabel, backfillBtn);
+        const backfillStatus = document.createElement('div');
+        backfillStatus.className = 'muted';
+        const backfillList = document.createElement('div');
+        backfillList.className = 'scroll-list';
+        backfillList.style.display = 'none';
+        const backfillActions = document.createElement('div');
+        backfillActions.className = 'row';
+        backfillActions.style.display = 'none';
+        const backfillSubmitBtn = document.createElement('button');
+        backfillSubmitBtn.textContent = 'Submit selected';
+        const backfillCancelBtn = document.createElement('button');
+        backfillCancelBtn.textContent = 'Cancel';
+        backfillActions.append(backfillCancelBtn, backfillSubmitBtn);
+        secLastfm.append(backfillRow, backfillStatus, backfillList, backfillActions);
+
+        const syncLovesRow = makeToggleRow('Sync SoundCloud likes to Last.fm loves', false);
+        const loveSyncRow = document.createElement('div');
+        loveSyncRow.className = 'row';
+        const loveSyncStatus = document.createElement('span');
+        loveSyncStatus.className = 'muted';
+        const loveSyncActions = document.createElement('div');
+        loveSyncActions.className = 'toggle';
+        const loveSyncPreviewBtn = document.createElement('button');
+        loveSyncPreviewBtn.textContent = 'Preview';
+        const loveSyncBtn = document.createElement('button');
+        loveSyncBtn.textContent = 'Sync now';
+        loveSyncActions.append(loveSyncPreviewBtn, loveSyncBtn);
+        loveSyncRow.append(loveSyncStatus, loveSyncActions);
+        const loveSyncReport = document.createElement('div');
+        loveSyncReport.className = 'muted';
+        const loveMissingBtn = document.createElement('button');
+        loveMissingBtn.textContent = 'Show loves missing from SoundCloud likes';
+        loveMissingBtn.style.display = 'none';
+        const loveMissingList = document.createElement('div');
+        loveMissingList.className = 'scroll-list';
+        loveMissingList.style.display = 'none';
+        secLastfm.append(syncLovesRow.row, loveSyncRow, loveSyncReport, loveMissingBtn, loveMissingList);
+
+        const manualRow = document.createElement('div');
+        manualRow.className = 'row';
+        const manualLabel = document.createElement('span');
+        manualLabel.textContent = 'Test scrobble';
+        const manualToggleBtn = document.createElement('button');
+        manualToggleBtn.textContent = 'Open form';
+        manualRow.append(manualLabel, manualToggleBtn);
+        const manualForm = document.createElement('div');
+        manualForm.style.display = 'none';
+        const manualArtist = makeInputRow('Artist');
+        const manualTitle = makeInputRow('Title');
+        const manualAlbum = makeInputRow('Album', 'text', 'optional');
+        const manualWhen = makeInputRow('Played at', 'datetime-local');
+        const manualDuration = makeInputRow('Duration (seconds)', 'number');
+        manualDuration.input.min = '1';
+        manualDuration.input.value = '180';
+        const manualDryRun = makeToggleRow('Dry run (show signed request only)', false);
+        const manualActions = document.createElement('div');
+        manualActions.className = 'row';
+        const manualStatus = document.createElement('span');
+        manualStatus.className = 'muted';
+        const manualSendBtn = document.createElement('button');
+        manualSendBtn.textContent = 'Scrobble';
+        manualActions.append(manualStatus, manualSendBtn);
+        const manualOutput = document.createElement('pre');
+        manualOutput.className = 'code';
+        manualOutput.style.display = 'none';
+        manualForm.append(
+          manualArtist.row,
+          manualTitle.row,
+          manualAlbum.row,
+          manualWhen.row,
+          manualDuration.row,
+          manualDryRun.row,
+          manualActions,
+          manualOutput,
+        );
+        secLastfm.append(manualRow, manualForm);
+
+        const secControl = document.createElement('div');
+        secControl.className = 'section';
+        const s4Title = document.createElement('h3');
+        s4Title.textContent = 'Control API';
+        const controlApiRow = makeToggleRow('Enable local control API', false);
+        const controlPortRow = makeInputRow('Port', 'number');
+        controlPortRow.input.min = '1024';
+        controlPortRow.input.max = '65535';
+        const controlTokenRow = makeInputRow('Token', 'text');
+        controlTokenRow.input.readOnly = true;
+        const controlRegenBtn = document.createElement('button');
+        controlRegenBtn.textContent = 'Regenerate';
+        controlTokenRow.row.appendChild(controlRegenBtn);
+        const controlStatus = document.createElement('div');
+        controlStatus.className = 'muted';
+        secControl.append(s4Title, controlApiRow.row, controlPortRow.row, controlTokenRow.row, controlStatus);
+
+        const nowFileRow = makeInputRow('Now playing text file', 'text', 'now-playing.txt, in the app folder (blank: off)');
+        const nowTemplateRow = makeInputRow('Text template', 'text', '{artist} – {title}');
+        const widgetThemeRow = makeSelectRow('Widget theme', [
+          { label: 'Dark', value: 'dark' },
+          { label: 'Light', value: 'light' },
+          { label: 'Transparent', value: 'transparent' },
+        ]);
+        const idleTextRow = makeInputRow('Idle text', 'text', 'Nothing playing');
+        const widgetInfo = document.createElement('div');
+        widgetInfo.className = 'muted';
+        widgetInfo.textContent = 'Enable the control API to serve the OBS widget.';
+        secControl.append(nowFileRow.row, nowTemplateRow.row, widgetThemeRow.row, idleTextRow.row, widgetInfo);
+
+        const secDebug = document.createElement('div');
+        secDebug.className = 'section';
+        const s5Title = document.createElement('h3');
+        s5Title.textContent = 'Diagnostics';
+        const debugRow = document.createElement('div');
+        debugRow.className = 'row';
+        const debugSummary = document.createElement('span');
+        debugSummary.className = 'muted';
+        const debugRefreshBtn = document.createElement('button');
+        debugRefreshBtn.textContent = 'Refresh';
+        debugRow.append(debugSummary, debugRefreshBtn);
+        const debugOutput = document.createElement('pre');
+        debugOutput.className = 'code';
+        secDebug.append(s5Title, debugRow, debugOutput);
+
+        const secHooks = document.createElement('div');
+        secHooks.className = 'section';
+        const s6Title = document.createElement('h3');
+        s6Title.textContent = 'Webhooks';
+        const hooksList = document.createElement('div');
+        // Read only: page scripts must not choose where events go. Hooks live under
+        // scrobble_config.webhooks in lastfm.json.
+        const hooksHint = document.createElement('div');
+        hooksHint.className = 'muted';
+        hooksHint.textContent = 'POST JSON on playback and scrobble events. Add hooks under scrobble_config.webhooks in lastfm.json next to the app.';
+        secHooks.append(s6Title, hooksList, hooksHint);
+        const webhookEvents = [
+          { value: 'track_started', label: 'Track started' },
+          { value: 'threshold_reached', label: 'Threshold reached' },
+          { value: 'scrobble_succeeded', label: 'Scrobbled' },
+          { value: 'scrobble_failed', label: 'Scrobble failed' },
+          { value: 'loved', label: 'Loved' },
+        ];
+        let webhooks = [];
+
+        const secMqtt = document.createElement('div');
+        secMqtt.className = 'section';
+        const s7Title = document.createElement('h3');
+        s7Title.textContent = 'MQTT';
+        // Read only, like webhooks: the broker and its credentials live under
+        // scrobble_config.mqtt in lastfm.json.
+        const mqttInfo = document.createElement('div');
+        mqttInfo.className = 'muted';
+        const mqttRow = document.createElement('div');
+        mqttRow.className = 'row';
+        const mqttHint = document.createElement('span');
+        mqttHint.className = 'muted';
+        mqttHint.textContent = 'Configure scrobble_config.mqtt in lastfm.json next to the app, then reconnect.';
+        const mqttReconnectBtn = document.createElement('button');
+        mqttReconnectBtn.textContent = 'Reconnect';
+        mqttRow.append(mqttHint, mqttReconnectBtn);
+        secMqtt.append(s7Title, mqttInfo, mqttRow);
+
+        modal.append(header, secPlayback, secScrobble, secLastfm, secControl, secHooks, secMqtt, secDebug);
         backdrop.appendChild(modal);
 
         const setModalOpen = (open) => {
@@ -463,7 +995,14 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
           backdrop.classList.toggle('open', open);
         };
 
-        btnSettings.onclick = () => setModalOpen(true);
+        btnSettings.onclick = () => {
+          setModalOpen(true);
+          refreshMissingScrobbles();
+          refreshLastfmStatus();
+          refreshLoveSyncStatus();
+          refreshControlApi();
+          refreshStatus();
+        };
         btnClose.onclick = () => setModalOpen(false);
         backdrop.onclick = (e) => {
           if (e.target === backdrop) setModalOpen(false);
@@ -494,12 +1033,78 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
           }
         };
 
+        const renderProfile = (view) => {
+          if (!view) {
+            profile.style.display = 'none';
+            return;
+          }
+          profile.style.display = '';
+          const info = view.info || {};
+          profileName.textContent = info.realname ? `${info.name} (${info.realname})` : (info.name || view.username);
+          const since = info.registered ? new Date(info.registered * 1000).toLocaleDateString() : null;
+          profileStats.textContent = `${(info.playcount || 0).toLocaleString()} scrobbles${since ? ` · since ${since}` : ''}`;
+          if (info.avatar) {
+            profileAvatar.src = info.avatar;
+            profileAvatar.style.visibility = '';
+          } else {
+            profileAvatar.removeAttribute('src');
+            profileAvatar.style.visibility = 'hidden';
+          }
+          const updated = view.fetched_at ? new Date(view.fetched_at).toLocaleString() : 'never';
+          profileStale.textContent = view.stale
+            ? `${view.error ? 'Offline' : 'Outdated'} · showing data from ${updated}`
+            : '';
+          recentList.textContent = '';
+          (view.recent_tracks || []).forEach((t) => {
+            const item = document.createElement('div');
+            item.className = 'list-item';
+            const when = t.now_playing ? 'now' : (t.timestamp ? new Date(t.timestamp * 1000).toLocaleTimeString([], { hour: '2-digit', minute: '2-digit' }) : '');
+            item.textContent = `${t.title} — ${t.artist}${when ? ` · ${when}` : ''}`;
+            item.title = item.textContent;
+            recentList.appendChild(item);
+          });
+          topList.textContent = '';
+          (view.top_artists || []).forEach((a, i) => {
+            const item = document.createElement('div');
+            item.className = 'list-item';
+            item.textContent = `${i + 1}. ${a.name} · ${a.playcount} plays`;
+            topList.appendChild(item);
+          });
+        };
+
+        const loadProfile = async (refresh = true) => {
+          const invoke = getInvoker();
+          if (!invoke) return;
+          try {
+            const cached = await invoke('get_lastfm_profile');
+            if (cached) renderProfile(cached);
+          } catch (err) {
+            console.warn('[MSCD] get_lastfm_profile failed', err);
+          }
+          if (!refresh) return;
+          profileRefreshBtn.disabled = true;
+          try {
+            renderProfile(await invoke('refresh_lastfm_profile'));
+          } catch (err) {
+            console.warn('[MSCD] refresh_lastfm_profile failed', err);
+          } finally {
+            profileRefreshBtn.disabled = false;
+          }
+        };
+
+        profileRefreshBtn.addEventListener('click', () => loadProfile(true));
+
         const refreshLastfmStatus = async () => {
           const invoke = getInvoker();
           if (!invoke) return null;
           try {
             const session = await invoke('get_lastfm_status');
             lf.setStatus(session || null);
+            if (session && !session.expired) {
+              loadProfile(settingsOpen);
+            } else {
+              renderProfile(null);
+            }
             return session || null;
           } catch (err) {
             console.warn('[MSCD] get_lastfm_status failed', err);
@@ -507,6 +1112,330 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
           }
         };
 
+        const refreshMissingScrobbles = async () => {
+          const invoke = getInvoker();
+          if (!invoke) return;
+          try {
+            const missing = (await invoke('get_missing_scrobbles')) || [];
+            const visible = missing.length > 0;
+            missingRow.style.display = visible ? '' : 'none';
+            missingList.style.display = visible ? '' : 'none';
+            missingLabel.textContent = `${missing.length} scrobble${missing.length === 1 ? '' : 's'} not recorded by Last.fm`;
+            missingList.textContent = '';
+            missing.slice(0, 5).forEach((m) => {
+              const line = document.createElement('div');
+              const when = new Date(m.timestamp * 1000).toLocaleString();
+              line.textContent = `${m.title} — ${m.artist} (${when})`;
+              missingList.appendChild(line);
+            });
+            if (missing.length > 5) {
+              const more = document.createElement('div');
+              more.textContent = `…and ${missing.length - 5} more`;
+              missingList.appendChild(more);
+            }
+          } catch (err) {
+            console.warn('[MSCD] get_missing_scrobbles failed', err);
+          }
+        };
+
+        resubmitBtn.addEventListener('click', async () => {
+          const invoke = getInvoker();
+          if (!invoke) return;
+          resubmitBtn.disabled = true;
+          try {
+            const count = await invoke('resubmit_scrobbles');
+            console.info('[MSCD] Resubmitted scrobbles', count);
+          } catch (err) {
+            console.warn('[MSCD] resubmit_scrobbles failed', err);
+            showToast({ kind: 'scrobble_failed', title: 'Resubmit', artist: 'Last.fm', message: String(err) });
+          } finally {
+            resubmitBtn.disabled = false;
+            refreshMissingScrobbles();
+          }
+        });
+
+        dismissBtn.addEventListener('click', async () => {
+          const invoke = getInvoker();
+          if (!invoke) return;
+          try {
+            await invoke('dismiss_missing_scrobbles');
+          } catch (err) {
+            console.warn('[MSCD] dismiss_missing_scrobbles failed', err);
+          }
+          refreshMissingScrobbles();
+        });
+
+        // Last.fm only accepts scrobbles from the past 14 days.
+        const BACKFILL_WINDOW_MS = 14 * 24 * 60 * 60 * 1000;
+        let backfillCandidates = [];
+
+        const fetchSoundcloudHistory = async (sinceMs) => {
+          const auth = scAuthorization();
+          if (!scApi.clientId || !auth) {
+            throw new Error('SoundCloud session not detected yet; browse a page while signed in and retry');
+          }
+          const plays = [];
+          let url = `https://api-v2.soundcloud.com/me/play-history/tracks?client_id=${encodeURIComponent(scApi.clientId)}&limit=50&offset=0&linked_partitioning=1`;
+          for (let page = 0; url && page < 40; page += 1) {
+            const res = await fetch(url, { headers: { Authorization: auth, Accept: 'application/json' } });
+            if (!res.ok) throw new Error(`SoundCloud history request failed: ${res.status}`);
+            const data = await res.json();
+            let reachedWindowEnd = false;
+            (data.collection || []).forEach((item) => {
+              const track = item.track || {};
+              if (!item.played_at || item.played_at < sinceMs) {
+                reachedWindowEnd = true;
+                return;
+              }
+              plays.push({
+                trackId: track.permalink_url || String(track.id || ''),
+                title: track.title || '',
+                artist: track.publisher_metadata?.artist || track.user?.username || '',
+                album: track.publisher_metadata?.album_title || null,
+                durationMs: track.full_duration || track.duration || 0,
+                playedAt: item.played_at,
+              });
+            });
+            if (reachedWindowEnd || !data.next_href) break;
+            url = data.next_href.includes('client_id=')
+              ? data.next_href
+              : `${data.next_href}&client_id=${encodeURIComponent(scApi.clientId)}`;
+          }
+          return plays;
+        };
+
+        const fetchSoundcloudLikes = async () => {
+          const auth = scAuthorization();
+          if (!scApi.clientId || !auth) {
+            throw new Error('SoundCloud session not detected yet; browse a page while signed in and retry');
+          }
+          const likes = [];
+          let url = `https://api-v2.soundcloud.com/me/track_likes?client_id=${encodeURIComponent(scApi.clientId)}&limit=200&offset=0&linked_partitioning=1`;
+          for (let page = 0; url && page < 50; page += 1) {
+            const res = await fetch(url, { headers: { Authorization: auth, Accept: 'application/json' } });
+            if (!res.ok) throw new Error(`SoundCloud likes request failed: ${res.status}`);
+            const data = await res.json();
+            (data.collection || []).forEach((item) => {
+              const track = item.track;
+              if (!track) return;
+              likes.push({
+                trackId: track.permalink_url || String(track.id || ''),
+                title: track.title || '',
+                artist: track.publisher_metadata?.artist || track.user?.username || '',
+                likedAt: Date.parse(item.created_at) || 0,
+              });
+            });
+            if (!data.next_href) break;
+            url = data.next_href.includes('client_id=')
+              ? data.next_href
+              : `${data.next_href}&client_id=${encodeURIComponent(scApi.clientId)}`;
+          }
+          return likes;
+        };
+
+        const LOVE_SYNC_INTERVAL_MS = 6 * 60 * 60 * 1000;
+        let loveSyncRunning = false;
+
+        const refreshLoveSyncStatus = async () => {
+          const invoke = getInvoker();
+          if (!invoke) return null;
+          try {
+            const status = await invoke('get_love_sync_status');
+            loveSyncStatus.textContent = status.last_run
+              ? `Last synced ${new Date(status.last_run).toLocaleString()} · ${status.synced.length} loved by sync`
+              : 'Loved tracks: not synced yet';
+            return status;
+          } catch (err) {
+            console.warn('[MSCD] get_love_sync_status failed', err);
+            return null;
+          }
+        };
+
+        const renderLoveReport = (report) => {
+          const verb = report.dry_run ? 'Would love' : 'Loved';
+          let summary = `${verb} ${report.loved.length} tracks; ${report.already_loved} already loved.`;
+          if (report.remaining) summary += ` ${report.remaining} more next run.`;
+          if (report.failed.length) {
+            summary += ` ${report.failed.length} failed: ${report.failed[0].error}`;
+          }
+          loveSyncReport.textContent = summary;
+          loveMissingList.textContent = '';
+          report.missing_on_soundcloud.forEach((t) => {
+            const item = document.createElement('div');
+            item.className = 'list-item';
+            item.textContent = `${t.title} — ${t.artist}`;
+            loveMissingList.appendChild(item);
+          });
+          const missing = report.missing_on_soundcloud.length;
+          loveMissingBtn.style.display = missing ? '' : 'none';
+          loveMissingBtn.textContent = `Show ${missing} loves missing from SoundCloud likes`;
+          loveMissingList.style.display = 'none';
+        };
+
+        const runLoveSync = async (dryRun) => {
+          const invoke = getInvoker();
+          if (!invoke || loveSyncRunning) return;
+          loveSyncRunning = true;
+          loveSyncBtn.disabled = true;
+          loveSyncPreviewBtn.disabled = true;
+          try {
+            loveSyncReport.textContent = 'Reading SoundCloud likes…';
+            const likes = await fetchSoundcloudLikes();
+            loveSyncReport.textContent = `Comparing ${likes.length} likes with Last.fm loves…`;
+            renderLoveReport(await invoke('sync_loves', { likes, dryRun }));
+          } catch (err) {
+            console.warn('[MSCD] love sync failed', err);
+            loveSyncReport.textContent = `Love sync failed: ${err?.message || err}`;
+          } finally {
+            loveSyncRunning = false;
+            loveSyncBtn.disabled = false;
+            loveSyncPreviewBtn.disabled = false;
+            refreshLoveSyncStatus();
+          }
+        };
+
+        // Background job: runs only when opted in, connected, and the last run is old enough.
+        const maybeAutoLoveSync = async () => {
+          if (!syncLovesRow.input.checked || !scApi.clientId || !scAuthorization()) return;
+          const status = await refreshLoveSyncStatus();
+          if (!status) return;
+          if (status.last_run && Date.now() - status.last_run < LOVE_SYNC_INTERVAL_MS) return;
+          runLoveSync(false);
+        };
+
+        loveSyncBtn.addEventListener('click', () => runLoveSync(false));
+        loveSyncPreviewBtn.addEventListener('click', () => runLoveSync(true));
+        loveMissingBtn.addEventListener('click', () => {
+          const open = loveMissingList.style.display === 'none';
+          loveMissingList.style.display = open ? '' : 'none';
+        });
+        setTimeout(maybeAutoLoveSync, 60 * 1000);
+        setInterval(maybeAutoLoveSync, 30 * 60 * 1000);
+
+        const resetBackfill = () => {
+          backfillCandidates = [];
+          backfillList.textContent = '';
+          backfillList.style.display = 'none';
+          backfillActions.style.display = 'none';
+        };
+
+        const renderBackfill = () => {
+          backfillList.textContent = '';
+          backfillCandidates.forEach((play) => {
+            const row = document.createElement('label');
+            row.className = 'check-row';
+            const box = document.createElement('input');
+            box.type = 'checkbox';
+            box.checked = true;
+            box.onchange = () => { play.selected = box.checked; };
+            play.selected = true;
+            const text = document.createElement('span');
+            text.textContent = `${new Date(play.playedAt).toLocaleString()} · ${play.title} — ${play.artist}`;
+            row.append(box, text);
+            backfillList.appendChild(row);
+          });
+          const any = backfillCandidates.length > 0;
+          backfillList.style.display = any ? '' : 'none';
+          backfillActions.style.display = any ? '' : 'none';
+        };
+
+        backfillBtn.addEventListener('click', async () => {
+          const invoke = getInvoker();
+          if (!invoke) return;
+          resetBackfill();
+          backfillBtn.disabled = true;
+          try {
+            backfillStatus.textContent = 'Reading SoundCloud history…';
+            const plays = await fetchSoundcloudHistory(Date.now() - BACKFILL_WINDOW_MS);
+            backfillStatus.textContent = `Checking ${plays.length} plays against Last.fm…`;
+            backfillCandidates = (await invoke('prepare_backfill', { plays })) || [];
+            backfillStatus.textContent = backfillCandidates.length
+              ? `${backfillCandidates.length} plays are not on Last.fm yet. Review and submit:`
+              : 'Nothing to backfill; Last.fm already has these plays.';
+            renderBackfill();
+          } catch (err) {
+            console.warn('[MSCD] backfill prepare failed', err);
+            backfillStatus.textContent = `Backfill failed: ${err?.message || err}`;
+          } finally {
+            backfillBtn.disabled = false;
+          }
+        });
+
+        backfillCancelBtn.addEventListener('click', () => {
+          resetBackfill();
+          backfillStatus.textContent = '';
+        });
+
+        backfillSubmitBtn.addEventListener('click', async () => {
+          const invoke = getInvoker();
+          if (!invoke) return;
+          const plays = backfillCandidates
+            .filter((p) => p.selected)
+            .map(({ selected, ...play }) => play);
+          if (!plays.length) return;
+          backfillSubmitBtn.disabled = true;
+          try {
+            backfillStatus.textContent = `Submitting ${plays.length} plays…`;
+            const report = await invoke('submit_backfill', { plays });
+            const counts = `${report.accepted} accepted, ${report.ignored} ignored by Last.fm`;
+            backfillStatus.textContent = report.failedBatch
+              ? `Backfill stopped after ${counts}: ${report.failedBatch}. Run it again to retry the rest.`
+              : `Backfill done: ${counts}.`;
+            resetBackfill();
+          } catch (err) {
+            console.warn('[MSCD] backfill submit failed', err);
+            backfillStatus.textContent = `Backfill failed: ${err?.message || err}`;
+          } finally {
+            backfillSubmitBtn.disabled = false;
+          }
+        });
+
+        const toLocalInputValue = (date) => {
+          const pad = (n) => String(n).padStart(2, '0');
+          return `${date.getFullYear()}-${pad(date.getMonth() + 1)}-${pad(date.getDate())}T${pad(date.getHours())}:${pad(date.getMinutes())}`;
+        };
+
+        manualToggleBtn.addEventListener('click', () => {
+          const open = manualForm.style.display === 'none';
+          manualForm.style.display = open ? '' : 'none';
+          manualToggleBtn.textContent = open ? 'Close form' : 'Open form';
+          if (open && !manualWhen.input.value) {
+            manualWhen.input.value = toLocalInputValue(new Date());
+          }
+        });
+
+        manualSendBtn.addEventListener('click', async () => {
+          const invoke = getInvoker();
+          if (!invoke) return;
+          const when = manualWhen.input.value ? new Date(manualWhen.input.value).getTime() : Date.now();
+          const scrobble = {
+            artist: manualArtist.input.value,
+            title: manualTitle.input.value,
+            album: manualAlbum.input.value || null,
+            timestamp: Math.floor(when / 1000),
+            durationMs: Math.max(0, Math.round(Number(manualDuration.input.value) || 0)) * 1000,
+            dryRun: manualDryRun.input.checked,
+          };
+          manualSendBtn.disabled = true;
+          manualOutput.style.display = 'none';
+          manualStatus.textContent = scrobble.dryRun ? 'Signing…' : 'Sending…';
+          try {
+            const result = await invoke('manual_scrobble', { scrobble });
+            if (result.dry_run) {
+              manualStatus.textContent = 'Dry run: nothing was sent.';
+              manualOutput.textContent = result.params.map(([k, v]) => `${k}=${v}`).join('\n');
+              manualOutput.style.display = '';
+            } else {
+              manualStatus.textContent = 'Scrobbled.';
+            }
+          } catch (err) {
+            console.warn('[MSCD] manual_scrobble failed', err);
+            manualStatus.textContent = `Failed: ${err?.message || err}`;
+          } finally {
+            manualSendBtn.disabled = false;
+          }
+        });
+
         const pollForSession = (attempt = 0) => {
           if (attempt > 30) return;
           setTimeout(async () => {
@@ -517,36 +1446,119 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
           }, 2000);
         };
 
+        let callbackRegistered = true;
+        let desktopAuthPending = false;
+
+        const loadAuthOptions = async () => {
+          const invoke = getInvoker();
+          if (!invoke) return;
+          try {
+            const options = await invoke('get_auth_options');
+            callbackRegistered = options.callback_registered;
+          } catch (err) {
+            console.warn('[MSCD] get_auth_options failed', err);
+            return;
+          }
+          const callbackOption = authFlowRow.select.querySelector('option[value="callback"]');
+          if (callbackOption) callbackOption.disabled = !callbackRegistered;
+          if (!callbackRegistered && authFlowRow.select.value === 'callback') {
+            authFlowRow.select.value = 'loopback';
+            updateAuthFlowRows();
+            lf.authInfo.textContent = 'No mscd:// handler is registered; using the local callback server.';
+          }
+        };
+
+        // Last.fm desktop flow: request a token, open the approval page, then wait while
+        // the backend polls auth.getSession. Clicking again cancels.
+        const connectDesktop = async () => {
+          const invoke = getInvoker();
+          if (!invoke) return;
+          if (desktopAuthPending) {
+            invoke('cancel_desktop_auth').catch(() => {});
+            return;
+          }
+          desktopAuthPending = true;
+          lf.connectBtn.textContent = 'Cancel sign-in';
+          try {
+            const url = await invoke('start_desktop_auth');
+            invoke('open_external', { url }).catch(() => fallbackOpen(url));
+            lf.authInfo.textContent = 'Approve access in your browser; waiting for Last.fm…';
+            const session = await invoke('await_desktop_auth');
+            lf.authInfo.textContent = session ? '' : 'Sign-in cancelled.';
+          } catch (err) {
+            console.warn('[MSCD] desktop auth failed', err);
+            lf.authInfo.textContent = `Sign-in failed: ${err}`;
+          } finally {
+            desktopAuthPending = false;
+            updateAuthFlowRows();
+            refreshLastfmStatus();
+          }
+        };
+
+        // Mobile flow: the password is handed to the backend once and cleared from the form.
+        const connectMobile = async () => {
+          const invoke = getInvoker();
+          if (!invoke) return;
+          const username = mobileUserRow.input.value.trim();
+          const password = mobilePassRow.input.value;
+          mobilePassRow.input.value = '';
+          if (!username || !password) {
+            lf.authInfo.textContent = 'Enter your Last.fm username and password.';
+            (username ? mobilePassRow : mobileUserRow).input.focus();
+            return;
+          }
+          lf.connectBtn.disabled = true;
+          lf.authInfo.textContent = 'Signing in…';
+          try {
+            await invoke('login_lastfm_mobile', { username, password });
+            lf.authInfo.textContent = '';
+          } catch (err) {
+            console.warn('[MSCD] login_lastfm_mobile failed', err);
+            lf.authInfo.textContent = `Sign-in failed: ${err}`;
+          } finally {
+            refreshLastfmStatus();
+          }
+        };
+        mobilePassRow.input.addEventListener('keydown', (e) => {
+          if (e.key === 'Enter' && !keyMissing) connectMobile();
+        });
+
         lf.connectBtn?.addEventListener('click', () => {
           if (keyMissing) {
             console.warn('[MSCD] Key missing; connect disabled');
             return;
           }
+          if (authFlowRow.select.value === 'desktop') {
+            connectDesktop();
+            return;
+          }
+          if (authFlowRow.select.value === 'mobile') {
+            connectMobile();
+            return;
+          }
 
-          try {
-            const invoke = getInvoker();
-            if (invoke) {
-              console.info('[MSCD] Opening via open_external command', authUrl);
-              Promise.resolve(invoke('open_external', { url: authUrl }))
-                .then(() => {
-                  console.info('[MSCD] open_external success');
-                  pollForSession();
-                })
+          const invoke = getInvoker();
+          if (!invoke) {
+            // Callbacks are only accepted for a request the backend knows about.
+            console.warn('[MSCD] __TAURI__.invoke unavailable; cannot start sign-in');
+            lf.authInfo.textContent = 'Sign-in unavailable: app bridge not ready, reload the page.';
+            return;
+          }
+          invoke('begin_lastfm_auth')
+            .then((url) => {
+              console.info('[MSCD] Opening via open_external command', url);
+              return Promise.resolve(invoke('open_external', { url }))
+                .then(() => console.info('[MSCD] open_external success'))
                 .catch((err) => {
                   console.warn('[MSCD] open_external failed; falling back', err);
-                  fallbackOpen(authUrl);
-                  pollForSession();
-                });
-              return;
-            } else {
-              console.warn('[MSCD] __TAURI__.invoke unavailable; falling back to window.open');
-            }
-          } catch (e) {
-            console.warn('TAURI shell unavailable, falling back to window.open', e);
-          }
-
-          fallbackOpen(authUrl);
-          pollForSession();
+                  fallbackOpen(url);
+                })
+                .then(() => pollForSession());
+            })
+            .catch((err) => {
+              console.warn('[MSCD] begin_lastfm_auth failed', err);
+              lf.authInfo.textContent = `Sign-in failed: ${err}`;
+            });
         });
 
         lf.disconnectBtn?.addEventListener('click', async () => {
@@ -555,6 +1567,7 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
           try {
             await invoke('disconnect_lastfm');
             lf.setStatus(null);
+            renderProfile(null);
           } catch (err) {
             console.warn('[MSCD] disconnect_lastfm failed', err);
           }
@@ -569,23 +1582,292 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
         const toastHost = document.createElement('div');
         toastHost.className = 'toast-container';
 
-        shadow.append(shell, backdrop, toastHost);
-
-        refreshLastfmStatus();
-
-        // --- Scrobble observer (MediaSession primary, DOM fallback) ---
-        const startScrobbleObserver = () => {
-          console.info('[MSCD] Scrobble observer starting, endpoint:', endpoint);
-          if (!endpoint) {
-            console.warn('[MSCD] playback endpoint missing, observer disabled');
+        // --- Artist info side panel (artist.getInfo + artist.getTopTags) ---
+        const artistPanel = document.createElement('div');
+        artistPanel.className = 'side-panel';
+        artistPanel.style.display = 'none';
+        const artistHeader = document.createElement('header');
+        const artistHeading = document.createElement('strong');
+        artistHeading.textContent = 'Artist';
+        const artistCollapseBtn = document.createElement('button');
+        artistCollapseBtn.textContent = 'Collapse';
+        artistHeader.append(artistHeading, artistCollapseBtn);
+        const artistBody = document.createElement('div');
+        artistBody.className = 'panel-body';
+        const similarBox = document.createElement('div');
+        similarBox.className = 'panel-body';
+        const btnSimilar = document.createElement('button');
+        btnSimilar.textContent = 'More like this';
+        const similarList = document.createElement('div');
+        similarList.className = 'scroll-list';
+        similarList.style.display = 'none';
+        similarBox.append(btnSimilar, similarList);
+        const tagBox = document.createElement('div');
+        tagBox.className = 'panel-body';
+        const btnTags = document.createElement('button');
+        btnTags.textContent = 'Tag this track';
+        const tagEditor = document.createElement('div');
+        tagEditor.className = 'panel-body';
+        tagEditor.style.display = 'none';
+        tagEditor.style.padding = '0';
+        const tagTrackLabel = document.createElement('div');
+        tagTrackLabel.className = 'muted';
+        const ownTags = document.createElement('div');
+        ownTags.className = 'tags';
+        const tagInput = document.createElement('input');
+        tagInput.type = 'text';
+        tagInput.placeholder = 'Add tags, comma separated';
+        const suggestedTitle = document.createElement('div');
+        suggestedTitle.className = 'muted';
+        suggestedTitle.textContent = 'Suggested';
+        const suggestedTags = document.createElement('div');
+        suggestedTags.className = 'tags';
+        const tagStatus = document.createElement('div');
+        tagStatus.className = 'muted';
+        tagEditor.append(tagTrackLabel, ownTags, tagInput, suggestedTitle, suggestedTags, tagStatus);
+        tagBox.append(btnTags, tagEditor);
+        artistPanel.append(artistHeader, artistBody, similarBox, tagBox);
+
+        let artistPanelOpen = false;
+        let artistCollapsed = false;
+        let currentArtist = null;
+        let currentTitle = null;
+        let shownArtist = null;
+        let tagTrack = null;
+
+        const renderArtist = (view, message) => {
+          artistBody.textContent = '';
+          if (!view) {
+            const empty = document.createElement('div');
+            empty.className = 'muted';
+            empty.textContent = message || 'Play a track to see artist info.';
+            artistBody.appendChild(empty);
             return;
           }
-          const blockedHosts = ['ad.doubleclick.net', 'reporting.deliveryengine.adswizz.com'];
-          const blockedPathMarkers = ['/vast/', '/trackimp/', '/ddm/trackimp/', '/audio-ad', '/ads/'];
-          let lastPayload = null;
-          let logCount = 0;
-          let lastLoggedTrack = null;
-          const logAdSkip = (reason) => console.info('[MSCD] Ad skip', reason);
+          artistHeading.textContent = view.name;
+          const link = document.createElement('a');
+          link.textContent = 'Open on Last.fm';
+          link.href = view.url || '#';
+          link.onclick = (e) => {
+            e.preventDefault();
+            const invoke = getInvoker();
+            if (invoke && view.url) invoke('open_external', { url: view.url }).catch(() => fallbackOpen(view.url));
+          };
+          const stats = document.createElement('div');
+          stats.className = 'muted';
+          const mine = typeof view.user_playcount === 'number' ? ` · you: ${view.user_playcount.toLocaleString()}` : '';
+          stats.textContent = `${view.listeners.toLocaleString()} listeners · ${view.playcount.toLocaleString()} scrobbles${mine}`;
+          const tags = document.createElement('div');
+          tags.className = 'tags';
+          (view.tags || []).forEach((t) => {
+            const tag = document.createElement('span');
+            tag.className = 'tag';
+            tag.textContent = t;
+            tags.appendChild(tag);
+          });
+          const bio = document.createElement('div');
+          bio.textContent = view.bio_summary || 'No biography available.';
+          artistBody.append(stats, tags, bio, link);
+          if (view.stale) {
+            const stale = document.createElement('div');
+            stale.className = 'warning';
+            stale.textContent = `Offline · cached ${new Date(view.fetched_at).toLocaleString()}`;
+            artistBody.appendChild(stale);
+          }
+        };
+
+        const loadArtistInfo = async () => {
+          const invoke = getInvoker();
+          if (!invoke || !artistPanelOpen) return;
+          shownArtist = currentArtist;
+          try {
+            const view = await invoke('get_artist_info', { artist: currentArtist });
+            if (shownArtist === currentArtist) renderArtist(view);
+          } catch (err) {
+            console.warn('[MSCD] get_artist_info failed', err);
+            renderArtist(null, `Artist info unavailable: ${err}`);
+          }
+        };
+
+        const searchSoundcloud = (name, seed) => {
+          const invoke = getInvoker();
+          if (!invoke) return;
+          invoke('open_soundcloud_search', { name, seed: seed || null }).catch((err) =>
+            console.warn('[MSCD] open_soundcloud_search failed', err)
+          );
+        };
+
+        const suggestionRow = (name, detail, seed) => {
+          const row = document.createElement('div');
+          row.className = 'list-item suggestion';
+          const label = document.createElement('span');
+          label.textContent = name;
+          const meta = document.createElement('span');
+          meta.className = 'muted';
+          meta.textContent = detail;
+          row.append(label, meta);
+          row.onclick = () => searchSoundcloud(name, seed);
+          return row;
+        };
+
+        const renderSimilar = (view, message) => {
+          similarList.textContent = '';
+          similarList.style.display = '';
+          if (!view || !view.similar.length) {
+            const empty = document.createElement('div');
+            empty.className = 'muted';
+            empty.textContent = message || 'No similar artists found.';
+            similarList.appendChild(empty);
+          } else {
+            view.similar.forEach((a) => {
+              similarList.appendChild(suggestionRow(a.name, `${Math.round(a.score * 100)}%`, view.artist));
+            });
+          }
+          if (view && view.recent.length) {
+            const heading = document.createElement('div');
+            heading.className = 'muted';
+            heading.textContent = 'Recently explored';
+            similarList.appendChild(heading);
+            view.recent.slice(0, 8).forEach((r) => {
+              similarList.appendChild(suggestionRow(r.name, r.seed ? `via ${r.seed}` : '', r.seed));
+            });
+          }
+        };
+
+        btnSimilar.onclick = async () => {
+          const invoke = getInvoker();
+          if (!invoke) return;
+          btnSimilar.disabled = true;
+          try {
+            renderSimilar(await invoke('get_similar_artists', { artist: currentArtist }));
+          } catch (err) {
+            console.warn('[MSCD] get_similar_artists failed', err);
+            renderSimilar(null, `Similar artists unavailable: ${err}`);
+          } finally {
+            btnSimilar.disabled = false;
+          }
+        };
+
+        const tagChip = (name, onClick, title) => {
+          const chip = document.createElement('span');
+          chip.className = 'tag clickable';
+          chip.textContent = name;
+          chip.title = title;
+          chip.onclick = onClick;
+          return chip;
+        };
+
+        const updateTags = async (add, remove) => {
+          const invoke = getInvoker();
+          if (!invoke || !tagTrack) return;
+          tagStatus.className = 'muted';
+          tagStatus.textContent = 'Saving…';
+          try {
+            await invoke('update_track_tags', { artist: tagTrack.artist, title: tagTrack.title, add, remove });
+            const lower = add.map((t) => t.trim().toLowerCase()).filter(Boolean);
+            tagTrack.tags = tagTrack.tags.filter((t) => !remove.includes(t)).concat(lower.filter((t) => !tagTrack.tags.includes(t)));
+            tagTrack.suggestions = tagTrack.suggestions.filter((t) => !lower.includes(t));
+            tagStatus.textContent = 'Saved.';
+          } catch (err) {
+            console.warn('[MSCD] update_track_tags failed', err);
+            tagStatus.className = 'warning';
+            tagStatus.textContent = `Tag update failed: ${err}`;
+          }
+          renderTagEditor();
+        };
+
+        const renderTagEditor = () => {
+          ownTags.textContent = '';
+          suggestedTags.textContent = '';
+          if (!tagTrack) return;
+          tagTrackLabel.textContent = `${tagTrack.title} — ${tagTrack.artist}`;
+          if (!tagTrack.tags.length) {
+            const none = document.createElement('span');
+            none.className = 'muted';
+            none.textContent = 'No tags yet.';
+            ownTags.appendChild(none);
+          }
+          tagTrack.tags.forEach((t) => {
+            ownTags.appendChild(tagChip(`${t} ×`, () => updateTags([], [t]), 'Remove tag'));
+          });
+          suggestedTitle.style.display = tagTrack.suggestions.length ? '' : 'none';
+          tagTrack.suggestions.forEach((t) => {
+            suggestedTags.appendChild(tagChip(`+ ${t}`, () => updateTags([t], []), 'Add tag'));
+          });
+        };
+
+        const loadTrackTags = async () => {
+          const invoke = getInvoker();
+          if (!invoke) return;
+          tagStatus.className = 'muted';
+          tagStatus.textContent = 'Loading tags…';
+          try {
+            tagTrack = await invoke('get_track_tags', { artist: currentArtist, title: currentTitle });
+            tagStatus.textContent = tagTrack ? '' : 'Play a track to tag it.';
+          } catch (err) {
+            console.warn('[MSCD] get_track_tags failed', err);
+            tagTrack = null;
+            tagStatus.className = 'warning';
+            tagStatus.textContent = `Tags unavailable: ${err}`;
+          }
+          renderTagEditor();
+        };
+
+        btnTags.onclick = () => {
+          const open = tagEditor.style.display === 'none';
+          tagEditor.style.display = open ? '' : 'none';
+          if (open) loadTrackTags();
+        };
+
+        tagInput.addEventListener('keydown', (e) => {
+          if (e.key !== 'Enter') return;
+          const add = tagInput.value.split(',').map((t) => t.trim()).filter(Boolean);
+          tagInput.value = '';
+          if (add.length) updateTags(add, []);
+        });
+
+        const onTrackChange = (artist, title) => {
+          if (title !== currentTitle) {
+            currentTitle = title;
+            tagTrack = null;
+            if (tagEditor.style.display !== 'none') loadTrackTags();
+          }
+          if (artist === currentArtist) return;
+          currentArtist = artist;
+          similarList.style.display = 'none';
+          if (artistPanelOpen) loadArtistInfo();
+        };
+
+        btnArtist.onclick = () => {
+          artistPanelOpen = !artistPanelOpen;
+          artistPanel.style.display = artistPanelOpen ? '' : 'none';
+          if (artistPanelOpen && (shownArtist === null || shownArtist !== currentArtist)) loadArtistInfo();
+        };
+
+        artistCollapseBtn.onclick = () => {
+          artistCollapsed = !artistCollapsed;
+          artistBody.style.display = artistCollapsed ? 'none' : '';
+          similarBox.style.display = artistCollapsed ? 'none' : '';
+          tagBox.style.display = artistCollapsed ? 'none' : '';
+          artistCollapseBtn.textContent = artistCollapsed ? 'Expand' : 'Collapse';
+        };
+
+        shadow.append(shell, backdrop, artistPanel, toastHost);
+
+        refreshLastfmStatus();
+        refreshMissingScrobbles();
+        loadAuthOptions();
+
+        // --- Scrobble observer (MediaSession primary, DOM fallback) ---
+        const startScrobbleObserver = () => {
+          console.info('[MSCD] Scrobble observer starting via', endpoint || 'ipc');
+          const blockedHosts = ['ad.doubleclick.net', 'reporting.deliveryengine.adswizz.com'];
+          const blockedPathMarkers = ['/vast/', '/trackimp/', '/ddm/trackimp/', '/audio-ad', '/ads/'];
+          let lastPayload = null;
+          let logCount = 0;
+          let lastLoggedTrack = null;
+          let lastDescriptionKey = null;
+          const logAdSkip = (reason) => console.info('[MSCD] Ad skip', reason);
 
           const shouldBlockUrl = (url) => {
             try {
@@ -602,6 +1884,7 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
             const origFetch = window.fetch;
             window.fetch = (...args) => {
               const url = args[0];
+              noteScApiRequest(url instanceof Request ? url.url : url, args[1]?.headers || (url instanceof Request ? url.headers : null));
               if (typeof url === 'string' && shouldBlockUrl(url)) {
                 console.info('[MSCD] Blocked fetch', url);
                 return Promise.resolve(new Response('', { status: 204 }));
@@ -618,7 +1901,17 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
             function WrappedXHR() {
               const xhr = new OrigXHR();
               const origOpen = xhr.open;
+              const origSetHeader = xhr.setRequestHeader;
+              let openedUrl = null;
+              xhr.setRequestHeader = function(name, value) {
+                if (openedUrl && String(name).toLowerCase() === 'authorization') {
+                  noteScApiRequest(openedUrl, { Authorization: value });
+                }
+                return origSetHeader.call(xhr, name, value);
+              };
               xhr.open = function(method, url, ...rest) {
+                openedUrl = url;
+                noteScApiRequest(url, null);
                 if (typeof url === 'string' && shouldBlockUrl(url)) {
                   console.info('[MSCD] Blocked XHR', url);
                   return origOpen.call(xhr, method, 'about:blank', ...rest);
@@ -738,6 +2031,16 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
               }
             }
 
+            let artworkUrl = null;
+            const sessionArt = navigator.mediaSession?.metadata?.artwork;
+            if (sessionArt && sessionArt.length) artworkUrl = sessionArt[sessionArt.length - 1].src || null;
+            if (!artworkUrl) {
+              const artEl = document.querySelector('.playbackSoundBadge .sc-artwork span');
+              const bg = artEl ? getComputedStyle(artEl).backgroundImage : '';
+              const match = bg && bg.match(/url\(["']?(.*?)["']?\)/);
+              artworkUrl = match ? match[1] : null;
+            }
+
             const trackHref = document.querySelector('.playbackSoundBadge__titleLink')?.getAttribute('href');
             const trackId = trackHref || window.location.pathname || title || 'unknown';
 
@@ -748,10 +2051,29 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
               durationMs,
               positionMs,
               paused,
+              artworkUrl,
               ts: Date.now(),
             };
           };
 
+          // Text of the playing track's page (description or comments), only readable while it is open.
+          const grabPageText = (trackHref, selector) => {
+            if (!trackHref) return null;
+            let path = null;
+            try {
+              path = new URL(trackHref, window.location.href).pathname;
+            } catch (_) {
+              return null;
+            }
+            if (window.location.pathname !== path) return null;
+            const parts = [];
+            document
+              .querySelectorAll(selector)
+              .forEach((node) => parts.push(node.innerText || node.textContent || ''));
+            const text = parts.join('\n').trim();
+            return text || null;
+          };
+
           const pushUpdate = () => {
             const payload = grabMeta();
             if (!payload.title || !payload.artist || !payload.durationMs) {
@@ -766,6 +2088,8 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
               return;
             }
 
+            onTrackChange(payload.artist, payload.title);
+
             if (logCount < 5 || payload.trackId !== lastLoggedTrack) {
               console.info('[MSCD] playback payload', payload);
               logCount += 1;
@@ -798,8 +2122,25 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
               }
             }
 
-            // avoid spamming identical payloads
+            if (mixRow.input.checked) {
+              const description = grabPageText(payload.trackId, '.listenDetails__description, .truncatedAudioInfo__content');
+              const comments = grabPageText(payload.trackId, '.commentItem__body');
+              const key = description || comments
+                ? `${payload.trackId}:${(description || '').length}:${(comments || '').length}`
+                : null;
+              if (key && key !== lastDescriptionKey) {
+                lastDescriptionKey = key;
+                payload.description = description;
+                payload.comments = comments;
+              }
+            }
+
+            // avoid spamming identical payloads, but report at least every 10s so the
+            // backend can tell a paused player from a closed one
             if (lastPayload &&
+                payload.ts - lastPayload.ts < 10000 &&
+                !payload.description &&
+                !payload.comments &&
                 lastPayload.trackId === payload.trackId &&
                 lastPayload.title === payload.title &&
                 lastPayload.artist === payload.artist &&
@@ -809,20 +2150,8 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
             }
             lastPayload = payload;
 
-            const body = JSON.stringify(payload);
-            if (navigator.sendBeacon) {
-              const blob = new Blob([body], { type: 'text/plain' });
-              const sent = navigator.sendBeacon(endpoint, blob);
-              if (sent) return;
-            }
-
-            fetch(endpoint, {
-              method: 'POST',
-              mode: 'no-cors',
-              headers: { 'Content-Type': 'text/plain' },
-              body,
-            }).catch((err) => {
-              console.warn('[MSCD] playback post failed', err);
+            backend.reportPlayback(payload).catch((err) => {
+              console.warn('[MSCD] playback report failed', err);
             });
           };
 
@@ -836,12 +2165,49 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
         const eventsUrl = endpoint ? endpoint.replace(/\/playback$/, '/events') : '';
         console.info('[MSCD] Endpoints', { endpoint, settingsUrl, eventsUrl });
 
+        // Overlay-to-backend transport: Tauri IPC by default, the loopback HTTP server
+        // when that fallback is enabled (then `endpoint` is set).
+        const useHttp = !!endpoint;
+        const ipc = (cmd, args) => {
+          const invoke = getInvoker();
+          return invoke ? invoke(cmd, args) : Promise.reject(new Error('app bridge not ready'));
+        };
+        const postJson = async (url, body) => {
+          const res = await fetch(url, {
+            method: 'POST',
+            mode: 'cors',
+            keepalive: true,
+            headers: { ...serverHeaders, 'Content-Type': 'application/json' },
+            body: JSON.stringify(body),
+          });
+          if (!res.ok) throw new Error(`HTTP ${res.status}`);
+          return res.status === 204 ? null : res.json();
+        };
+        const backend = {
+          reportPlayback: (payload) => (useHttp ? postJson(endpoint, payload) : ipc('report_playback', { payload })),
+          getSettings: async () => {
+            if (!useHttp) return ipc('get_settings');
+            const res = await fetch(settingsUrl, { method: 'GET', mode: 'cors', headers: serverHeaders });
+            if (!res.ok) throw new Error(`HTTP ${res.status}`);
+            return res.json();
+          },
+          updateSettings: (update) => (useHttp ? postJson(settingsUrl, update) : ipc('update_settings', { update })),
+        };
+
+        const toastTitles = {
+          scrobble: 'Scrobbled',
+          scrobble_failed: 'Scrobble failed',
+          scrobble_missing: 'Scrobble not recorded',
+          session_expired: 'Last.fm session expired',
+        };
+
         const showToast = (ev) => {
           if (!toastHost) return;
+          if (ev.kind === 'scrobble_missing') refreshMissingScrobbles();
           const node = document.createElement('div');
-          node.className = `toast ${ev.kind === 'scrobble_failed' ? 'error' : 'success'}`;
+          node.className = `toast ${ev.kind === 'scrobble' ? 'success' : 'error'}`;
           const h4 = document.createElement('h4');
-          h4.textContent = ev.kind === 'scrobble_failed' ? 'Scrobble failed' : 'Scrobbled';
+          h4.textContent = toastTitles[ev.kind] || 'Scrobbled';
           const body = document.createElement('div');
           body.className = 'muted';
           body.textContent = `${ev.title} — ${ev.artist}`;
@@ -852,29 +2218,240 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
             msg.textContent = ev.message;
             node.appendChild(msg);
           }
+          if (ev.kind === 'session_expired') {
+            refreshLastfmStatus();
+            const reconnect = document.createElement('button');
+            reconnect.textContent = 'Reconnect';
+            reconnect.style.marginTop = '6px';
+            reconnect.onclick = async () => {
+              node.remove();
+              setModalOpen(true);
+              await refreshLastfmStatus();
+              lf.connectBtn.click();
+            };
+            node.appendChild(reconnect);
+          }
           toastHost.appendChild(node);
           setTimeout(() => {
             node.style.opacity = '0';
             setTimeout(() => node.remove(), 250);
-          }, 4000);
+          }, ev.kind === 'session_expired' ? 20000 : 4000);
+        };
+
+        const renderWebhooks = () => {
+          hooksList.replaceChildren();
+          webhooks.forEach((hook) => {
+            const row = document.createElement('div');
+            row.className = 'row';
+            const url = document.createElement('span');
+            url.textContent = hook.url || '(no URL)';
+            const info = document.createElement('span');
+            info.className = 'muted';
+            const events = (hook.events || []).length
+              ? webhookEvents.filter((e) => hook.events.includes(e.value)).map((e) => e.label).join(', ')
+              : 'all events';
+            info.textContent = `${events}${hook.secret ? ', signed' : ''}${hook.enabled ? '' : ' (disabled)'}`;
+            row.append(url, info);
+            hooksList.appendChild(row);
+          });
+        };
+
+        const ago = (at, now) => {
+          if (!at) return 'never';
+          const secs = Math.max(0, Math.round((now - at) / 1000));
+          return secs < 120 ? `${secs}s ago` : `${Math.round(secs / 60)} min ago`;
+        };
+
+        const renderStatus = (st) => {
+          const lines = [];
+          lines.push(`Overlay: ${st.overlay.alive ? 'alive' : 'silent'} (last report ${ago(st.overlay.last_payload_at, st.now)}, ${st.overlay.attached ? 'IPC events attached' : 'no IPC listener'})`);
+          const np = st.now_playing;
+          if (np) {
+            lines.push(`Track: ${np.artist} – ${np.title}${np.mix ? ' (mix)' : ''}`);
+            lines.push(`  position ${Math.round(np.position_ms / 1000)}s of ${Math.round(np.duration_ms / 1000)}s, listened ${Math.round(np.listened_ms / 1000)}s`);
+            lines.push(`  threshold ${Math.round(np.threshold_ms / 1000)}s: ${np.scrobbled ? 'scrobbled' : `${Math.round(np.progress * 100)}%`}`);
+          } else {
+            lines.push('Track: none');
+          }
+          const ses = st.session;
+          lines.push(`Session: ${!ses.connected ? 'not connected' : `${ses.username}${ses.expired ? ' (expired)' : ' (valid)'}`}`);
+          const last = st.last_scrobble;
+          lines.push(last
+            ? `Last scrobble: ${last.ok ? 'ok' : 'failed'} – ${last.artist} – ${last.title} (${ago(last.at, st.now)})${last.error ? `\n  ${last.error}` : ''}`
+            : 'Last scrobble: none this session');
+          const src = st.config_sources;
+          lines.push(`Config: api key ${src.api_key}, secret ${src.api_secret}, callback ${src.callback}`);
+          lines.push(`  local file: ${src.local_file || 'none found'}`);
+          lines.push(`Settings: ${JSON.stringify(st.config)}`);
+          return lines.join('\n');
+        };
+
+        const refreshStatus = async () => {
+          try {
+            const st = await ipc('get_status');
+            debugSummary.textContent = `v${st.version}, ${st.overlay.alive ? 'overlay reporting' : 'overlay silent'}`;
+            debugOutput.textContent = renderStatus(st);
+          } catch (err) {
+            debugSummary.textContent = 'Status unavailable';
+            console.warn('[MSCD] status failed', err);
+          }
+        };
+        debugRefreshBtn.onclick = () => refreshStatus();
+
+        const showControlApi = (info) => {
+          if (!info) return;
+          controlTokenRow.input.value = info.token || '';
+          widgetInfo.textContent = info.running && info.token
+            ? `OBS browser source: ${info.url}/widget?token=${info.token} (JSON: /widget.json)`
+            : 'Enable the control API to serve the OBS widget.';
+          if (info.error) {
+            controlStatus.textContent = `Not running: ${info.error}`;
+          } else if (info.running) {
+            controlStatus.textContent = `Listening on ${info.url} (send the token as Authorization: Bearer)`;
+          } else {
+            controlStatus.textContent = 'Off';
+          }
+        };
+
+        const refreshControlApi = async () => {
+          try {
+            showControlApi(await ipc('get_control_api'));
+          } catch (err) {
+            console.warn('[MSCD] control API status failed', err);
+          }
+        };
+
+        mqttReconnectBtn.onclick = () => {
+          ipc('reconnect_mqtt').catch((err) => console.warn('[MSCD] MQTT reconnect failed', err));
+        };
+
+        controlRegenBtn.onclick = async () => {
+          if (!confirm('Regenerate the control API token? Scripts using the old one stop working.')) return;
+          try {
+            showControlApi(await ipc('regenerate_control_token'));
+          } catch (err) {
+            console.warn('[MSCD] token regeneration failed', err);
+          }
+        };
+
+        // Player actions sent to the control API (`POST /control/<action>`).
+        const clickControl = (selector) => {
+          const btn = document.querySelector(selector);
+          if (btn) btn.dispatchEvent(new MouseEvent('click', { bubbles: true, cancelable: true }));
+        };
+
+        const runControl = (cmd) => {
+          const audio = document.querySelector('audio');
+          const playBtn = document.querySelector('.playControls__play');
+          const playing = playBtn ? playBtn.classList.contains('playing') : !!(audio && !audio.paused);
+          console.info('[MSCD] Control', cmd.action);
+          switch (cmd.action) {
+            case 'play':
+              if (!playing) clickControl('.playControls__play');
+              break;
+            case 'pause':
+              if (playing) clickControl('.playControls__play');
+              break;
+            case 'toggle':
+              clickControl('.playControls__play');
+              break;
+            case 'next':
+              clickControl('.playControls__next');
+              break;
+            case 'prev':
+              clickControl('.playControls__prev');
+              break;
+            case 'seek':
+              if (audio && typeof cmd.position_ms === 'number') audio.currentTime = cmd.position_ms / 1000;
+              break;
+            case 'like':
+              clickControl('.playbackSoundBadge__like');
+              break;
+            default:
+              console.warn('[MSCD] Unknown control', cmd.action);
+          }
+        };
+
+        // Server-Sent Events from the backend: toasts, saved settings and session changes.
+        // Read through fetch because EventSource can't send the token header.
+        const streamHandlers = {
+          toast: (ev) => showToast(ev),
+          settings: (cfg) => applySettings(cfg),
+          session: () => refreshLastfmStatus(),
+          control: (cmd) => runControl(cmd),
+          control_api: (info) => showControlApi(info),
+        };
+
+        const dispatchStreamEvent = (block) => {
+          let name = 'message';
+          const data = [];
+          block.split('\n').forEach((line) => {
+            if (line.startsWith('event:')) name = line.slice(6).trim();
+            else if (line.startsWith('data:')) data.push(line.slice(5).trimStart());
+          });
+          const handler = streamHandlers[name];
+          if (!handler || !data.length) return;
+          try {
+            handler(JSON.parse(data.join('\n')));
+          } catch (err) {
+            console.warn('[MSCD] Bad stream event', name, err);
+          }
+        };
+
+        // IPC counterpart of the stream: the same events arrive as `mscd:<name>`.
+        const startIpcEvents = async () => {
+          const listen = window.__TAURI__?.event?.listen;
+          if (!listen) {
+            console.warn('[MSCD] __TAURI__.event unavailable; no live events');
+            return;
+          }
+          try {
+            await Promise.all(Object.entries(streamHandlers).map(([name, handler]) =>
+              listen(`mscd:${name}`, (event) => handler(event.payload))));
+            (await ipc('take_toasts')).forEach(showToast);
+          } catch (err) {
+            console.warn('[MSCD] IPC event setup failed', err);
+          }
         };
 
-        const startEventPoller = () => {
-          if (!eventsUrl) return;
-          console.info('[MSCD] Event poller start', eventsUrl);
-          const poll = async () => {
+        const startEventStream = () => {
+          if (!useHttp) {
+            startIpcEvents();
+            return;
+          }
+          let retryMs = 1000;
+          const connect = async () => {
             try {
-              const res = await fetch(eventsUrl, { method: 'GET', mode: 'cors' });
-              if (!res.ok) return;
-              const data = await res.json();
-              (data.events || []).forEach(showToast);
+              const res = await fetch(eventsUrl, {
+                method: 'GET',
+                mode: 'cors',
+                cache: 'no-store',
+                headers: { ...serverHeaders, Accept: 'text/event-stream' },
+              });
+              if (!res.ok || !res.body) throw new Error(`HTTP ${res.status}`);
+              console.info('[MSCD] Event stream connected');
+              retryMs = 1000;
+              const reader = res.body.getReader();
+              const decoder = new TextDecoder();
+              let buffer = '';
+              for (;;) {
+                const { value, done } = await reader.read();
+                if (done) break;
+                buffer += decoder.decode(value, { stream: true });
+                let split;
+                while ((split = buffer.indexOf('\n\n')) >= 0) {
+                  dispatchStreamEvent(buffer.slice(0, split));
+                  buffer = buffer.slice(split + 2);
+                }
+              }
+              console.info('[MSCD] Event stream closed');
             } catch (err) {
-              // ignore
-            } finally {
-              setTimeout(poll, 3000);
+              console.warn('[MSCD] Event stream failed', err);
             }
+            setTimeout(connect, retryMs);
+            retryMs = Math.min(retryMs * 2, 30000);
           };
-          poll();
+          connect();
         };
 
         let lastAppliedCfg = null;
@@ -896,6 +2473,51 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
           if (typeof cfg.skip_promoted === 'boolean') {
             promoRow.input.checked = cfg.skip_promoted;
           }
+          if (typeof cfg.split_mixes === 'boolean') {
+            mixRow.input.checked = cfg.split_mixes;
+          }
+          if (typeof cfg.sync_loves === 'boolean') {
+            syncLovesRow.input.checked = cfg.sync_loves;
+          }
+          if (cfg.auth_flow) {
+            authFlowRow.select.value = callbackRegistered || cfg.auth_flow !== 'callback' ? cfg.auth_flow : 'loopback';
+            updateAuthFlowRows();
+          }
+          if (typeof cfg.local_server === 'boolean') {
+            localServerRow.input.checked = cfg.local_server;
+          }
+          if ('proxy' in cfg) {
+            proxyRow.input.value = cfg.proxy || '';
+          }
+          if (typeof cfg.control_api === 'boolean') {
+            controlApiRow.input.checked = cfg.control_api;
+          }
+          if (typeof cfg.control_port === 'number') {
+            controlPortRow.input.value = String(cfg.control_port);
+          }
+          if ('now_playing_file' in cfg) {
+            nowFileRow.input.value = cfg.now_playing_file || '';
+          }
+          if (typeof cfg.now_playing_template === 'string') {
+            nowTemplateRow.input.value = cfg.now_playing_template;
+          }
+          if (cfg.widget_theme) {
+            widgetThemeRow.select.value = cfg.widget_theme;
+          }
+          if (typeof cfg.widget_idle_text === 'string') {
+            idleTextRow.input.value = cfg.widget_idle_text;
+          }
+          if (Array.isArray(cfg.webhooks)) {
+            webhooks = cfg.webhooks;
+            renderWebhooks();
+          }
+          if (cfg.mqtt) {
+            const mqtt = cfg.mqtt;
+            const format = mqtt.format === 'topics' ? 'separate topics' : 'JSON';
+            mqttInfo.textContent = mqtt.enabled
+              ? `Publishing to ${mqtt.broker_url} under ${mqtt.topic_prefix}/ (${format}); commands on ${mqtt.topic_prefix}/command.`
+              : 'Off.';
+          }
           if (typeof cfg.enable_notifications === 'boolean') {
             notifyRow.input.checked = cfg.enable_notifications;
           }
@@ -920,40 +2542,42 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
             console.info('[MSCD] Seeded default volume to 0.2');
             const payload = gatherSettings();
             payload.volume_seeded = true;
-            fetch(settingsUrl, {
-              method: 'POST',
-              mode: 'cors',
-              headers: { 'Content-Type': 'application/json' },
-              body: JSON.stringify(payload),
-            }).catch(() => {});
+            backend.updateSettings(payload).catch(() => {});
           } catch (e) {
             console.warn('[MSCD] Failed to seed default volume', e);
           }
         };
 
+        // Out-of-range ports are left out so the rest of the update still saves.
+        const controlPortValue = () => {
+          const port = Number(controlPortRow.input.value);
+          return Number.isInteger(port) && port >= 1024 && port <= 65535 ? port : undefined;
+        };
+
         const gatherSettings = () => ({
           threshold: Math.max(0.01, Math.min(1, Number(thresholdRow.slider.value) / 100)),
           enable_scrobble: scrobbleToggle.input.checked,
           skip_audio_ads: adRow.input.checked,
           skip_promoted: promoRow.input.checked,
+          split_mixes: mixRow.input.checked,
+          sync_loves: syncLovesRow.input.checked,
+          auth_flow: authFlowRow.select.value,
+          local_server: localServerRow.input.checked,
+          control_api: controlApiRow.input.checked,
+          control_port: controlPortValue(),
+          now_playing_file: nowFileRow.input.value.trim(),
+          now_playing_template: nowTemplateRow.input.value,
+          widget_theme: widgetThemeRow.select.value,
+          widget_idle_text: idleTextRow.input.value,
           enable_notifications: notifyRow.input.checked,
           notification_mode: notifyModeRow.select.value,
           volume_seeded: !!(lastAppliedCfg && lastAppliedCfg.volume_seeded),
         });
 
         const loadSettings = async () => {
-          if (!settingsUrl) {
-            console.warn('[MSCD] settingsUrl missing; skipping load');
-            return;
-          }
-          console.info('[MSCD] Loading settings from', settingsUrl);
+          console.info('[MSCD] Loading settings via', useHttp ? settingsUrl : 'ipc');
           try {
-            const res = await fetch(settingsUrl, { method: 'GET', mode: 'cors' });
-            if (!res.ok) {
-              console.warn('[MSCD] settings load failed', res.status);
-              return;
-            }
-            const cfg = await res.json();
+            const cfg = await backend.getSettings();
             console.info('[MSCD] Loaded settings', cfg);
             applySettings(cfg);
             shell.dataset.dirty = '';
@@ -963,36 +2587,10 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
         };
 
         const saveSettings = async () => {
-          if (!settingsUrl) {
-            console.warn('[MSCD] settingsUrl missing; skip save');
-            return;
-          }
           const payload = gatherSettings();
           console.info('[MSCD] Saving settings', payload);
-          let sent = false;
-          try {
-            if (navigator.sendBeacon) {
-              const blob = new Blob([JSON.stringify(payload)], { type: 'application/json' });
-              sent = navigator.sendBeacon(settingsUrl, blob);
-              if (sent) {
-                console.info('[MSCD] settings sent via sendBeacon');
-              }
-            }
-          } catch (e) {
-            console.warn('[MSCD] sendBeacon settings failed', e);
-          }
           try {
-            const res = await fetch(settingsUrl, {
-              method: 'POST',
-              mode: 'cors',
-              headers: { 'Content-Type': 'application/json' },
-              body: JSON.stringify(payload),
-            });
-            if (!res.ok) {
-              console.warn('[MSCD] settings save failed', res.status);
-              return;
-            }
-            const cfg = await res.json();
+            const cfg = await backend.updateSettings(payload);
             applySettings(cfg);
           } catch (err) {
             console.warn('[MSCD] settings save error', err);
@@ -1011,8 +2609,22 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
           scrobbleToggle.input.addEventListener('change', () => { markDirty(); saveSettings(); });
           adRow.input.addEventListener('change', () => { markDirty(); saveSettings(); });
           promoRow.input.addEventListener('change', () => { markDirty(); saveSettings(); });
+          mixRow.input.addEventListener('change', () => { markDirty(); saveSettings(); });
+          syncLovesRow.input.addEventListener('change', () => {
+            markDirty();
+            saveSettings();
+            if (syncLovesRow.input.checked) setTimeout(() => runLoveSync(false), 2000);
+          });
           notifyRow.input.addEventListener('change', () => { markDirty(); saveSettings(); });
           notifyModeRow.select.addEventListener('change', () => { markDirty(); saveSettings(); });
+          authFlowRow.select.addEventListener('change', () => { updateAuthFlowRows(); markDirty(); saveSettings(); });
+          localServerRow.input.addEventListener('change', () => { markDirty(); saveSettings(); });
+          controlApiRow.input.addEventListener('change', () => { markDirty(); saveSettings(); });
+          controlPortRow.input.addEventListener('change', () => { markDirty(); saveSettings(); });
+          nowFileRow.input.addEventListener('change', () => { markDirty(); saveSettings(); });
+          nowTemplateRow.input.addEventListener('change', () => { markDirty(); saveSettings(); });
+          widgetThemeRow.select.addEventListener('change', () => { markDirty(); saveSettings(); });
+          idleTextRow.input.addEventListener('change', () => { markDirty(); saveSettings(); });
         };
 
         if (initialSettings && typeof initialSettings === 'object') {
@@ -1026,7 +2638,7 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
         // Attach immediately so UI reacts even if load stalls.
         attachHandlers();
         startScrobbleObserver();
-        startEventPoller();
+        startEventStream();
 
         // Then hydrate from persisted settings.
         loadSettings();
@@ -1053,6 +2665,7 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
     .replace("{key}", lastfm_key)
     .replace("{version}", version)
     .replace("{playback_url}", playback_url)
+    .replace("{server_token}", server_token)
     .replace("{initial_settings}", initial_settings)
 }
 
@@ -1061,6 +2674,12 @@ fn build_overlay_script(lastfm_key: &str, lastfm_callback: &str, version: &str,
 struct PersistedState {
   session: Option<LastfmSession>,
   scrobble_config: ScrobbleConfig,
+  submissions: Vec<SubmittedScrobble>,
+  profile_cache: Option<ProfileCache>,
+  recent_suggestions: Vec<RecentSuggestion>,
+  love_sync: LoveSyncState,
+  // Bearer token for the control API; created on first use, kept across launches.
+  control_token: Option<String>,
 }
 
 fn store_path() -> Result<PathBuf, String> {
@@ -1079,6 +2698,14 @@ fn webview_data_dir() -> Result<PathBuf, String> {
   Ok(dir.join("webview-data"))
 }
 
+fn now_playing_dir() -> Result<PathBuf, String> {
+  let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+  let dir = exe
+    .parent()
+    .ok_or_else(|| "Failed to resolve exe directory".to_string())?;
+  Ok(dir.join("now-playing"))
+}
+
 fn read_store() -> PersistedState {
   let path = match store_path() {
     Ok(p) => p,
@@ -1101,8 +2728,22 @@ fn write_store(state: &PersistedState) -> Result<(), String> {
   fs::write(&path, payload).map_err(|e| e.to_string())
 }
 
+// Held for every read-modify-write of the store so concurrent writers (scrobbles,
+// verification, settings saves, love sync) don't overwrite each other's changes.
+static STORE_LOCK: Mutex<()> = Mutex::new(());
+
+// The only way to change the store. Never hold across an await: `f` is synchronous.
+fn update_store<T>(f: impl FnOnce(&mut PersistedState) -> T) -> Result<T, String> {
+  let _guard = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+  let mut state = read_store();
+  let out = f(&mut state);
+  write_store(&state)?;
+  Ok(out)
+}
+
+// Usable session only; an expired one is treated like no session at all.
 fn get_lastfm_session(_app: &tauri::AppHandle) -> Option<LastfmSession> {
-  read_store().session
+  read_store().session.filter(|s| !s.expired)
 }
 
 fn load_scrobble_config(_app: &tauri::AppHandle) -> ScrobbleConfig {
@@ -1120,9 +2761,17 @@ fn load_scrobble_config(_app: &tauri::AppHandle) -> ScrobbleConfig {
   cfg
 }
 
-fn save_scrobble_config(_app: &tauri::AppHandle, cfg: &ScrobbleConfig) -> Result<(), String> {
+// Changes the stored config under the store lock, so concurrent saves build on each other.
+fn save_scrobble_config(
+  app: &tauri::AppHandle,
+  update: impl FnOnce(&mut ScrobbleConfig),
+) -> Result<ScrobbleConfig, String> {
+  let cfg = update_store(|state| {
+    update(&mut state.scrobble_config);
+    state.scrobble_config.clone()
+  })?;
   log::info!(
-    "[Settings] Saving scrobble_config threshold={} scrobble={} skip_audio_ads={} skip_promoted={} notifications={} mode={:?} volume_seeded={}",
+    "[Settings] Saved scrobble_config threshold={} scrobble={} skip_audio_ads={} skip_promoted={} notifications={} mode={:?} volume_seeded={}",
     cfg.threshold,
     cfg.enable_scrobble,
     cfg.skip_audio_ads,
@@ -1131,9 +2780,9 @@ fn save_scrobble_config(_app: &tauri::AppHandle, cfg: &ScrobbleConfig) -> Result
     cfg.notification_mode,
     cfg.volume_seeded
   );
-  let mut state = read_store();
-  state.scrobble_config = cfg.clone();
-  write_store(&state)
+  // Keeps settings modals in other windows (and the sender) in sync.
+  publish(app, "settings", &redacted(cfg.clone()));
+  Ok(cfg)
 }
 
 #[tauri::command]
@@ -1148,7 +2797,27 @@ async fn open_external(app: tauri::AppHandle, url: String) -> Result<(), String>
 struct LastfmSession {
   session_key: String,
   username: String,
+  // Set when Last.fm rejected the key (revoked access); cleared by reconnecting.
+  #[serde(default)]
+  expired: bool,
+}
+
+// What the webview may see of a session; the key itself is never serialized to it.
+#[derive(Debug, Clone, serde::Serialize)]
+struct SessionView {
+  username: String,
+  expired: bool,
+}
+
+impl From<&LastfmSession> for SessionView {
+  fn from(session: &LastfmSession) -> Self {
+    Self {
+      username: session.username.clone(),
+      expired: session.expired,
+    }
+  }
 }
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(default)]
 struct ScrobbleConfig {
@@ -1159,6 +2828,29 @@ struct ScrobbleConfig {
   enable_notifications: bool,
   notification_mode: NotificationMode,
   volume_seeded: bool,
+  split_mixes: bool,
+  sync_loves: bool,
+  auth_flow: AuthFlow,
+  // Proxy for outbound requests (http://, https://, socks5:// or socks5h://). When
+  // unset, HTTP_PROXY/HTTPS_PROXY/ALL_PROXY from the environment apply. Only set by
+  // editing the store, since settings updates come from page script; read at startup.
+  proxy: Option<String>,
+  // Serve the overlay over the loopback HTTP server instead of Tauri IPC. Read at startup.
+  local_server: bool,
+  // Local control API (now playing, player controls) on 127.0.0.1:control_port.
+  control_api: bool,
+  control_port: u16,
+  // Now-playing outputs: a text file (a name in the app's now-playing folder)
+  // rendered from the template, and the control API's widget.
+  now_playing_file: Option<String>,
+  now_playing_template: String,
+  widget_theme: WidgetTheme,
+  widget_idle_text: String,
+  // Only set by editing the store: page script must not choose where listening
+  // history is sent.
+  webhooks: Vec<Webhook>,
+  // Store only, like webhooks; applied at startup and on "Reconnect".
+  mqtt: MqttConfig,
 }
 
 impl Default for ScrobbleConfig {
@@ -1171,6 +2863,19 @@ impl Default for ScrobbleConfig {
       enable_notifications: true,
       notification_mode: NotificationMode::InApp,
       volume_seeded: false,
+      split_mixes: true,
+      sync_loves: false,
+      auth_flow: AuthFlow::Callback,
+      proxy: None,
+      local_server: false,
+      control_api: false,
+      control_port: DEFAULT_CONTROL_PORT,
+      now_playing_file: None,
+      now_playing_template: now_playing::DEFAULT_TEMPLATE.to_string(),
+      widget_theme: WidgetTheme::Dark,
+      widget_idle_text: now_playing::DEFAULT_IDLE_TEXT.to_string(),
+      webhooks: Vec::new(),
+      mqtt: MqttConfig::default(),
     }
   }
 }
@@ -1185,6 +2890,17 @@ struct ScrobbleConfigUpdate {
   enable_notifications: Option<bool>,
   notification_mode: Option<NotificationMode>,
   volume_seeded: Option<bool>,
+  split_mixes: Option<bool>,
+  sync_loves: Option<bool>,
+  auth_flow: Option<AuthFlow>,
+  local_server: Option<bool>,
+  control_api: Option<bool>,
+  control_port: Option<u16>,
+  // File name only; empty string stops writing the file.
+  now_playing_file: Option<String>,
+  now_playing_template: Option<String>,
+  widget_theme: Option<WidgetTheme>,
+  widget_idle_text: Option<String>,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Default, PartialEq, Eq)]
@@ -1195,6 +2911,32 @@ enum NotificationMode {
   System,
 }
 
+// How "Connect" signs in: Last.fm redirects to the configured callback (the mscd://
+// deep link by default), to a loopback server started for that sign-in, the desktop
+// flow where we poll auth.getSession for an approved token, or a username/password
+// exchange (auth.getMobileSession) for machines without a browser.
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum AuthFlow {
+  #[default]
+  Callback,
+  Loopback,
+  Desktop,
+  Mobile,
+}
+
+#[derive(Debug, Default)]
+struct AuthState {
+  // False when the mscd:// handler could not be registered.
+  callback_registered: bool,
+  // auth.getToken token of the desktop flow while it waits for approval.
+  desktop_token: Option<String>,
+  // Browser flow started from "Connect in browser"; callbacks must echo its nonce.
+  pending: Option<PendingAuth>,
+  // Loopback server of the pending browser flow, if it uses one.
+  callback_server: Option<tauri::async_runtime::JoinHandle<()>>,
+}
+
 #[derive(Debug, Clone, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct PlaybackPayload {
@@ -1207,6 +2949,13 @@ struct PlaybackPayload {
   position_ms: u64,
   paused: bool,
   ts: u64,
+  // Description and comment text from the track page, sent once per track for tracklist parsing.
+  #[serde(default)]
+  description: Option<String>,
+  #[serde(default)]
+  comments: Option<String>,
+  #[serde(default)]
+  artwork_url: Option<String>,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -1221,27 +2970,194 @@ struct TrackState {
   last_pos_ms: u64,
   last_update_ts_ms: u64,
   scrobbled: bool,
+  mix: Option<MixProgress>,
+}
+
+fn threshold_ms(duration_ms: u64, threshold: f32) -> u64 {
+  (duration_ms as f32 * threshold).round() as u64
+}
+
+// The current track as the control API reports it; `progress` runs from 0 to 1
+// toward the scrobble threshold.
+#[derive(Debug, serde::Serialize)]
+struct NowPlaying {
+  track_id: String,
+  title: String,
+  artist: String,
+  album: Option<String>,
+  duration_ms: u64,
+  started_at: u64,
+  position_ms: u64,
+  listened_ms: u64,
+  threshold_ms: u64,
+  progress: f32,
+  scrobbled: bool,
+  mix: bool,
+}
+
+impl NowPlaying {
+  fn new(track: &TrackState, threshold: f32) -> Self {
+    let threshold_ms = threshold_ms(track.duration_ms, threshold);
+    let progress = if threshold_ms == 0 {
+      0.0
+    } else {
+      (track.listened_ms as f32 / threshold_ms as f32).min(1.0)
+    };
+    Self {
+      track_id: track.track_id.clone(),
+      title: track.title.clone(),
+      artist: track.artist.clone(),
+      album: track.album.clone(),
+      duration_ms: track.duration_ms,
+      started_at: track.started_at,
+      position_ms: track.last_pos_ms,
+      listened_ms: track.listened_ms,
+      threshold_ms,
+      progress,
+      scrobbled: track.scrobbled,
+      mix: track.mix.is_some(),
+    }
+  }
 }
 
 #[derive(Default)]
 struct ScrobbleState {
   current: Option<TrackState>,
+  // Toasts raised while no overlay was listening; sent when the next one attaches.
   events: std::collections::VecDeque<ToastEvent>,
+  // An overlay listens for IPC events (set by `take_toasts`, cleared when the page navigates).
+  overlay_attached: bool,
+  // When the overlay last reported playback, scrobbling on or off.
+  last_payload_at: Option<u64>,
+  last_scrobble: Option<ScrobbleOutcome>,
+  // Last reported track for the now-playing outputs, and the text file's content.
+  playing: Option<Playing>,
+  now_playing_text: Option<String>,
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-#[serde(rename_all = "snake_case")]
-enum ToastKind {
-  Scrobble,
-  ScrobbleFailed,
+impl ScrobbleState {
+  fn overlay_alive(&self, now: u64) -> bool {
+    self
+      .last_payload_at
+      .is_some_and(|at| now.saturating_sub(at) <= OVERLAY_ALIVE_MS)
+  }
+
+  // What the outputs show: nothing while paused or once the overlay stops reporting.
+  fn active_playing(&self, now: u64) -> Option<&Playing> {
+    let fresh = self.overlay_alive(now);
+    self.playing.as_ref().filter(|p| fresh && !p.paused)
+  }
+
+  // MQTT keeps paused tracks so automations can tell paused from stopped.
+  fn mqtt_state(&self, now: u64) -> NowState {
+    match self.playing.as_ref().filter(|_| self.overlay_alive(now)) {
+      Some(p) => NowState {
+        state: if p.paused { PlayerState::Paused } else { PlayerState::Playing },
+        title: Some(p.title.clone()),
+        artist: Some(p.artist.clone()),
+        album: p.album.clone(),
+        artwork_url: p.artwork_url.clone(),
+        duration_ms: p.duration_ms,
+      },
+      None => NowState::default(),
+    }
+  }
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-struct ToastEvent {
-  kind: ToastKind,
+#[derive(Debug, Clone, serde::Serialize)]
+struct ScrobbleOutcome {
+  at: u64,
   title: String,
   artist: String,
-  #[serde(default)]
+  ok: bool,
+  error: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct OverlayStatus {
+  alive: bool,
+  attached: bool,
+  last_payload_at: Option<u64>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct SessionStatus {
+  connected: bool,
+  username: Option<String>,
+  expired: bool,
+}
+
+// Everything needed to debug a missing scrobble without reading the log.
+#[derive(Debug, serde::Serialize)]
+struct AppStatus {
+  version: String,
+  now: u64,
+  overlay: OverlayStatus,
+  now_playing: Option<NowPlaying>,
+  session: SessionStatus,
+  last_scrobble: Option<ScrobbleOutcome>,
+  config: ScrobbleConfig,
+  config_sources: ConfigSources,
+}
+
+// Settings as anything outside the backend sees them (the overlay runs in page
+// script): no proxy, MQTT or webhook secrets.
+fn redacted(mut cfg: ScrobbleConfig) -> ScrobbleConfig {
+  cfg.proxy = cfg.proxy.as_deref().map(http::redact_proxy);
+  if cfg.mqtt.password.is_some() {
+    cfg.mqtt.password = Some("***".to_string());
+  }
+  for hook in &mut cfg.webhooks {
+    if hook.secret.as_deref().is_some_and(|s| !s.is_empty()) {
+      hook.secret = Some("***".to_string());
+    }
+  }
+  cfg
+}
+
+fn app_status(app: &tauri::AppHandle, state: &Arc<Mutex<ScrobbleState>>) -> AppStatus {
+  let store = read_store();
+  let config = redacted(store.scrobble_config);
+  let now = millis_now();
+  let lock = state.lock().unwrap();
+  AppStatus {
+    version: app.package_info().version.to_string(),
+    now,
+    overlay: OverlayStatus {
+      alive: lock.overlay_alive(now),
+      attached: lock.overlay_attached,
+      last_payload_at: lock.last_payload_at,
+    },
+    now_playing: lock
+      .current
+      .as_ref()
+      .map(|track| NowPlaying::new(track, config.threshold)),
+    session: SessionStatus {
+      connected: store.session.is_some(),
+      username: store.session.as_ref().map(|s| s.username.clone()),
+      expired: store.session.as_ref().is_some_and(|s| s.expired),
+    },
+    last_scrobble: lock.last_scrobble.clone(),
+    config,
+    config_sources: config_sources(),
+  }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ToastKind {
+  Scrobble,
+  ScrobbleFailed,
+  ScrobbleMissing,
+  SessionExpired,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct ToastEvent {
+  kind: ToastKind,
+  title: String,
+  artist: String,
+  #[serde(default)]
   message: Option<String>,
 }
 
@@ -1261,14 +3177,50 @@ async fn handle_playback(
   payload: PlaybackPayload,
 ) -> Result<(), String> {
   let cfg = load_scrobble_config(&app);
+  let started = {
+    let mut lock = state.lock().unwrap();
+    lock.last_payload_at = Some(millis_now());
+    let started = lock.playing.as_ref().map_or(true, |p| p.track_id != payload.track_id);
+    lock.playing = Some(Playing {
+      track_id: payload.track_id.clone(),
+      title: payload.title.clone(),
+      artist: payload.artist.clone(),
+      album: payload.album.clone(),
+      artwork_url: payload.artwork_url.as_deref().map(now_playing::large_artwork),
+      duration_ms: payload.duration_ms,
+      position_ms: payload.position_ms,
+      paused: payload.paused,
+    });
+    started
+  };
+  write_now_playing_file(&app, &cfg, false);
+  update_mqtt_state(&app);
+  if started {
+    fire_webhooks(
+      &app,
+      &cfg,
+      WebhookEvent::TrackStarted,
+      webhooks::TrackInfo {
+        track_id: payload.track_id.clone(),
+        title: payload.title.clone(),
+        artist: payload.artist.clone(),
+        album: payload.album.clone(),
+        duration_ms: payload.duration_ms,
+        started_at: millis_now().saturating_sub(payload.position_ms),
+        ..webhooks::TrackInfo::default()
+      },
+      None,
+    );
+  }
   if !cfg.enable_scrobble {
     log::info!("[Settings] Scrobbling disabled; skipping playback report");
     return Ok(());
   }
 
-  if payload.duration_ms == 0 || payload.title.is_empty() || payload.artist.is_empty() {
+  if let Err(reason) = validate_metadata(&payload.title, &payload.artist, payload.duration_ms) {
     log::info!(
-      "[Last.fm] report_playback skipped missing data title='{}' artist='{}' duration_ms={}",
+      "[Last.fm] report_playback skipped: {} title='{}' artist='{}' duration_ms={}",
+      reason,
       payload.title,
       payload.artist,
       payload.duration_ms
@@ -1276,384 +3228,2058 @@ async fn handle_playback(
     return Ok(());
   }
 
-  let api_key = match lastfm_key() {
-    Some(k) => k,
-    None => {
+  let client = match lastfm_client(&app) {
+    Ok(c) if c.can_sign() => c,
+    Ok(_) => {
+      log::info!("[Last.fm] report_playback skipped: api secret missing");
+      return Ok(());
+    }
+    Err(_) => {
       log::info!("[Last.fm] report_playback skipped: api key missing");
       return Ok(());
     }
-  };
-  let api_secret = match lastfm_secret() {
-    Some(s) => s,
-    None => {
-      log::info!("[Last.fm] report_playback skipped: api secret missing");
-      return Ok(());
+  };
+  let session = match get_lastfm_session(&app) {
+    Some(s) => s,
+    None => {
+      log::info!("[Last.fm] report_playback skipped: no session");
+      return Ok(());
+    }
+  };
+
+  let scrobbles_to_send = {
+    let mut state_lock = state.lock().unwrap();
+    let mut scrobbles_to_send: Vec<TrackState> = Vec::new();
+
+    let is_new_track = match &state_lock.current {
+      Some(t) => t.track_id != payload.track_id,
+      None => true,
+    };
+
+    if is_new_track {
+      log::info!(
+        "[Last.fm] new track detected: '{}' by '{}' ({} ms)",
+        payload.title,
+        payload.artist,
+        payload.duration_ms
+      );
+      log::info!(
+        "[Settings] Using threshold={} skip_audio_ads={} skip_promoted={} notifications={}",
+        cfg.threshold,
+        cfg.skip_audio_ads,
+        cfg.skip_promoted,
+        cfg.enable_notifications
+      );
+      let t = TrackState {
+        track_id: payload.track_id.clone(),
+        title: payload.title.clone(),
+        artist: payload.artist.clone(),
+        album: payload.album.clone(),
+        duration_ms: payload.duration_ms,
+        started_at: millis_now().saturating_sub(payload.position_ms),
+        listened_ms: 0,
+        last_pos_ms: payload.position_ms,
+        last_update_ts_ms: payload.ts,
+        scrobbled: false,
+        mix: None,
+      };
+      state_lock.current = Some(t);
+    }
+
+    if let Some(current) = state_lock.current.as_mut() {
+      if cfg.split_mixes && current.mix.is_none() {
+        // The uploader's description wins; comments are only a fallback.
+        let entries = payload
+          .description
+          .as_deref()
+          .and_then(tracklist::parse_tracklist)
+          .or_else(|| payload.comments.as_deref().and_then(tracklist::parse_tracklist));
+        if let Some(entries) = entries {
+          log::info!(
+            "[Last.fm] mix mode for '{}': {} tracklist entries, scrobbling sub-tracks only",
+            current.title,
+            entries.len()
+          );
+          current.mix = Some(MixProgress::new(entries, current.duration_ms));
+        }
+      }
+    }
+
+    if let Some(current) = state_lock.current.as_mut().filter(|_| !is_new_track) {
+      let delta_pos = payload.position_ms.saturating_sub(current.last_pos_ms);
+      let delta_time = payload.ts.saturating_sub(current.last_update_ts_ms);
+
+      // Only count real listened time; if the position jump is larger than elapsed wall time,
+      // treat it as a seek and cap by delta_time.
+      let mut increment = 0;
+      if !payload.paused {
+        increment = if delta_pos > delta_time.saturating_add(1_500) {
+          delta_time
+        } else {
+          delta_pos
+        };
+        current.listened_ms = current.listened_ms.saturating_add(increment);
+      }
+      current.last_pos_ms = payload.position_ms;
+      current.last_update_ts_ms = payload.ts;
+
+      let threshold_ms = threshold_ms(current.duration_ms, cfg.threshold);
+      if let Some(mix) = current.mix.as_mut() {
+        // Mixes are never scrobbled as a whole; each tracklist entry has its own threshold.
+        if !payload.paused {
+          if let Some(hit) = mix.advance(payload.position_ms, increment, cfg.threshold, millis_now()) {
+            log::info!(
+              "[Last.fm] mix sub-track {} threshold met: '{}' by '{}' ({} ms)",
+              hit.index,
+              hit.title,
+              hit.artist,
+              hit.duration_ms
+            );
+            scrobbles_to_send.push(TrackState {
+              track_id: format!("{}#{}", current.track_id, hit.index),
+              title: hit.title,
+              artist: hit.artist,
+              album: None,
+              duration_ms: hit.duration_ms,
+              started_at: hit.started_at,
+              scrobbled: true,
+              ..TrackState::default()
+            });
+          }
+        }
+      } else if !current.scrobbled && current.listened_ms >= threshold_ms && current.duration_ms > 0 {
+        current.scrobbled = true;
+        scrobbles_to_send.push(current.clone());
+        log::info!(
+          "[Last.fm] threshold met for '{}' listened_ms={} threshold_ms={}",
+          current.title,
+          current.listened_ms,
+          threshold_ms
+        );
+      }
+    }
+
+    scrobbles_to_send
+  };
+
+  for track in scrobbles_to_send {
+    fire_webhooks(&app, &cfg, WebhookEvent::ThresholdReached, track_info(&track), None);
+    let _ = submit_scrobble(&app, state, &cfg, &client, &session, &track).await;
+  }
+
+  Ok(())
+}
+
+fn validate_metadata(title: &str, artist: &str, duration_ms: u64) -> Result<(), String> {
+  if title.trim().is_empty() || artist.trim().is_empty() || duration_ms == 0 {
+    return Err("missing data".to_string());
+  }
+  Ok(())
+}
+
+fn validate_scrobble(track: &TrackState, now_ms: u64) -> Result<(), String> {
+  validate_metadata(&track.title, &track.artist, track.duration_ms)
+    .map_err(|_| "artist, title and duration are required".to_string())?;
+  let ts = track.started_at / 1000;
+  if ts.saturating_add(scrobble_log::MAX_SCROBBLE_AGE_SECS) <= now_ms / 1000 {
+    return Err("timestamp is older than Last.fm's 14-day limit".to_string());
+  }
+  if ts > now_ms / 1000 + 60 {
+    return Err("timestamp is in the future".to_string());
+  }
+  Ok(())
+}
+
+// Shared by automatic and manual scrobbles: validate, send, log the submission and notify.
+async fn submit_scrobble(
+  app: &tauri::AppHandle,
+  state: &Arc<Mutex<ScrobbleState>>,
+  cfg: &ScrobbleConfig,
+  client: &LastfmClient,
+  session: &LastfmSession,
+  track: &TrackState,
+) -> Result<(), String> {
+  let result = match validate_scrobble(track, millis_now()) {
+    Ok(()) => send_scrobble(client, session, track).await,
+    Err(err) => Err(err),
+  };
+  state.lock().unwrap().last_scrobble = Some(ScrobbleOutcome {
+    at: millis_now(),
+    title: track.title.clone(),
+    artist: track.artist.clone(),
+    ok: result.is_ok(),
+    error: result.as_ref().err().cloned(),
+  });
+  let event = match &result {
+    Ok(_) => WebhookEvent::ScrobbleSucceeded,
+    Err(_) => WebhookEvent::ScrobbleFailed,
+  };
+  fire_webhooks(app, cfg, event, track_info(track), result.as_ref().err().cloned());
+  match &result {
+    Ok(_) => {
+      log::info!("[Last.fm] scrobbled '{}'", track.title);
+      record_submission(track);
+      notify(app, state, cfg, ToastEvent {
+        kind: ToastKind::Scrobble,
+        title: track.title.clone(),
+        artist: track.artist.clone(),
+        message: None,
+      });
+    }
+    Err(err) => {
+      log::warn!("[Last.fm] scrobble failed: {}", err);
+      notify(app, state, cfg, ToastEvent {
+        kind: ToastKind::ScrobbleFailed,
+        title: track.title.clone(),
+        artist: track.artist.clone(),
+        message: Some(err.clone()),
+      });
+    }
+  }
+  result
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ManualScrobble {
+  artist: String,
+  title: String,
+  #[serde(default)]
+  album: Option<String>,
+  // Unix seconds; defaults to now.
+  #[serde(default)]
+  timestamp: Option<u64>,
+  duration_ms: u64,
+  #[serde(default)]
+  dry_run: bool,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct ManualScrobbleResult {
+  dry_run: bool,
+  // track.scrobble parameters as they would be sent, only for dry runs. The session
+  // key is masked (page script can call this command); without it the signature
+  // cannot be replayed, so api_sig is left intact for debugging signing.
+  params: Vec<(String, String)>,
+}
+
+#[tauri::command]
+async fn manual_scrobble(
+  app: tauri::AppHandle,
+  state: tauri::State<'_, Arc<Mutex<ScrobbleState>>>,
+  scrobble: ManualScrobble,
+) -> Result<ManualScrobbleResult, String> {
+  let client = lastfm_client(&app)?;
+  let session = get_lastfm_session(&app).ok_or("Connect Last.fm first")?;
+  let now = millis_now();
+  let track = TrackState {
+    track_id: "manual".to_string(),
+    title: scrobble.title.trim().to_string(),
+    artist: scrobble.artist.trim().to_string(),
+    album: scrobble.album.map(|a| a.trim().to_string()).filter(|a| !a.is_empty()),
+    duration_ms: scrobble.duration_ms,
+    started_at: scrobble.timestamp.map(|t| t * 1000).unwrap_or(now),
+    scrobbled: true,
+    ..TrackState::default()
+  };
+  log::info!(
+    "[Last.fm] manual scrobble '{}' by '{}' @ {} dry_run={}",
+    track.title,
+    track.artist,
+    track.started_at / 1000,
+    scrobble.dry_run
+  );
+
+  if scrobble.dry_run {
+    validate_scrobble(&track, now)?;
+    let owned = scrobble_params(std::slice::from_ref(&track));
+    let params = owned.iter().map(|(k, v)| (k.as_str(), v.clone())).collect();
+    let signed = client.signed_params("track.scrobble", params, Some(&session.session_key))?;
+    return Ok(ManualScrobbleResult {
+      dry_run: true,
+      params: signed
+        .into_iter()
+        .map(|(k, v)| match k {
+          "sk" => (k.to_string(), "***".to_string()),
+          _ => (k.to_string(), v),
+        })
+        .collect(),
+    });
+  }
+
+  let cfg = load_scrobble_config(&app);
+  submit_scrobble(&app, &state, &cfg, &client, &session, &track).await?;
+  Ok(ManualScrobbleResult {
+    dry_run: false,
+    params: Vec::new(),
+  })
+}
+
+// Rewrites the now-playing text file when its content changed (or always, after a
+// settings change, in case the name did). Stored paths from older versions are
+// ignored.
+fn write_now_playing_file(app: &tauri::AppHandle, cfg: &ScrobbleConfig, force: bool) {
+  let path = match cfg.now_playing_file.as_deref().map(now_playing::file_name) {
+    Some(Ok(name)) => match now_playing_dir() {
+      Ok(dir) => dir.join(name),
+      Err(err) => {
+        log::warn!("[NowPlaying] {}", err);
+        return;
+      }
+    },
+    Some(Err(err)) => {
+      if force {
+        log::warn!("[NowPlaying] Not writing: {}", err);
+      }
+      return;
+    }
+    None => return,
+  };
+  let state = app.state::<Arc<Mutex<ScrobbleState>>>();
+  let text = {
+    let mut lock = state.lock().unwrap();
+    let text = now_playing::text(
+      lock.active_playing(millis_now()),
+      &cfg.now_playing_template,
+      &cfg.widget_idle_text,
+    );
+    if !force && lock.now_playing_text.as_deref() == Some(text.as_str()) {
+      return;
+    }
+    lock.now_playing_text = Some(text.clone());
+    text
+  };
+  let written = fs::create_dir_all(path.parent().unwrap_or(&path))
+    .map_err(|e| e.to_string())
+    .and_then(|_| now_playing::write_text(&path, &text));
+  if let Err(err) = written {
+    log::warn!("[NowPlaying] Failed to write {}: {}", path.display(), err);
+  }
+}
+
+// Hands the latest state to the MQTT client, which publishes only on change.
+fn update_mqtt_state(app: &tauri::AppHandle) {
+  let state = app.state::<Arc<Mutex<ScrobbleState>>>().lock().unwrap().mqtt_state(millis_now());
+  if let Some((_, states)) = &app.state::<Mutex<MqttPublisher>>().lock().unwrap().running {
+    states.send_if_modified(|current| {
+      if *current == state {
+        return false;
+      }
+      *current = state;
+      true
+    });
+  }
+}
+
+fn start_mqtt_refresh(app: tauri::AppHandle) {
+  tauri::async_runtime::spawn(async move {
+    loop {
+      tokio::time::sleep(Duration::from_secs(MQTT_REFRESH_SECS)).await;
+      update_mqtt_state(&app);
+    }
+  });
+}
+
+fn track_info(track: &TrackState) -> webhooks::TrackInfo {
+  webhooks::TrackInfo {
+    track_id: track.track_id.clone(),
+    title: track.title.clone(),
+    artist: track.artist.clone(),
+    album: track.album.clone(),
+    duration_ms: track.duration_ms,
+    started_at: track.started_at,
+    listened_ms: track.listened_ms,
+    scrobbled: track.scrobbled,
+  }
+}
+
+// Sends `event` to every enabled hook that wants it, each on its own task so slow
+// receivers and retries never hold up playback.
+fn fire_webhooks(
+  app: &tauri::AppHandle,
+  cfg: &ScrobbleConfig,
+  event: WebhookEvent,
+  track: webhooks::TrackInfo,
+  error: Option<String>,
+) {
+  let hooks: Vec<Webhook> = cfg.webhooks.iter().filter(|h| h.wants(event)).cloned().collect();
+  if hooks.is_empty() {
+    return;
+  }
+  let payload = webhooks::Payload {
+    event,
+    timestamp: millis_now(),
+    track,
+    error,
+  };
+  let client = app.state::<reqwest::Client>().inner().clone();
+  for hook in hooks {
+    let client = client.clone();
+    let payload = payload.clone();
+    tauri::async_runtime::spawn(async move {
+      match webhooks::deliver(&client, &hook, &payload, webhooks::RetryPolicy::default()).await {
+        Ok(status) => log::info!("[Webhook] {} delivered (HTTP {})", event.as_str(), status),
+        Err(err) => log::warn!("[Webhook] {} not delivered: {}", event.as_str(), err),
+      }
+    });
+  }
+}
+
+// Pushes an event to the overlay as a Tauri event (`mscd:<name>`) and to open
+// fallback streams. Returns false when no stream is connected.
+fn publish<T: serde::Serialize>(app: &tauri::AppHandle, name: &'static str, data: &T) -> bool {
+  if let Err(err) = app.emit(&format!("mscd:{}", name), data) {
+    log::warn!("[Events] Failed to emit {}: {}", name, err);
+  }
+  app.state::<EventHub>().publish(ServerEvent::new(name, data))
+}
+
+// Session changes for the overlay's status line.
+fn publish_session(app: &tauri::AppHandle, session: Option<&LastfmSession>) {
+  publish(app, "session", &session.map(SessionView::from));
+}
+
+// Toasts go straight to a listening overlay, or wait for the next one to attach.
+fn push_toast(app: &tauri::AppHandle, state: &Arc<Mutex<ScrobbleState>>, event: ToastEvent) {
+  if publish(app, "toast", &event) {
+    return;
+  }
+  let mut lock = state.lock().unwrap();
+  if lock.overlay_attached {
+    return;
+  }
+  lock.events.push_back(event);
+  while lock.events.len() > TOAST_BACKLOG_LIMIT {
+    lock.events.pop_front();
+  }
+}
+
+fn notify(app: &tauri::AppHandle, state: &Arc<Mutex<ScrobbleState>>, cfg: &ScrobbleConfig, event: ToastEvent) {
+  if !cfg.enable_notifications {
+    return;
+  }
+  match cfg.notification_mode {
+    NotificationMode::InApp => push_toast(app, state, event),
+    NotificationMode::System => {
+      let title = match event.kind {
+        ToastKind::Scrobble => "Scrobbled",
+        ToastKind::ScrobbleFailed => "Scrobble failed",
+        ToastKind::ScrobbleMissing => "Scrobble not recorded by Last.fm",
+        ToastKind::SessionExpired => "Last.fm session expired",
+      };
+      let _ = app
+        .notification()
+        .builder()
+        .title(title)
+        .body(format!("{} — {}", event.title, event.artist))
+        .show();
+    }
+  }
+}
+
+fn record_submission(track: &TrackState) {
+  let now = millis_now();
+  let entry = SubmittedScrobble {
+    artist: track.artist.clone(),
+    title: track.title.clone(),
+    album: track.album.clone(),
+    duration_ms: track.duration_ms,
+    timestamp: track.started_at / 1000,
+    submitted_at: now,
+    status: SubmissionStatus::Pending,
+    attempts: 0,
+  };
+  if let Err(err) = update_store(|store| scrobble_log::record(&mut store.submissions, entry, now)) {
+    log::warn!("[Last.fm] Failed to persist submission log: {}", err);
+  }
+}
+
+impl From<&SubmittedScrobble> for TrackState {
+  fn from(entry: &SubmittedScrobble) -> Self {
+    TrackState {
+      track_id: format!("resubmit:{}", entry.timestamp),
+      title: entry.title.clone(),
+      artist: entry.artist.clone(),
+      album: entry.album.clone(),
+      duration_ms: entry.duration_ms,
+      started_at: entry.timestamp * 1000,
+      scrobbled: true,
+      ..TrackState::default()
+    }
+  }
+}
+
+// Checks pending submissions against user.getRecentTracks; returns how many turned out missing.
+async fn verify_submissions(app: &tauri::AppHandle, state: &Arc<Mutex<ScrobbleState>>) -> Result<usize, String> {
+  let client = lastfm_client(app)?;
+  let session = get_lastfm_session(app).ok_or("no session")?;
+  let now = millis_now();
+  let from = match scrobble_log::awaiting_verification(&read_store().submissions, now) {
+    Some(ts) => ts,
+    None => return Ok(0),
+  };
+
+  let mut played = Vec::new();
+  let mut page = 1;
+  let covered_from = loop {
+    let body = client.get(
+      "user.getRecentTracks",
+      vec![
+        ("user", session.username.clone()),
+        ("from", from.to_string()),
+        ("limit", "200".to_string()),
+        ("page", page.to_string()),
+      ],
+    )
+    .await?;
+    let (tracks, total_pages) = lastfm::parse_recent_tracks(&body);
+    played.extend(tracks);
+    if page >= total_pages {
+      break from;
+    }
+    if page >= VERIFY_MAX_PAGES {
+      // Pages run newest to oldest; anything before the last fetched play is unknown.
+      break played.iter().filter_map(|t| t.timestamp).min().unwrap_or(u64::MAX);
+    }
+    page += 1;
+  };
+
+  let missing = update_store(|store| scrobble_log::reconcile(&mut store.submissions, &played, covered_from, now))?;
+
+  if !missing.is_empty() {
+    log::warn!("[Last.fm] {} submitted scrobbles missing from recent tracks", missing.len());
+    let cfg = load_scrobble_config(app);
+    for entry in &missing {
+      notify(app, state, &cfg, ToastEvent {
+        kind: ToastKind::ScrobbleMissing,
+        title: entry.title.clone(),
+        artist: entry.artist.clone(),
+        message: Some("Last.fm did not record this play. Resubmit it from Settings.".to_string()),
+      });
+    }
+  }
+  Ok(missing.len())
+}
+
+fn start_scrobble_verifier(app: tauri::AppHandle, state: Arc<Mutex<ScrobbleState>>) {
+  tauri::async_runtime::spawn(async move {
+    // First pass shortly after launch picks up submissions from the previous session.
+    let mut delay = Duration::from_secs(60);
+    loop {
+      tokio::time::sleep(delay).await;
+      delay = Duration::from_secs(VERIFY_INTERVAL_SECS);
+      if let Err(err) = verify_submissions(&app, &state).await {
+        log::info!("[Last.fm] Scrobble verification skipped: {}", err);
+      }
+    }
+  });
+}
+
+// Validates the stored session with a cheap authenticated call (user.getInfo). Network
+// trouble leaves the session alone; only Last.fm's "invalid session key" expires it.
+async fn check_session(app: &tauri::AppHandle, state: &Arc<Mutex<ScrobbleState>>) -> Result<(), String> {
+  let session = get_lastfm_session(app).ok_or("no active session")?;
+  let client = lastfm_client(app)?;
+  let body = client
+    .get_signed_raw("user.getInfo", Vec::new(), Some(&session.session_key))
+    .await?;
+  match lastfm::error_code(&body) {
+    None => {
+      log::info!("[Last.fm] Session for {} is valid", session.username);
+      Ok(())
+    }
+    Some(lastfm::ERROR_INVALID_SESSION) => {
+      log::warn!("[Last.fm] Session for {} was rejected; marking expired", session.username);
+      // The user may have reconnected while the check was in flight.
+      let expired = update_store(|store| match store.session.as_mut() {
+        Some(stored) if stored.session_key == session.session_key => {
+          stored.expired = true;
+          Some(stored.clone())
+        }
+        _ => None,
+      })?;
+      if expired.is_none() {
+        return Ok(());
+      }
+      publish_session(app, expired.as_ref());
+      // Always in-app: the toast carries the reconnect button, and this fires once per expiry.
+      push_toast(
+        app,
+        state,
+        ToastEvent {
+          kind: ToastKind::SessionExpired,
+          title: "Last.fm access was revoked or expired".to_string(),
+          artist: session.username,
+          message: Some("Reconnect to keep scrobbling.".to_string()),
+        },
+      );
+      Ok(())
+    }
+    Some(_) => Err(lastfm::error_message(&body).unwrap_or_default()),
+  }
+}
+
+fn start_session_monitor(app: tauri::AppHandle, state: Arc<Mutex<ScrobbleState>>) {
+  tauri::async_runtime::spawn(async move {
+    let mut delay = Duration::from_secs(5);
+    loop {
+      tokio::time::sleep(delay).await;
+      delay = Duration::from_secs(SESSION_CHECK_INTERVAL_SECS);
+      if let Err(err) = check_session(&app, &state).await {
+        log::info!("[Last.fm] Session check skipped: {}", err);
+      }
+    }
+  });
+}
+
+#[tauri::command]
+async fn verify_scrobbles(
+  app: tauri::AppHandle,
+  state: tauri::State<'_, Arc<Mutex<ScrobbleState>>>,
+) -> Result<usize, String> {
+  verify_submissions(&app, &state).await
+}
+
+#[tauri::command]
+async fn get_missing_scrobbles(_app: tauri::AppHandle) -> Result<Vec<SubmittedScrobble>, String> {
+  Ok(
+    read_store()
+      .submissions
+      .into_iter()
+      .filter(|e| e.status == SubmissionStatus::Missing)
+      .collect(),
+  )
+}
+
+#[tauri::command]
+async fn resubmit_scrobbles(app: tauri::AppHandle, timestamps: Option<Vec<u64>>) -> Result<usize, String> {
+  let client = lastfm_client(&app)?;
+  let session = get_lastfm_session(&app).ok_or("no session")?;
+  let now = millis_now();
+  let selected: Vec<SubmittedScrobble> = read_store()
+    .submissions
+    .into_iter()
+    .filter(|e| e.status == SubmissionStatus::Missing)
+    .filter(|e| timestamps.as_ref().map(|t| t.contains(&e.timestamp)).unwrap_or(true))
+    .collect();
+
+  let mut resubmitted = 0;
+  let mut last_err = None;
+  for entry in &selected {
+    if entry.is_expired(now) {
+      log::info!("[Last.fm] Not resubmitting '{}': older than 14 days", entry.title);
+      continue;
+    }
+    match send_scrobble(&client, &session, &TrackState::from(entry)).await {
+      Ok(_) => {
+        log::info!("[Last.fm] Resubmitted '{}' @ {}", entry.title, entry.timestamp);
+        record_submission(&TrackState::from(entry));
+        resubmitted += 1;
+      }
+      Err(err) => {
+        log::warn!("[Last.fm] Resubmit of '{}' failed: {}", entry.title, err);
+        last_err = Some(err);
+      }
+    }
+  }
+
+  match last_err {
+    Some(err) if resubmitted == 0 => Err(err),
+    _ => Ok(resubmitted),
+  }
+}
+
+#[tauri::command]
+async fn dismiss_missing_scrobbles(_app: tauri::AppHandle, timestamps: Option<Vec<u64>>) -> Result<(), String> {
+  update_store(|store| {
+    store.submissions.retain(|e| {
+      e.status != SubmissionStatus::Missing
+        || !timestamps.as_ref().map(|t| t.contains(&e.timestamp)).unwrap_or(true)
+    })
+  })
+}
+
+async fn fetch_lastfm_session(client: &LastfmClient, token: &str) -> Result<LastfmSession, String> {
+  log::info!("[Last.fm] Requesting session for token {}", token);
+  let body = client
+    .get_signed("auth.getSession", vec![("token", token.to_string())])
+    .await?;
+  parse_session(body)
+}
+
+fn parse_session(body: serde_json::Value) -> Result<LastfmSession, String> {
+  #[derive(serde::Deserialize)]
+  struct SessionResp {
+    session: SessionInner,
+  }
+  #[derive(serde::Deserialize)]
+  struct SessionInner {
+    name: String,
+    key: String,
+  }
+
+  let body: SessionResp = serde_json::from_value(body).map_err(|e| e.to_string())?;
+  Ok(LastfmSession {
+    session_key: body.session.key,
+    username: body.session.name,
+    expired: false,
+  })
+}
+
+#[tauri::command]
+async fn get_lastfm_status(_app: tauri::AppHandle) -> Result<Option<SessionView>, String> {
+  if let Some(session) = read_store().session {
+    log::info!(
+      "[Last.fm] Returning stored session for user {}{}",
+      session.username,
+      if session.expired { " (expired)" } else { "" }
+    );
+    Ok(Some(SessionView::from(&session)))
+  } else {
+    log::info!("[Last.fm] No session stored");
+    Ok(None)
+  }
+}
+
+#[tauri::command]
+async fn disconnect_lastfm(app: tauri::AppHandle) -> Result<(), String> {
+  update_store(|state| {
+    state.session = None;
+    state.profile_cache = None;
+  })?;
+  publish_session(&app, None);
+  Ok(())
+}
+
+#[derive(Debug, Clone, Copy, Default, serde::Serialize)]
+struct ScrobbleAck {
+  accepted: u64,
+  ignored: u64,
+}
+
+async fn send_scrobble(client: &LastfmClient, session: &LastfmSession, track: &TrackState) -> Result<(), String> {
+  let ack = send_scrobbles(client, session, std::slice::from_ref(track)).await?;
+  if ack.accepted == 0 && ack.ignored > 0 {
+    return Err("Last.fm ignored the scrobble".to_string());
+  }
+  Ok(())
+}
+
+fn scrobble_params(tracks: &[TrackState]) -> Vec<(String, String)> {
+  let plays: Vec<lastfm::ScrobblePlay> = tracks
+    .iter()
+    .map(|track| lastfm::ScrobblePlay {
+      artist: &track.artist,
+      title: &track.title,
+      album: track.album.as_deref(),
+      duration_ms: track.duration_ms,
+      timestamp: track.started_at / 1000,
+    })
+    .collect();
+  lastfm::scrobble_params(&plays)
+}
+
+// Submits up to 50 plays in one track.scrobble request.
+async fn send_scrobbles(client: &LastfmClient, session: &LastfmSession, tracks: &[TrackState]) -> Result<ScrobbleAck, String> {
+  let owned = scrobble_params(tracks);
+  let params = owned.iter().map(|(k, v)| (k.as_str(), v.clone())).collect();
+  let body = client.call("track.scrobble", params, &session.session_key).await?;
+  let attr = body.get("scrobbles").and_then(|s| s.get("@attr"));
+  Ok(ScrobbleAck {
+    accepted: lastfm::number(attr.and_then(|a| a.get("accepted"))).unwrap_or(tracks.len() as u64),
+    ignored: lastfm::number(attr.and_then(|a| a.get("ignored"))).unwrap_or(0),
+  })
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+struct ProfileCache {
+  username: String,
+  info: Option<lastfm::UserInfo>,
+  recent_tracks: Vec<lastfm::RecentTrack>,
+  top_artists: Vec<lastfm::TopArtist>,
+  fetched_at: u64,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct ProfileView {
+  #[serde(flatten)]
+  cache: ProfileCache,
+  // Older than PROFILE_MAX_AGE_MS, or the last refresh failed (e.g. offline).
+  stale: bool,
+  error: Option<String>,
+}
+
+impl ProfileView {
+  fn new(cache: ProfileCache, error: Option<String>) -> Self {
+    let stale = error.is_some() || millis_now().saturating_sub(cache.fetched_at) > PROFILE_MAX_AGE_MS;
+    Self { cache, stale, error }
+  }
+}
+
+fn cached_profile(username: &str) -> Option<ProfileCache> {
+  read_store()
+    .profile_cache
+    .filter(|c| c.username.eq_ignore_ascii_case(username))
+}
+
+async fn fetch_profile(client: &LastfmClient, username: &str) -> Result<ProfileCache, String> {
+  let (info, recent, top) = tokio::try_join!(
+    client.get("user.getInfo", vec![("user", username.to_string())]),
+    client.get(
+      "user.getRecentTracks",
+      vec![("user", username.to_string()), ("limit", PROFILE_RECENT_LIMIT.to_string())],
+    ),
+    client.get(
+      "user.getTopArtists",
+      vec![("user", username.to_string()), ("period", "7day".to_string()), ("limit", "10".to_string())],
+    ),
+  )?;
+  let (mut recent_tracks, _) = lastfm::parse_recent_tracks(&recent);
+  // The now-playing entry comes on top of the requested limit.
+  recent_tracks.truncate(PROFILE_RECENT_LIMIT);
+  Ok(ProfileCache {
+    username: username.to_string(),
+    info: lastfm::parse_user_info(&info),
+    recent_tracks,
+    top_artists: lastfm::parse_top_artists(&top),
+    fetched_at: millis_now(),
+  })
+}
+
+// Cached profile for instant rendering; may be stale.
+#[tauri::command]
+async fn get_lastfm_profile(app: tauri::AppHandle) -> Result<Option<ProfileView>, String> {
+  Ok(
+    get_lastfm_session(&app)
+      .and_then(|session| cached_profile(&session.username))
+      .map(|cache| ProfileView::new(cache, None)),
+  )
+}
+
+#[tauri::command]
+async fn refresh_lastfm_profile(app: tauri::AppHandle) -> Result<ProfileView, String> {
+  let client = lastfm_client(&app)?;
+  let session = get_lastfm_session(&app).ok_or("Connect Last.fm first")?;
+  match fetch_profile(&client, &session.username).await {
+    Ok(cache) => {
+      update_store(|store| store.profile_cache = Some(cache.clone()))?;
+      Ok(ProfileView::new(cache, None))
+    }
+    Err(err) => {
+      log::warn!("[Last.fm] Profile refresh failed: {}", err);
+      match cached_profile(&session.username) {
+        Some(cache) => Ok(ProfileView::new(cache, Some(err))),
+        None => Err(err),
+      }
+    }
+  }
+}
+
+fn lastfm_cache() -> Result<DiskCache, String> {
+  Ok(DiskCache::new(store_path()?.with_file_name(CACHE_PATH)))
+}
+
+#[derive(Debug, serde::Serialize)]
+struct ArtistInfoView {
+  #[serde(flatten)]
+  info: ArtistInfo,
+  fetched_at: u64,
+  // Served from an expired cache entry because Last.fm could not be reached.
+  stale: bool,
+}
+
+// Artist the overlay is showing, falling back to the current TrackState when
+// the overlay did not pass one. The tracked state can lag behind a skip.
+fn current_artist(state: &Mutex<ScrobbleState>, artist: Option<String>) -> Option<String> {
+  let artist = artist.map(|a| a.trim().to_string()).filter(|a| !a.is_empty());
+  artist.or_else(|| {
+    let current = state.lock().unwrap().current.as_ref().map(|t| t.artist.clone());
+    current.map(|a| a.trim().to_string()).filter(|a| !a.is_empty())
+  })
+}
+
+#[tauri::command]
+async fn get_artist_info(
+  app: tauri::AppHandle,
+  state: tauri::State<'_, Arc<Mutex<ScrobbleState>>>,
+  artist: Option<String>,
+) -> Result<Option<ArtistInfoView>, String> {
+  let name = match current_artist(&state, artist) {
+    Some(name) => name,
+    None => return Ok(None),
+  };
+  let username = get_lastfm_session(&app).map(|s| s.username);
+  let key = format!("artist:{}:{}", name.to_lowercase(), username.as_deref().unwrap_or(""));
+  let cache = lastfm_cache()?;
+  let now = millis_now();
+  if let Some((info, fetched_at)) = cache.get::<ArtistInfo>(&key, ARTIST_CACHE_TTL_MS, now) {
+    return Ok(Some(ArtistInfoView { info, fetched_at, stale: false }));
+  }
+
+  let client = lastfm_client(&app)?;
+  let mut info_params = vec![("artist", name.clone()), ("autocorrect", "1".to_string())];
+  if let Some(user) = &username {
+    info_params.push(("username", user.clone()));
+  }
+  let fetched = tokio::try_join!(
+    client.get("artist.getInfo", info_params),
+    client.get(
+      "artist.getTopTags",
+      vec![("artist", name.clone()), ("autocorrect", "1".to_string())],
+    ),
+  );
+  let (info, tags) = match fetched {
+    Ok(v) => v,
+    Err(err) => {
+      log::warn!("[Last.fm] Artist info for '{}' failed: {}", name, err);
+      return match cache.get::<ArtistInfo>(&key, u64::MAX, now) {
+        Some((info, fetched_at)) => Ok(Some(ArtistInfoView { info, fetched_at, stale: true })),
+        None => Err(err),
+      };
+    }
+  };
+  let info = lastfm::parse_artist_info(&info, &tags, ARTIST_MAX_TAGS).ok_or("Last.fm returned no artist info")?;
+  if let Err(err) = cache.put(&key, &info, now) {
+    log::warn!("[Last.fm] Failed to cache artist info: {}", err);
+  }
+  Ok(Some(ArtistInfoView { info, fetched_at: now, stale: false }))
+}
+
+// Same fallback as `current_artist`, for calls that need the track title too.
+fn current_track(
+  state: &Mutex<ScrobbleState>,
+  artist: Option<String>,
+  title: Option<String>,
+) -> Option<(String, String)> {
+  let non_empty = |(artist, title): (String, String)| {
+    let (artist, title) = (artist.trim().to_string(), title.trim().to_string());
+    if artist.is_empty() || title.is_empty() {
+      None
+    } else {
+      Some((artist, title))
+    }
+  };
+  let passed = match (artist, title) {
+    (Some(artist), Some(title)) => non_empty((artist, title)),
+    _ => None,
+  };
+  passed.or_else(|| {
+    let current = state
+      .lock()
+      .unwrap()
+      .current
+      .as_ref()
+      .map(|t| (t.artist.clone(), t.title.clone()));
+    current.and_then(non_empty)
+  })
+}
+
+#[derive(Debug, serde::Serialize)]
+struct TrackTagsView {
+  artist: String,
+  title: String,
+  // The connected user's tags on this track.
+  tags: Vec<String>,
+  // The track's own top tags, then Last.fm's global top tags.
+  suggestions: Vec<String>,
+}
+
+async fn global_top_tags(client: &LastfmClient) -> Vec<String> {
+  let key = "toptags:global";
+  let now = millis_now();
+  let cache = lastfm_cache().ok();
+  if let Some((tags, _)) = cache.as_ref().and_then(|c| c.get::<Vec<String>>(key, TOP_TAGS_TTL_MS, now)) {
+    return tags;
+  }
+  match client.get("tag.getTopTags", Vec::new()).await {
+    Ok(body) => {
+      let tags = lastfm::parse_tags(&body);
+      if let Some(cache) = &cache {
+        if let Err(err) = cache.put(key, &tags, now) {
+          log::warn!("[Last.fm] Failed to cache top tags: {}", err);
+        }
+      }
+      tags
+    }
+    Err(err) => {
+      log::warn!("[Last.fm] Global top tags failed: {}", err);
+      Vec::new()
+    }
+  }
+}
+
+#[tauri::command]
+async fn get_track_tags(
+  app: tauri::AppHandle,
+  state: tauri::State<'_, Arc<Mutex<ScrobbleState>>>,
+  artist: Option<String>,
+  title: Option<String>,
+) -> Result<Option<TrackTagsView>, String> {
+  let (artist, title) = match current_track(&state, artist, title) {
+    Some(track) => track,
+    None => return Ok(None),
+  };
+  let session = get_lastfm_session(&app).ok_or("Connect Last.fm first")?;
+  let client = lastfm_client(&app)?;
+  let track_params = || {
+    vec![
+      ("artist", artist.clone()),
+      ("track", title.clone()),
+      ("autocorrect", "1".to_string()),
+    ]
+  };
+  let mut own_params = track_params();
+  own_params.push(("user", session.username.clone()));
+  let (own, track_top, global) = tokio::join!(
+    client.get("track.getTags", own_params),
+    client.get("track.getTopTags", track_params()),
+    global_top_tags(&client),
+  );
+  let tags = lastfm::clean_tags(lastfm::parse_tags(&own?));
+  // Tracks Last.fm has never seen have no top tags; that is not worth failing over.
+  let track_top = track_top.map(|b| lastfm::parse_tags(&b)).unwrap_or_default();
+  let suggestions = lastfm::clean_tags(track_top.into_iter().chain(global))
+    .into_iter()
+    .filter(|t| !tags.contains(t))
+    .take(TAG_SUGGESTION_LIMIT)
+    .collect();
+  Ok(Some(TrackTagsView { artist, title, tags, suggestions }))
+}
+
+// Applies every change it can and reports the Last.fm error for each one that failed.
+#[tauri::command]
+async fn update_track_tags(
+  app: tauri::AppHandle,
+  artist: String,
+  title: String,
+  add: Vec<String>,
+  remove: Vec<String>,
+) -> Result<(), String> {
+  let session = get_lastfm_session(&app).ok_or("Connect Last.fm first")?;
+  let client = lastfm_client(&app)?;
+  let add = lastfm::clean_tags(add);
+  let remove = lastfm::clean_tags(remove);
+  if add.is_empty() && remove.is_empty() {
+    return Err("No tags to update".into());
+  }
+  let mut errors = Vec::new();
+  for chunk in add.chunks(MAX_TAGS_PER_CALL) {
+    let params = vec![
+      ("artist", artist.clone()),
+      ("track", title.clone()),
+      ("tags", chunk.join(",")),
+    ];
+    if let Err(err) = client.call("track.addTags", params, &session.session_key).await {
+      errors.push(err);
+    }
+  }
+  // track.removeTags takes a single tag per request.
+  for tag in &remove {
+    let params = vec![
+      ("artist", artist.clone()),
+      ("track", title.clone()),
+      ("tag", tag.clone()),
+    ];
+    if let Err(err) = client.call("track.removeTags", params, &session.session_key).await {
+      errors.push(err);
+    }
+  }
+  log::info!(
+    "[Tags] '{}' by '{}': +{:?} -{:?} ({} failed)",
+    title,
+    artist,
+    add,
+    remove,
+    errors.len()
+  );
+  if errors.is_empty() {
+    Ok(())
+  } else {
+    Err(errors.join("; "))
+  }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct RecentSuggestion {
+  name: String,
+  // Artist whose similar list the suggestion came from.
+  #[serde(default)]
+  seed: Option<String>,
+  used_at: u64,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct SimilarArtistsView {
+  artist: String,
+  similar: Vec<SimilarArtist>,
+  recent: Vec<RecentSuggestion>,
+  stale: bool,
+}
+
+#[tauri::command]
+async fn get_similar_artists(
+  app: tauri::AppHandle,
+  state: tauri::State<'_, Arc<Mutex<ScrobbleState>>>,
+  artist: Option<String>,
+) -> Result<Option<SimilarArtistsView>, String> {
+  let name = match current_artist(&state, artist) {
+    Some(name) => name,
+    None => return Ok(None),
+  };
+  let recent = read_store().recent_suggestions;
+  let key = format!("similar:{}", name.to_lowercase());
+  let cache = lastfm_cache()?;
+  let now = millis_now();
+  if let Some((similar, _)) = cache.get::<Vec<SimilarArtist>>(&key, ARTIST_CACHE_TTL_MS, now) {
+    return Ok(Some(SimilarArtistsView { artist: name, similar, recent, stale: false }));
+  }
+
+  let client = lastfm_client(&app)?;
+  let body = client
+    .get(
+      "artist.getSimilar",
+      vec![
+        ("artist", name.clone()),
+        ("autocorrect", "1".to_string()),
+        ("limit", SIMILAR_LIMIT.to_string()),
+      ],
+    )
+    .await;
+  let body = match body {
+    Ok(body) => body,
+    Err(err) => {
+      log::warn!("[Last.fm] Similar artists for '{}' failed: {}", name, err);
+      return match cache.get::<Vec<SimilarArtist>>(&key, u64::MAX, now) {
+        Some((similar, _)) => Ok(Some(SimilarArtistsView { artist: name, similar, recent, stale: true })),
+        None => Err(err),
+      };
+    }
+  };
+  let similar = lastfm::parse_similar_artists(&body);
+  if let Err(err) = cache.put(&key, &similar, now) {
+    log::warn!("[Last.fm] Failed to cache similar artists: {}", err);
+  }
+  Ok(Some(SimilarArtistsView { artist: name, similar, recent, stale: false }))
+}
+
+fn soundcloud_search_url(query: &str) -> Result<Url, String> {
+  Url::parse_with_params("https://soundcloud.com/search", &[("q", query)]).map_err(|e| e.to_string())
+}
+
+// Remembers the suggestion, then points the main webview at SoundCloud's search page.
+#[tauri::command]
+async fn open_soundcloud_search(app: tauri::AppHandle, name: String, seed: Option<String>) -> Result<(), String> {
+  let name = name.trim().to_string();
+  if name.is_empty() {
+    return Err("Artist name is empty".into());
+  }
+  let url = soundcloud_search_url(&name)?;
+
+  update_store(|store| {
+    store.recent_suggestions.retain(|r| !r.name.eq_ignore_ascii_case(&name));
+    store.recent_suggestions.insert(
+      0,
+      RecentSuggestion {
+        name: name.clone(),
+        seed,
+        used_at: millis_now(),
+      },
+    );
+    store.recent_suggestions.truncate(RECENT_SUGGESTIONS_LIMIT);
+  })?;
+
+  log::info!("[Discover] Searching SoundCloud for '{}'", name);
+  let window = app.get_webview_window("main").ok_or("Main window not found")?;
+  window.navigate(url).map_err(|e| e.to_string())
+}
+
+// Last.fm plays since the oldest in-window history play, for deduping a backfill.
+async fn backfill_recent_tracks(
+  client: &LastfmClient,
+  session: &LastfmSession,
+  plays: &[HistoryPlay],
+  now: u64,
+) -> Result<Vec<lastfm::RecentTrack>, String> {
+  let oldest = (now / 1000).saturating_sub(scrobble_log::MAX_SCROBBLE_AGE_SECS);
+  let from = plays
+    .iter()
+    .map(|p| p.timestamp())
+    .filter(|ts| *ts > oldest)
+    .min();
+
+  let mut recent = Vec::new();
+  if let Some(from) = from {
+    let mut page = 1;
+    loop {
+      let body = client.get(
+        "user.getRecentTracks",
+        vec![
+          ("user", session.username.clone()),
+          ("from", from.saturating_sub(BACKFILL_MARGIN_SECS).to_string()),
+          ("limit", "200".to_string()),
+          ("page", page.to_string()),
+        ],
+      )
+      .await?;
+      let (tracks, total_pages) = lastfm::parse_recent_tracks(&body);
+      recent.extend(tracks);
+      if page >= total_pages || page >= BACKFILL_MAX_PAGES {
+        break;
+      }
+      page += 1;
+    }
+  }
+  Ok(recent)
+}
+
+#[tauri::command]
+async fn prepare_backfill(app: tauri::AppHandle, plays: Vec<HistoryPlay>) -> Result<Vec<HistoryPlay>, String> {
+  let client = lastfm_client(&app)?;
+  let session = get_lastfm_session(&app).ok_or("Connect Last.fm first")?;
+  let now = millis_now();
+  log::info!("[Backfill] {} history plays received", plays.len());
+  let recent = backfill_recent_tracks(&client, &session, &plays, now).await?;
+  let candidates = backfill::build_candidates(plays, &recent, &read_store().submissions, now);
+  log::info!(
+    "[Backfill] {} candidates after filtering against {} Last.fm plays",
+    candidates.len(),
+    recent.len()
+  );
+  Ok(candidates)
+}
+
+#[derive(Debug, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BackfillReport {
+  accepted: u64,
+  ignored: u64,
+  // Error of the batch that stopped the backfill; the batches before it landed.
+  failed_batch: Option<String>,
+}
+
+#[tauri::command]
+async fn submit_backfill(app: tauri::AppHandle, plays: Vec<HistoryPlay>) -> Result<BackfillReport, String> {
+  let client = lastfm_client(&app)?;
+  let session = get_lastfm_session(&app).ok_or("Connect Last.fm first")?;
+  let now = millis_now();
+  // Re-apply the window and dedupe: the user may have left the confirmation open for a
+  // while, or be retrying after a partial failure whose batches are already in the log.
+  let recent = match backfill_recent_tracks(&client, &session, &plays, now).await {
+    Ok(recent) => recent,
+    Err(err) => {
+      log::warn!("[Backfill] Could not re-check Last.fm, deduping against the submission log only: {}", err);
+      Vec::new()
+    }
+  };
+  let plays = backfill::build_candidates(plays, &recent, &read_store().submissions, now);
+  let mut report = BackfillReport::default();
+  for batch in plays.chunks(backfill::BATCH_SIZE) {
+    let tracks: Vec<TrackState> = batch
+      .iter()
+      .map(|p| TrackState {
+        track_id: p.track_id.clone(),
+        title: p.title.clone(),
+        artist: p.artist.clone(),
+        album: p.album.clone(),
+        duration_ms: p.duration_ms,
+        started_at: p.timestamp() * 1000,
+        scrobbled: true,
+        ..TrackState::default()
+      })
+      .collect();
+    let ack = match send_scrobbles(&client, &session, &tracks).await {
+      Ok(ack) => ack,
+      Err(err) => {
+        log::warn!("[Backfill] batch of {} failed: {}", tracks.len(), err);
+        report.failed_batch = Some(err);
+        break;
+      }
+    };
+    log::info!(
+      "[Backfill] batch of {} submitted: accepted={} ignored={}",
+      tracks.len(),
+      ack.accepted,
+      ack.ignored
+    );
+    for track in &tracks {
+      record_submission(track);
+    }
+    report.accepted += ack.accepted;
+    report.ignored += ack.ignored;
+  }
+  Ok(report)
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+struct LoveSyncState {
+  last_run: Option<u64>,
+  // SoundCloud track ids the sync already loved. They are not loved again, so
+  // un-loving one on Last.fm sticks.
+  synced: Vec<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct LoveFailure {
+  track: LikedTrack,
+  error: String,
+}
+
+#[derive(Debug, Default, serde::Serialize)]
+struct LoveSyncReport {
+  dry_run: bool,
+  // Loved in this run, or would be on a dry run.
+  loved: Vec<LikedTrack>,
+  failed: Vec<LoveFailure>,
+  // New likes left for the next run.
+  remaining: usize,
+  already_loved: usize,
+  missing_on_soundcloud: Vec<LovedTrack>,
+}
+
+async fn fetch_loved_tracks(client: &LastfmClient, username: &str) -> Result<Vec<LovedTrack>, String> {
+  let mut loved = Vec::new();
+  let mut page = 1;
+  loop {
+    let body = client
+      .get(
+        "user.getLovedTracks",
+        vec![
+          ("user", username.to_string()),
+          ("limit", LOVED_PAGE_SIZE.to_string()),
+          ("page", page.to_string()),
+        ],
+      )
+      .await?;
+    let (tracks, pages) = lastfm::parse_loved_tracks(&body);
+    loved.extend(tracks);
+    if page >= pages || page >= LOVED_MAX_PAGES {
+      break;
+    }
+    page += 1;
+  }
+  Ok(loved)
+}
+
+#[tauri::command]
+async fn get_love_sync_status() -> Result<LoveSyncState, String> {
+  Ok(read_store().love_sync)
+}
+
+// Likes come from the overlay, which reads api-v2 with the page's own SoundCloud auth.
+#[tauri::command]
+async fn sync_loves(app: tauri::AppHandle, likes: Vec<LikedTrack>, dry_run: bool) -> Result<LoveSyncReport, String> {
+  let client = lastfm_client(&app)?;
+  let session = get_lastfm_session(&app).ok_or("Connect Last.fm first")?;
+  let loved = fetch_loved_tracks(&client, &session.username).await?;
+  let mut sync = read_store().love_sync;
+  let diff = love_sync::diff(&likes, &loved);
+  let mut to_love: Vec<LikedTrack> = diff
+    .to_love
+    .into_iter()
+    .filter(|l| !sync.synced.contains(&l.track_id))
+    .collect();
+  let mut report = LoveSyncReport {
+    dry_run,
+    remaining: to_love.len().saturating_sub(LOVE_SYNC_MAX_PER_RUN),
+    already_loved: diff.already_loved,
+    missing_on_soundcloud: diff.missing_on_soundcloud,
+    ..LoveSyncReport::default()
+  };
+  to_love.truncate(LOVE_SYNC_MAX_PER_RUN);
+  log::info!(
+    "[LoveSync] {} likes, {} loves on Last.fm, {} to love{}",
+    likes.len(),
+    loved.len(),
+    to_love.len(),
+    if dry_run { " (dry run)" } else { "" }
+  );
+  if dry_run {
+    report.loved = to_love;
+    return Ok(report);
+  }
+
+  for like in to_love {
+    let (artist, track) = love_sync::love_metadata(&like);
+    match client
+      .call("track.love", vec![("artist", artist.clone()), ("track", track.clone())], &session.session_key)
+      .await
+    {
+      Ok(_) => {
+        let cfg = load_scrobble_config(&app);
+        fire_webhooks(
+          &app,
+          &cfg,
+          WebhookEvent::Loved,
+          webhooks::TrackInfo {
+            track_id: like.track_id.clone(),
+            title: track,
+            artist,
+            ..webhooks::TrackInfo::default()
+          },
+          None,
+        );
+        sync.synced.push(like.track_id.clone());
+        report.loved.push(like);
+      }
+      Err(error) => {
+        log::warn!("[LoveSync] Failed to love '{}': {}", like.title, error);
+        report.failed.push(LoveFailure { track: like, error });
+      }
+    }
+    tokio::time::sleep(Duration::from_millis(LOVE_SYNC_DELAY_MS)).await;
+  }
+  if sync.synced.len() > LOVE_SYNC_SYNCED_LIMIT {
+    let excess = sync.synced.len() - LOVE_SYNC_SYNCED_LIMIT;
+    sync.synced.drain(..excess);
+  }
+  sync.last_run = Some(millis_now());
+  // Re-read under the lock: scrobbles may have written the store while the sync was running.
+  update_store(|store| store.love_sync = sync)?;
+  log::info!("[LoveSync] loved={} failed={}", report.loved.len(), report.failed.len());
+  Ok(report)
+}
+
+// Auth page URL for the browser flow. The nonce rides along in the callback URL,
+// and Last.fm appends `token` to it.
+fn lastfm_auth_url(callback: &str, nonce: &str) -> Result<String, String> {
+  let api_key = lastfm_key().ok_or("LASTFM_API_KEY not set")?;
+  let mut cb = Url::parse(callback).map_err(|e| e.to_string())?;
+  cb.query_pairs_mut().append_pair("state", nonce);
+  let mut url = Url::parse(lastfm::AUTH_URL).map_err(|e| e.to_string())?;
+  url
+    .query_pairs_mut()
+    .append_pair("api_key", &api_key)
+    .append_pair("cb", cb.as_str());
+  Ok(url.to_string())
+}
+
+// Binds an ephemeral loopback port for this sign-in and returns its callback URL. The
+// server replaces any previous one and stops on success or when the request expires.
+async fn start_callback_server(app: &tauri::AppHandle, auth: &Mutex<AuthState>) -> Result<String, String> {
+  let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+    .await
+    .map_err(|e| format!("Failed to start callback server: {}", e))?;
+  let addr = listener.local_addr().map_err(|e| e.to_string())?;
+  let callback = format!("http://{}{}", addr, callback_server::CALLBACK_PATH);
+  log::info!("[Last.fm] Callback server listening on {}", callback);
+
+  let app_handle = app.clone();
+  let handle = tauri::async_runtime::spawn(async move {
+    let ttl = Duration::from_millis(auth::PENDING_AUTH_TTL_MS);
+    let completed = callback_server::serve(listener, ttl, |url| {
+      let app = app_handle.clone();
+      async move { complete_lastfm(app, url).await.map(|s| s.username) }
+    })
+    .await;
+    log::info!(
+      "[Last.fm] Callback server on {} stopped ({})",
+      addr,
+      if completed { "signed in" } else { "timed out" }
+    );
+  });
+  if let Some(previous) = auth.lock().unwrap().callback_server.replace(handle) {
+    previous.abort();
+  }
+  Ok(callback)
+}
+
+#[tauri::command]
+async fn begin_lastfm_auth(
+  app: tauri::AppHandle,
+  auth: tauri::State<'_, Arc<Mutex<AuthState>>>,
+) -> Result<String, String> {
+  let flow = load_scrobble_config(&app).auth_flow;
+  let callback = match (flow, lastfm_callback()) {
+    (AuthFlow::Callback, Some(callback)) => callback,
+    _ => start_callback_server(&app, &auth).await?,
+  };
+  let pending = PendingAuth::new(millis_now())?;
+  let url = lastfm_auth_url(&callback, &pending.nonce)?;
+  auth.lock().unwrap().pending = Some(pending);
+  log::info!("[Last.fm] Browser sign-in started; waiting for callback");
+  Ok(url)
+}
+
+#[tauri::command]
+async fn complete_lastfm(app: tauri::AppHandle, url: String) -> Result<SessionView, String> {
+  let parsed = Url::parse(&url).map_err(|e| e.to_string())?;
+  let param = |name: &str| {
+    parsed
+      .query_pairs()
+      .find(|(k, _)| k == name)
+      .map(|(_, v)| v.to_string())
+  };
+  let token = param("token").ok_or("missing token")?;
+  {
+    let auth = app.state::<Arc<Mutex<AuthState>>>();
+    let mut auth = auth.lock().unwrap();
+    if let Err(err) = auth::take_pending(&mut auth.pending, param("state").as_deref(), millis_now()) {
+      log::warn!("[Last.fm] Rejected callback: {}", err);
+      return Err(format!("Rejected Last.fm callback: {}", err));
+    }
+  }
+
+  log::info!("[Last.fm] Received callback with token {}", token);
+
+  let client = lastfm_client(&app)?;
+  let session = fetch_lastfm_session(&client, &token).await?;
+  save_session(&app, &session)?;
+  Ok(SessionView::from(&session))
+}
+
+fn save_session(app: &tauri::AppHandle, session: &LastfmSession) -> Result<(), String> {
+  log::info!(
+    "[Last.fm] Session established for user {}, key starts with {}***",
+    session.username,
+    session.session_key.chars().take(4).collect::<String>()
+  );
+  update_store(|state| state.session = Some(session.clone()))?;
+  log::info!("[Last.fm] Session persisted to store");
+  publish_session(app, Some(session));
+  Ok(())
+}
+
+#[derive(Debug, serde::Serialize)]
+struct AuthOptions {
+  callback_registered: bool,
+}
+
+#[tauri::command]
+async fn get_auth_options(auth: tauri::State<'_, Arc<Mutex<AuthState>>>) -> Result<AuthOptions, String> {
+  Ok(AuthOptions {
+    callback_registered: auth.lock().unwrap().callback_registered,
+  })
+}
+
+// Desktop flow, step 1: get a request token and return the page where the user approves it.
+#[tauri::command]
+async fn start_desktop_auth(
+  app: tauri::AppHandle,
+  auth: tauri::State<'_, Arc<Mutex<AuthState>>>) -> Result<String, String> {
+  let client = lastfm_client(&app)?;
+  let body = client.get_signed("auth.getToken", Vec::new()).await?;
+  let token = lastfm::text(body.get("token")).ok_or("Last.fm returned no token")?;
+  log::info!("[Last.fm] Desktop auth token issued");
+  let url = client.desktop_auth_url(&token);
+  auth.lock().unwrap().desktop_token = Some(token);
+  Ok(url)
+}
+
+// Desktop flow, step 2: poll auth.getSession until the token is approved, the flow is
+// cancelled (Ok(None)) or it times out.
+#[tauri::command]
+async fn await_desktop_auth(
+  app: tauri::AppHandle,
+  auth: tauri::State<'_, Arc<Mutex<AuthState>>>,
+) -> Result<Option<SessionView>, String> {
+  let token = auth
+    .lock()
+    .unwrap()
+    .desktop_token
+    .clone()
+    .ok_or("No desktop sign-in in progress")?;
+  let client = lastfm_client(&app)?;
+  let deadline = millis_now() + DESKTOP_AUTH_TIMEOUT_SECS * 1000;
+  let still_pending = || auth.lock().unwrap().desktop_token.as_deref() == Some(token.as_str());
+
+  let result = loop {
+    tokio::time::sleep(Duration::from_secs(DESKTOP_AUTH_POLL_SECS)).await;
+    if !still_pending() {
+      log::info!("[Last.fm] Desktop auth cancelled");
+      return Ok(None);
+    }
+    if millis_now() >= deadline {
+      break Err("Timed out waiting for approval on Last.fm".to_string());
+    }
+    let body = match client
+      .get_signed_raw("auth.getSession", vec![("token", token.clone())], None)
+      .await
+    {
+      Ok(body) => body,
+      Err(err) => {
+        log::warn!("[Last.fm] Desktop auth poll failed: {}", err);
+        continue;
+      }
+    };
+    match lastfm::error_code(&body) {
+      None => break parse_session(body).and_then(|session| save_session(&app, &session).map(|_| Some(SessionView::from(&session)))),
+      Some(lastfm::ERROR_TOKEN_UNAUTHORIZED) => continue,
+      Some(_) => {
+        break Err(format!(
+          "Last.fm sign-in failed: {}",
+          lastfm::error_message(&body).unwrap_or_default()
+        ))
+      }
+    }
+  };
+  // Only clear our own token; a newer flow may have replaced it meanwhile.
+  let mut lock = auth.lock().unwrap();
+  if lock.desktop_token.as_deref() == Some(token.as_str()) {
+    lock.desktop_token = None;
+  }
+  result
+}
+
+#[tauri::command]
+async fn cancel_desktop_auth(auth: tauri::State<'_, Arc<Mutex<AuthState>>>) -> Result<(), String> {
+  auth.lock().unwrap().desktop_token = None;
+  Ok(())
+}
+
+// Mobile flow: trade credentials for a session key. The password is only sent to
+// Last.fm over HTTPS; it is never stored or logged.
+#[tauri::command]
+async fn login_lastfm_mobile(
+  app: tauri::AppHandle,
+  username: String,
+  password: String,
+) -> Result<SessionView, String> {
+  let username = username.trim();
+  if username.is_empty() || password.is_empty() {
+    return Err("Enter your Last.fm username and password".to_string());
+  }
+  log::info!("[Last.fm] Requesting mobile session for user {}", username);
+  let client = lastfm_client(&app)?;
+  let body = client
+    .post_signed(
+      "auth.getMobileSession",
+      vec![("username", username.to_string()), ("password", password)],
+    )
+    .await?;
+  let session = parse_session(body)?;
+  save_session(&app, &session)?;
+  Ok(SessionView::from(&session))
+}
+
+#[tauri::command]
+async fn get_settings(app: tauri::AppHandle) -> Result<ScrobbleConfig, String> {
+  Ok(redacted(load_scrobble_config(&app)))
+}
+
+#[tauri::command]
+async fn update_settings(app: tauri::AppHandle, update: ScrobbleConfigUpdate) -> Result<ScrobbleConfig, String> {
+  Ok(redacted(apply_settings_update(&app, update)))
+}
+
+// Called once the overlay listens for `mscd:*` events: returns toasts raised before
+// that, and from then on toasts are only emitted.
+#[tauri::command]
+async fn take_toasts(state: tauri::State<'_, Arc<Mutex<ScrobbleState>>>) -> Result<Vec<ToastEvent>, String> {
+  let mut lock = state.lock().unwrap();
+  lock.overlay_attached = true;
+  Ok(lock.events.drain(..).collect())
+}
+
+#[tauri::command]
+async fn report_playback(
+  app: tauri::AppHandle,
+  state: tauri::State<'_, Arc<Mutex<ScrobbleState>>>,
+  payload: PlaybackPayload,
+) -> Result<(), String> {
+  handle_playback(app, &state, payload).await
+}
+
+// Applies a settings update from the overlay and persists the result.
+fn apply_settings_update(app: &tauri::AppHandle, update: ScrobbleConfigUpdate) -> ScrobbleConfig {
+  let mut control_before = (false, 0);
+  let cfg = match save_scrobble_config(app, |cfg| {
+    if let Some(v) = update.threshold {
+      cfg.threshold = v.clamp(0.01, 1.0);
+    }
+    if let Some(v) = update.enable_scrobble {
+      cfg.enable_scrobble = v;
+    }
+    if let Some(v) = update.skip_audio_ads {
+      cfg.skip_audio_ads = v;
+    }
+    if let Some(v) = update.skip_promoted {
+      cfg.skip_promoted = v;
+    }
+    if let Some(v) = update.enable_notifications {
+      cfg.enable_notifications = v;
+    }
+    if let Some(v) = update.notification_mode {
+      cfg.notification_mode = v;
+    }
+    if let Some(v) = update.volume_seeded {
+      cfg.volume_seeded = v;
+    }
+    if let Some(v) = update.split_mixes {
+      cfg.split_mixes = v;
+    }
+    if let Some(v) = update.sync_loves {
+      cfg.sync_loves = v;
+    }
+    if let Some(v) = update.auth_flow {
+      cfg.auth_flow = v;
+    }
+    if let Some(v) = update.local_server {
+      cfg.local_server = v;
+    }
+    control_before = (cfg.control_api, cfg.control_port);
+    if let Some(v) = update.control_api {
+      cfg.control_api = v;
+    }
+    if let Some(v) = update.control_port {
+      if v >= 1024 {
+        cfg.control_port = v;
+      } else {
+        log::warn!("[Control] Rejected port {}; use 1024 or above", v);
+      }
+    }
+    if let Some(v) = update.now_playing_file {
+      if v.trim().is_empty() {
+        cfg.now_playing_file = None;
+      } else {
+        match now_playing::file_name(&v) {
+          Ok(name) => cfg.now_playing_file = Some(name),
+          Err(err) => log::warn!("[NowPlaying] Rejected file setting: {}", err),
+        }
+      }
+    }
+    if let Some(v) = update.now_playing_template {
+      cfg.now_playing_template = v;
     }
-  };
-  let session = match get_lastfm_session(&app) {
-    Some(s) => s,
-    None => {
-      log::info!("[Last.fm] report_playback skipped: no session");
-      return Ok(());
+    if let Some(v) = update.widget_theme {
+      cfg.widget_theme = v;
+    }
+    if let Some(v) = update.widget_idle_text {
+      cfg.widget_idle_text = v;
+    }
+  }) {
+    Ok(cfg) => cfg,
+    Err(err) => {
+      log::warn!("[Settings] Failed to save settings: {}", err);
+      load_scrobble_config(app)
     }
   };
+  write_now_playing_file(app, &cfg, true);
+  if (cfg.control_api, cfg.control_port) != control_before {
+    tauri::async_runtime::spawn(restart_control_server(app.clone()));
+  }
+  cfg
+}
 
-  let scrobble_to_send = {
-    let mut state_lock = state.lock().unwrap();
-    let mut scrobble_to_send: Option<TrackState> = None;
-
-    let is_new_track = match &state_lock.current {
-      Some(t) => t.track_id != payload.track_id,
-      None => true,
-    };
-
-    if is_new_track {
+async fn handle_local_request(
+  app: tauri::AppHandle,
+  state: Arc<Mutex<ScrobbleState>>,
+  route: Route,
+  body: Bytes,
+) -> Reply {
+  match route {
+    Route::Playback => {
+      let payload: PlaybackPayload = match local_server::parse_json(&body) {
+        Ok(payload) => payload,
+        Err(reply) => {
+          log::warn!("[Last.fm] Playback server JSON parse failed");
+          return reply;
+        }
+      };
+      if let Err(err) = handle_playback(app, &state, payload).await {
+        log::warn!("[Last.fm] handle_playback from server failed: {}", err);
+      }
+      Reply::no_content()
+    }
+    Route::GetSettings => {
+      let cfg = load_scrobble_config(&app);
       log::info!(
-        "[Last.fm] new track detected: '{}' by '{}' ({} ms)",
-        payload.title,
-        payload.artist,
-        payload.duration_ms
+        "[Settings] GET /settings threshold={} scrobble={} skip_audio_ads={} skip_promoted={} notifications={} mode={:?}",
+        cfg.threshold,
+        cfg.enable_scrobble,
+        cfg.skip_audio_ads,
+        cfg.skip_promoted,
+        cfg.enable_notifications,
+        cfg.notification_mode
       );
+      Reply::json(&redacted(cfg))
+    }
+    Route::UpdateSettings => {
+      let update: ScrobbleConfigUpdate = match local_server::parse_json(&body) {
+        Ok(update) => update,
+        Err(reply) => {
+          log::warn!("[Last.fm] Settings JSON parse failed");
+          return reply;
+        }
+      };
+      let cfg = apply_settings_update(&app, update);
       log::info!(
-        "[Settings] Using threshold={} skip_audio_ads={} skip_promoted={} notifications={}",
+        "[Settings] POST /settings threshold={} scrobble={} skip_audio_ads={} skip_promoted={} notifications={} mode={:?}",
         cfg.threshold,
+        cfg.enable_scrobble,
         cfg.skip_audio_ads,
         cfg.skip_promoted,
-        cfg.enable_notifications
+        cfg.enable_notifications,
+        cfg.notification_mode
       );
-      let t = TrackState {
-        track_id: payload.track_id.clone(),
-        title: payload.title.clone(),
-        artist: payload.artist.clone(),
-        album: payload.album.clone(),
-        duration_ms: payload.duration_ms,
-        started_at: millis_now().saturating_sub(payload.position_ms),
-        listened_ms: 0,
-        last_pos_ms: payload.position_ms,
-        last_update_ts_ms: payload.ts,
-        scrobbled: false,
-      };
-      state_lock.current = Some(t);
-    } else if let Some(current) = state_lock.current.as_mut() {
-      let delta_pos = payload.position_ms.saturating_sub(current.last_pos_ms);
-      let delta_time = payload.ts.saturating_sub(current.last_update_ts_ms);
-
-      // Only count real listened time; if the position jump is larger than elapsed wall time,
-      // treat it as a seek and cap by delta_time.
-      if !payload.paused {
-        let increment = if delta_pos > delta_time.saturating_add(1_500) {
-          delta_time
-        } else {
-          delta_pos
-        };
-        current.listened_ms = current.listened_ms.saturating_add(increment);
-      }
-      current.last_pos_ms = payload.position_ms;
-      current.last_update_ts_ms = payload.ts;
-
-      let threshold_ms = (current.duration_ms as f32 * cfg.threshold).round() as u64;
-      if !current.scrobbled && current.listened_ms >= threshold_ms && current.duration_ms > 0 {
-        current.scrobbled = true;
-        scrobble_to_send = Some(current.clone());
-        log::info!(
-          "[Last.fm] threshold met for '{}' listened_ms={} threshold_ms={}",
-          current.title,
-          current.listened_ms,
-          threshold_ms
-        );
-      }
+      Reply::json(&redacted(cfg))
     }
-
-    scrobble_to_send
-  };
-
-  if let Some(track) = scrobble_to_send {
-    match send_scrobble(&session, &api_key, &api_secret, &track).await {
-      Ok(_) => {
-        log::info!("[Last.fm] scrobbled '{}'", track.title);
-        if cfg.enable_notifications {
-          match cfg.notification_mode {
-            NotificationMode::InApp => {
-              let mut lock = state.lock().unwrap();
-              lock.events.push_back(ToastEvent {
-                kind: ToastKind::Scrobble,
-                title: track.title.clone(),
-                artist: track.artist.clone(),
-                message: None,
-              });
-            }
-            NotificationMode::System => {
-              let _ = app
-                .notification()
-                .builder()
-                .title("Scrobbled")
-                .body(format!("{} — {}", track.title, track.artist))
-                .show();
-            }
-          }
-        }
-      }
-      Err(err) => {
-        log::warn!("[Last.fm] scrobble failed: {}", err);
-        if cfg.enable_notifications {
-          match cfg.notification_mode {
-            NotificationMode::InApp => {
-              let mut lock = state.lock().unwrap();
-              lock.events.push_back(ToastEvent {
-                kind: ToastKind::ScrobbleFailed,
-                title: track.title.clone(),
-                artist: track.artist.clone(),
-                message: Some(err.clone()),
-              });
-            }
-            NotificationMode::System => {
-              let _ = app
-                .notification()
-                .builder()
-                .title("Scrobble failed")
-                .body(format!("{} — {}", track.title, track.artist))
-                .show();
-            }
-          }
-        }
+    Route::Events => {
+      // Subscribe before draining so a toast pushed in between is streamed, not lost.
+      let events = app.state::<EventHub>().subscribe();
+      let mut backlog = vec![ServerEvent::new("settings", &redacted(load_scrobble_config(&app)))];
+      backlog.extend(
+        state
+          .lock()
+          .unwrap()
+          .events
+          .drain(..)
+          .map(|event| ServerEvent::new("toast", &event)),
+      );
+      Reply::event_stream(backlog, events)
+    }
+    Route::NowPlaying => {
+      let threshold = load_scrobble_config(&app).threshold;
+      let now_playing = state
+        .lock()
+        .unwrap()
+        .current
+        .as_ref()
+        .map(|track| NowPlaying::new(track, threshold));
+      Reply::json(&now_playing)
+    }
+    Route::Status => Reply::json(&app_status(&app, &state)),
+    Route::Widget => Reply::html(now_playing::WIDGET_HTML),
+    Route::WidgetFeed => {
+      let cfg = load_scrobble_config(&app);
+      let lock = state.lock().unwrap();
+      Reply::json(&now_playing::feed(
+        lock.active_playing(millis_now()),
+        &cfg.now_playing_template,
+        &cfg.widget_idle_text,
+        cfg.widget_theme,
+      ))
+    }
+    Route::Config => Reply::json(&redacted(load_scrobble_config(&app))),
+    Route::Control(action) => {
+      let position_ms = match action {
+        Control::Seek => match local_server::parse_json::<SeekRequest>(&body) {
+          Ok(seek) => Some(seek.position_ms),
+          Err(reply) => return reply,
+        },
+        _ => None,
+      };
+      if !forward_control(&app, &state, action, position_ms) {
+        return Reply::error(hyper::StatusCode::SERVICE_UNAVAILABLE, "player not ready");
       }
+      log::info!("[Control] Forwarded {:?} to the overlay", action);
+      Reply::accepted()
     }
   }
+}
 
-  Ok(())
+// Player actions run in the overlay; false when none listens on IPC or a stream.
+fn forward_control(
+  app: &tauri::AppHandle,
+  state: &Arc<Mutex<ScrobbleState>>,
+  action: Control,
+  position_ms: Option<u64>,
+) -> bool {
+  let command = serde_json::json!({ "action": action, "position_ms": position_ms });
+  publish(app, "control", &command) || state.lock().unwrap().overlay_attached
 }
-async fn fetch_lastfm_session(api_key: &str, api_secret: &str, token: &str) -> Result<LastfmSession, String> {
-  let sig_base = format!(
-    "api_key{}methodauth.getSessiontoken{}{}",
-    api_key, token, api_secret
-  );
-  let sig = format!("{:x}", md5::compute(sig_base.as_bytes()));
-  log::info!("[Last.fm] Requesting session for token {}", token);
 
-  #[derive(serde::Deserialize)]
-  struct SessionResp {
-    session: SessionInner,
-  }
-  #[derive(serde::Deserialize)]
-  struct SessionInner {
-    name: String,
-    key: String,
-  }
+#[derive(Debug, Deserialize)]
+struct SeekRequest {
+  position_ms: u64,
+}
 
-  let client = reqwest::Client::new();
-  let url = "https://ws.audioscrobbler.com/2.0/";
-  let res = client
-    .get(url)
-    .query(&[
-      ("method", "auth.getSession"),
-      ("api_key", api_key),
-      ("token", token),
-      ("api_sig", &sig),
-      ("format", "json"),
-    ])
-    .send()
-    .await
-    .map_err(|e| e.to_string())?;
+// The control API listener; replaced when its settings or token change.
+#[derive(Default)]
+struct ControlServer {
+  task: Option<tauri::async_runtime::JoinHandle<()>>,
+  // Why the last start failed (port in use, ...), shown in settings.
+  error: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+struct ControlApiInfo {
+  enabled: bool,
+  running: bool,
+  url: String,
+  token: String,
+  error: Option<String>,
+}
 
-  if !res.status().is_success() {
-    return Err(format!("Last.fm session request failed: {}", res.status()));
+fn control_token() -> Result<String, String> {
+  if let Some(token) = read_store().control_token {
+    return Ok(token);
   }
+  let token = auth::random_token()?;
+  // Another caller may have created one in the meantime; keep whichever landed first.
+  update_store(|store| store.control_token.get_or_insert(token).clone())
+}
 
-  let body: SessionResp = res.json().await.map_err(|e| e.to_string())?;
-  Ok(LastfmSession {
-    session_key: body.session.key,
-    username: body.session.name,
+fn control_api_info(app: &tauri::AppHandle) -> Result<ControlApiInfo, String> {
+  let cfg = load_scrobble_config(app);
+  let server = app.state::<Mutex<ControlServer>>();
+  let server = server.lock().unwrap();
+  Ok(ControlApiInfo {
+    enabled: cfg.control_api,
+    running: server.task.is_some(),
+    url: format!("http://127.0.0.1:{}", cfg.control_port),
+    token: control_token()?,
+    error: server.error.clone(),
   })
 }
 
-#[tauri::command]
-async fn get_lastfm_status(app: tauri::AppHandle) -> Result<Option<LastfmSession>, String> {
-  if let Some(session) = get_lastfm_session(&app) {
-    log::info!("[Last.fm] Returning stored session for user {}", session.username);
-    Ok(Some(session))
+// Stops the running control API (waiting until its port is released) and starts it
+// again if enabled, then tells the overlay.
+async fn restart_control_server(app: tauri::AppHandle) {
+  let previous = app.state::<Mutex<ControlServer>>().lock().unwrap().task.take();
+  if let Some(task) = previous {
+    task.abort();
+    let _ = task.await;
+  }
+  let cfg = load_scrobble_config(&app);
+  let started = if cfg.control_api {
+    start_control_server(&app, cfg.control_port).map(Some)
   } else {
-    log::info!("[Last.fm] No session stored");
     Ok(None)
+  };
+  {
+    let server = app.state::<Mutex<ControlServer>>();
+    let mut server = server.lock().unwrap();
+    match started {
+      Ok(task) => {
+        server.task = task;
+        server.error = None;
+      }
+      Err(err) => {
+        log::warn!("[Control] Failed to start API on port {}: {}", cfg.control_port, err);
+        server.error = Some(err);
+      }
+    }
+  }
+  if let Ok(info) = control_api_info(&app) {
+    publish(&app, "control_api", &info);
   }
 }
 
-#[tauri::command]
-async fn disconnect_lastfm(_app: tauri::AppHandle) -> Result<(), String> {
-  let mut state = read_store();
-  state.session = None;
-  write_store(&state)
+fn start_control_server(
+  app: &tauri::AppHandle,
+  port: u16,
+) -> Result<tauri::async_runtime::JoinHandle<()>, String> {
+  let token = control_token()?;
+  let listener = std::net::TcpListener::bind(("127.0.0.1", port)).map_err(|e| e.to_string())?;
+  listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+  let app = app.clone();
+  let state = app.state::<Arc<Mutex<ScrobbleState>>>().inner().clone();
+  log::info!("[Control] API listening on 127.0.0.1:{}", port);
+  Ok(tauri::async_runtime::spawn(async move {
+    let listener = match tokio::net::TcpListener::from_std(listener) {
+      Ok(l) => l,
+      Err(err) => {
+        log::warn!("[Control] API start failed: {}", err);
+        return;
+      }
+    };
+    local_server::serve(listener, Api::Control, token, move |route, body| {
+      handle_local_request(app.clone(), state.clone(), route, body)
+    })
+    .await;
+  }))
 }
 
-fn sign_lastfm(params: &mut Vec<(&str, String)>, api_secret: &str) -> String {
-  params.sort_by_key(|(k, _)| k.to_string());
-  let mut base = String::new();
-  for (k, v) in params.iter() {
-    base.push_str(k);
-    base.push_str(v);
-  }
-  base.push_str(api_secret);
-  format!("{:x}", md5::compute(base.as_bytes()))
+// The running MQTT client and its state channel; dropping the sender stops it. The
+// client id stays the same so a new connection takes over the old one's session.
+#[derive(Default)]
+struct MqttPublisher {
+  running: Option<(tauri::async_runtime::JoinHandle<()>, tokio::sync::watch::Sender<NowState>)>,
+  client_id: String,
+  // Held for a whole restart so overlapping ones can't start two clients.
+  restart: Arc<tokio::sync::Mutex<()>>,
 }
 
-async fn lastfm_call(method: &str, params: Vec<(&str, String)>, api_key: &str, api_secret: &str, sk: &str) -> Result<(), String> {
-  let mut params = params;
-  params.push(("method", method.to_string()));
-  params.push(("api_key", api_key.to_string()));
-  params.push(("sk", sk.to_string()));
-  let api_sig = sign_lastfm(&mut params.clone(), api_secret);
-
-  let mut query: Vec<(&str, String)> = params;
-  query.push(("api_sig", api_sig));
-  query.push(("format", "json".to_string()));
-
-  let client = reqwest::Client::new();
-  let res = client
-    .post("https://ws.audioscrobbler.com/2.0/")
-    .form(&query)
-    .send()
-    .await
-    .map_err(|e| e.to_string())?;
-
-  if !res.status().is_success() {
-    return Err(format!("Last.fm call {} failed: {}", method, res.status()));
+impl MqttPublisher {
+  fn new() -> Self {
+    let suffix = auth::random_token().unwrap_or_default();
+    Self {
+      client_id: format!("mscd-{}", &suffix[..suffix.len().min(12)]),
+      ..Self::default()
+    }
   }
-
-  Ok(())
 }
 
-async fn send_scrobble(session: &LastfmSession, api_key: &str, api_secret: &str, track: &TrackState) -> Result<(), String> {
-  let ts = track.started_at as i64 / 1000;
-  lastfm_call(
-    "track.scrobble",
-    vec![
-      ("track[0]", track.title.clone()),
-      ("artist[0]", track.artist.clone()),
-      ("duration[0]", track.duration_ms.to_string()),
-      ("timestamp[0]", ts.to_string()),
-    ],
-    api_key,
-    api_secret,
-    &session.session_key,
-  )
-  .await
+// Stops the MQTT client (cleanly, so it can publish "offline") and starts it again
+// with the stored settings, if enabled.
+async fn restart_mqtt(app: tauri::AppHandle) {
+  let restart = app.state::<Mutex<MqttPublisher>>().lock().unwrap().restart.clone();
+  let _restarting = restart.lock().await;
+  let previous = app.state::<Mutex<MqttPublisher>>().lock().unwrap().running.take();
+  if let Some((mut task, states)) = previous {
+    drop(states);
+    if tokio::time::timeout(Duration::from_secs(MQTT_STOP_SECS), &mut task).await.is_err() {
+      log::warn!("[MQTT] Client did not stop in time; aborting it");
+      task.abort();
+    }
+  }
+  let mut cfg = load_scrobble_config(&app).mqtt;
+  if !cfg.enabled {
+    return;
+  }
+  if let Err(err) = mqtt::parse_broker(&cfg.broker_url) {
+    log::warn!("[MQTT] Not started: {}", err);
+    return;
+  }
+  if cfg.topic_prefix.trim().trim_end_matches('/').is_empty() {
+    cfg.topic_prefix = MqttConfig::default().topic_prefix;
+  }
+  let state = app.state::<Arc<Mutex<ScrobbleState>>>().inner().clone();
+  let (states, receiver) = tokio::sync::watch::channel(state.lock().unwrap().mqtt_state(millis_now()));
+  let publisher = app.state::<Mutex<MqttPublisher>>();
+  let mut publisher = publisher.lock().unwrap();
+  let client_id = publisher.client_id.clone();
+  let handle = app.clone();
+  log::info!("[MQTT] Publishing to {} under {}/", cfg.broker_url, cfg.topic_prefix);
+  let task = tauri::async_runtime::spawn(mqtt::run(cfg, client_id, receiver, move |action| {
+    if forward_control(&handle, &state, action, None) {
+      log::info!("[MQTT] Forwarded {:?} to the overlay", action);
+    } else {
+      log::warn!("[MQTT] Dropped {:?}: player not ready", action);
+    }
+  }));
+  publisher.running = Some((task, states));
 }
 
+// Applies hand edits to the MQTT settings in the store.
 #[tauri::command]
-async fn complete_lastfm(_app: tauri::AppHandle, url: String) -> Result<LastfmSession, String> {
-  let parsed = Url::parse(&url).map_err(|e| e.to_string())?;
-  let token = parsed
-    .query_pairs()
-    .find(|(k, _)| k == "token")
-    .map(|(_, v)| v.to_string())
-    .ok_or("missing token")?;
-
-  log::info!("[Last.fm] Received callback with token {}", token);
-
-  let api_key = lastfm_key().ok_or("LASTFM_API_KEY not set")?;
-  let api_secret = lastfm_secret().ok_or("LASTFM_API_SECRET not set")?;
-
-  let session = fetch_lastfm_session(&api_key, &api_secret, &token).await?;
-  log::info!(
-    "[Last.fm] Session established for user {}, key starts with {}***",
-    session.username,
-    session.session_key.chars().take(4).collect::<String>()
-  );
-
-  let mut state = read_store();
-  state.session = Some(session.clone());
-  write_store(&state)?;
-  log::info!("[Last.fm] Session persisted to store");
-
-  Ok(session)
+async fn reconnect_mqtt(app: tauri::AppHandle) -> Result<(), String> {
+  restart_mqtt(app).await;
+  Ok(())
 }
 
 #[tauri::command]
-async fn report_playback(
+async fn get_status(
   app: tauri::AppHandle,
   state: tauri::State<'_, Arc<Mutex<ScrobbleState>>>,
-  payload: PlaybackPayload,
-) -> Result<(), String> {
-  handle_playback(app, &state, payload).await
+) -> Result<AppStatus, String> {
+  Ok(app_status(&app, &state))
 }
 
-#[cfg(debug_assertions)]
-fn start_dev_callback_server(app: tauri::AppHandle) {
-  let app_handle = app.clone();
-  tauri::async_runtime::spawn(async move {
-    use tokio::io::{AsyncReadExt, AsyncWriteExt};
-    let listener = match tokio::net::TcpListener::bind("127.0.0.1:35729").await {
-      Ok(l) => {
-        log::info!("[Last.fm] Dev callback server listening on {}", DEV_CALLBACK_URL);
-        l
-      }
-      Err(err) => {
-        log::warn!("[Last.fm] Failed to bind dev callback server: {}", err);
-        return;
-      }
-    };
-
-    loop {
-      let (mut socket, _) = match listener.accept().await {
-        Ok(s) => s,
-        Err(err) => {
-          log::warn!("[Last.fm] Accept failed: {}", err);
-          continue;
-        }
-      };
-      let app = app_handle.clone();
-      tauri::async_runtime::spawn(async move {
-        let mut buf = vec![0u8; 2048];
-        let n = match socket.read(&mut buf).await {
-          Ok(n) => n,
-          Err(err) => {
-            log::warn!("[Last.fm] Read failed: {}", err);
-            return;
-          }
-        };
-        let req = String::from_utf8_lossy(&buf[..n]);
-        let mut token: Option<String> = None;
-        if let Some(line) = req.lines().next() {
-          if let Some(path) = line.split_whitespace().nth(1) {
-            if let Some(q_idx) = path.find('?') {
-              let query = &path[q_idx + 1..];
-              for pair in query.split('&') {
-                if let Some((k, v)) = pair.split_once('=') {
-                  if k == "token" {
-                    token = Some(v.to_string());
-                    break;
-                  }
-                }
-              }
-            }
-          }
-        }
-
-        let response = if let Some(tok) = token {
-          let url = format!("{}?token={}", DEV_CALLBACK_URL, tok);
-          log::info!("[Last.fm] Dev callback received token {}", tok);
-          if let Err(err) = complete_lastfm(app.clone(), url).await {
-            log::warn!("[Last.fm] Dev callback processing failed: {}", err);
-          }
-          "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\r\nYou can close this tab.\r\n"
-        } else {
-          log::warn!("[Last.fm] Dev callback missing token");
-          "HTTP/1.1 400 Bad Request\r\nContent-Type: text/plain\r\n\r\nMissing token.\r\n"
-        };
+#[tauri::command]
+async fn get_control_api(app: tauri::AppHandle) -> Result<ControlApiInfo, String> {
+  control_api_info(&app)
+}
 
-        let _ = socket.write_all(response.as_bytes()).await;
-        let _ = socket.shutdown().await;
-      });
-    }
-  });
+// Invalidates the old token; the server restarts so open connections drop too.
+#[tauri::command]
+async fn regenerate_control_token(app: tauri::AppHandle) -> Result<ControlApiInfo, String> {
+  let token = auth::random_token()?;
+  update_store(|store| store.control_token = Some(token))?;
+  log::info!("[Control] Token regenerated");
+  restart_control_server(app.clone()).await;
+  control_api_info(&app)
 }
 
 fn start_playback_server(
   app: tauri::AppHandle,
   state: Arc<Mutex<ScrobbleState>>,
+  token: String,
 ) -> Option<String> {
-  let state_for_server = state.clone();
-  let app_handle = app.clone();
   let listener = std::net::TcpListener::bind("127.0.0.1:0").ok()?;
+  listener.set_nonblocking(true).ok()?;
   let port = listener.local_addr().ok()?.port();
   let local_url = format!("http://127.0.0.1:{}/playback", port);
 
@@ -1665,209 +5291,10 @@ fn start_playback_server(
         return;
       }
     };
-    loop {
-      let (mut socket, _) = match listener.accept().await {
-        Ok(s) => s,
-        Err(err) => {
-          log::warn!("[Last.fm] Playback server accept failed: {}", err);
-          continue;
-        }
-      };
-      //log::info!("[Last.fm] Playback server accepted connection");
-      let app_clone = app_handle.clone();
-      let state_clone = state_for_server.clone();
-      tauri::async_runtime::spawn(async move {
-        use tokio::io::{AsyncReadExt, AsyncWriteExt};
-        let mut buf = vec![0u8; 8192];
-        let mut total_read = 0usize;
-        let mut content_length: Option<usize> = None;
-        let mut method = String::new();
-        let mut path = String::new();
-        loop {
-          let n = match socket.read(&mut buf[total_read..]).await {
-            Ok(0) => break,
-            Ok(n) => n,
-            Err(err) => {
-              log::warn!("[Last.fm] Playback server read failed: {}", err);
-              return;
-            }
-          };
-          total_read += n;
-          if total_read >= 4 {
-            if let Some(idx) = twoway::find_bytes(&buf[..total_read], b"\r\n\r\n") {
-              let headers = &buf[..idx];
-              let header_str = String::from_utf8_lossy(headers);
-            if let Some(line) = header_str.lines().next() {
-              let mut parts = line.split_whitespace();
-              method = parts.next().unwrap_or("").to_string();
-              path = parts.next().unwrap_or("").to_string();
-            }
-            //log::info!("[HTTP] {} {}", method, path);
-            for line in headers.split(|b| *b == b'\n') {
-              if let Some(pos) = line.iter().position(|b| *b == b':') {
-                let (name, val) = line.split_at(pos);
-                  if name.eq_ignore_ascii_case(b"content-length") {
-                    if let Ok(len_str) = std::str::from_utf8(&val[1..]).map(|s| s.trim()) {
-                      if let Ok(len) = len_str.parse::<usize>() {
-                        content_length = Some(len);
-                      }
-                    }
-                  }
-                }
-              }
-              let body_start = idx + 4;
-              if method == "OPTIONS" {
-                let response = "HTTP/1.1 204 No Content\r\nAccess-Control-Allow-Origin: *\r\nAccess-Control-Allow-Methods: GET, POST, OPTIONS\r\nAccess-Control-Allow-Headers: Content-Type\r\n\r\n";
-                let _ = socket.write_all(response.as_bytes()).await;
-                let _ = socket.shutdown().await;
-                return;
-              }
-
-              let want_body = method == "POST";
-              if want_body && content_length.is_none() {
-                log::warn!("[Last.fm] Playback server missing content-length");
-                let response = "HTTP/1.1 411 Length Required\r\nAccess-Control-Allow-Origin: *\r\nAccess-Control-Allow-Methods: GET, POST, OPTIONS\r\nAccess-Control-Allow-Headers: Content-Type\r\n\r\n";
-                let _ = socket.write_all(response.as_bytes()).await;
-                let _ = socket.shutdown().await;
-                return;
-              }
-
-              if let Some(len) = content_length {
-                let needed = body_start + len;
-                while total_read < needed {
-                  let n = match socket.read(&mut buf[total_read..]).await {
-                    Ok(0) => break,
-                    Ok(n) => n,
-                    Err(err) => {
-                      log::warn!("[Last.fm] Playback server read failed: {}", err);
-                      return;
-                    }
-                  };
-                  total_read += n;
-                }
-                let body = &buf[body_start..std::cmp::min(total_read, body_start + len)];
-                if path == "/settings" && method == "POST" {
-                  match serde_json::from_slice::<ScrobbleConfigUpdate>(body) {
-                    Ok(update) => {
-                      let mut cfg = load_scrobble_config(&app_clone);
-                      if let Some(v) = update.threshold {
-                        cfg.threshold = v.clamp(0.01, 1.0);
-                      }
-                      if let Some(v) = update.enable_scrobble {
-                        cfg.enable_scrobble = v;
-                      }
-                      if let Some(v) = update.skip_audio_ads {
-                        cfg.skip_audio_ads = v;
-                      }
-                      if let Some(v) = update.skip_promoted {
-                        cfg.skip_promoted = v;
-                      }
-                      if let Some(v) = update.enable_notifications {
-                        cfg.enable_notifications = v;
-                      }
-                      if let Some(v) = update.notification_mode {
-                        cfg.notification_mode = v;
-                      }
-                      if let Some(v) = update.volume_seeded {
-                        cfg.volume_seeded = v;
-                      }
-                      let _ = save_scrobble_config(&app_clone, &cfg);
-                      let body = serde_json::to_string(&cfg).unwrap_or_else(|_| "{}".to_string());
-                      let response = format!(
-                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nAccess-Control-Allow-Origin: *\r\nAccess-Control-Allow-Methods: GET, POST, OPTIONS\r\nAccess-Control-Allow-Headers: Content-Type\r\n\r\n{}",
-                        body
-                      );
-                log::info!(
-                  "[Settings] POST /settings threshold={} scrobble={} skip_audio_ads={} skip_promoted={} notifications={} mode={:?}",
-                  cfg.threshold,
-                  cfg.enable_scrobble,
-                  cfg.skip_audio_ads,
-                  cfg.skip_promoted,
-                  cfg.enable_notifications,
-                  cfg.notification_mode
-                );
-                      let _ = socket.write_all(response.as_bytes()).await;
-                      let _ = socket.shutdown().await;
-                      return;
-                    }
-                    Err(err) => {
-                      log::warn!("[Last.fm] Settings JSON parse failed: {}", err);
-                    }
-                  }
-                } else if path == "/playback" {
-                  //log::info!("[Last.fm] Playback server received {} bytes", body.len());
-                  match serde_json::from_slice::<PlaybackPayload>(body) {
-                    Ok(payload) => {
-                      if let Err(err) = handle_playback(app_clone.clone(), &state_clone, payload).await {
-                        log::warn!("[Last.fm] handle_playback from server failed: {}", err);
-                      }
-                    }
-                    Err(err) => {
-                      log::warn!("[Last.fm] Playback server JSON parse failed: {}", err);
-                    }
-                  }
-                } else if path == "/events" && method == "GET" {
-                  let events = {
-                    let mut lock = state_clone.lock().unwrap();
-                    lock.events.drain(..).collect::<Vec<ToastEvent>>()
-                  };
-                  let body = serde_json::to_string(&serde_json::json!({ "events": events }))
-                    .unwrap_or_else(|_| "{}".to_string());
-                  let response = format!(
-                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nAccess-Control-Allow-Origin: *\r\nAccess-Control-Allow-Methods: GET, POST, OPTIONS\r\nAccess-Control-Allow-Headers: Content-Type\r\n\r\n{}",
-                    body
-                  );
-                  //log::info!("[Events] GET /events returned {}", events.len());
-                  let _ = socket.write_all(response.as_bytes()).await;
-                  let _ = socket.shutdown().await;
-                  return;
-                }
-              } else if method == "GET" && path == "/settings" {
-                let cfg = load_scrobble_config(&app_clone);
-                let body = serde_json::to_string(&cfg).unwrap_or_else(|_| "{}".to_string());
-              let response = format!(
-                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nAccess-Control-Allow-Origin: *\r\nAccess-Control-Allow-Methods: GET, POST, OPTIONS\r\nAccess-Control-Allow-Headers: Content-Type\r\n\r\n{}",
-                body
-              );
-              log::info!(
-                  "[Settings] GET /settings threshold={} scrobble={} skip_audio_ads={} skip_promoted={} notifications={} mode={:?}",
-                  cfg.threshold,
-                  cfg.enable_scrobble,
-                  cfg.skip_audio_ads,
-                  cfg.skip_promoted,
-                  cfg.enable_notifications,
-                  cfg.notification_mode
-              );
-                let _ = socket.write_all(response.as_bytes()).await;
-                let _ = socket.shutdown().await;
-                return;
-              } else if method == "GET" && path == "/events" {
-                let events = {
-                  let mut lock = state_clone.lock().unwrap();
-                  lock.events.drain(..).collect::<Vec<ToastEvent>>()
-                };
-                let body = serde_json::to_string(&serde_json::json!({ "events": events }))
-                  .unwrap_or_else(|_| "{}".to_string());
-                let response = format!(
-                  "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nAccess-Control-Allow-Origin: *\r\nAccess-Control-Allow-Methods: GET, POST, OPTIONS\r\nAccess-Control-Allow-Headers: Content-Type\r\n\r\n{}",
-                  body
-                );
-                log::info!("[Events] GET /events returned {} events", events.len());
-                let _ = socket.write_all(response.as_bytes()).await;
-                let _ = socket.shutdown().await;
-                return;
-              }
-              break;
-            }
-          }
-          if total_read == buf.len() {
-            buf.resize(buf.len() * 2, 0);
-          }
-        }
-        let _ = socket.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nAccess-Control-Allow-Origin: *\r\nAccess-Control-Allow-Methods: GET, POST, OPTIONS\r\nAccess-Control-Allow-Headers: Content-Type\r\n\r\nok").await;
-        let _ = socket.shutdown().await;
-      });
-    }
+    local_server::serve(listener, Api::Overlay, token, move |route, body| {
+      handle_local_request(app.clone(), state.clone(), route, body)
+    })
+    .await;
   });
 
   Some(local_url)
@@ -1876,7 +5303,7 @@ fn start_playback_server(
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
   let key_for_overlay = lastfm_key().unwrap_or_else(|| "REPLACE_ME".to_string());
-  let lastfm_cb = lastfm_callback();
+  let lastfm_cb = lastfm_callback().unwrap_or_else(|| "(local callback server)".to_string());
   let context = tauri::generate_context!();
   let version = context
     .config()
@@ -1884,6 +5311,9 @@ pub fn run() {
     .clone()
     .unwrap_or_else(|| "0.0.0".to_string());
 
+  let server_token = auth::random_token().expect("OS randomness unavailable");
+  let server_token_for_setup = server_token.clone();
+
   let playback_url_holder = Arc::new(Mutex::new(String::new()));
   let playback_url_for_load = playback_url_holder.clone();
   let playback_url_for_setup = playback_url_holder.clone();
@@ -1926,12 +5356,60 @@ pub fn run() {
       complete_lastfm,
       get_lastfm_status,
       disconnect_lastfm,
-      report_playback
+      report_playback,
+      get_settings,
+      update_settings,
+      take_toasts,
+      get_status,
+      get_control_api,
+      regenerate_control_token,
+      reconnect_mqtt,
+      verify_scrobbles,
+      get_missing_scrobbles,
+      resubmit_scrobbles,
+      dismiss_missing_scrobbles,
+      prepare_backfill,
+      submit_backfill,
+      manual_scrobble,
+      get_lastfm_profile,
+      refresh_lastfm_profile,
+      get_artist_info,
+      get_similar_artists,
+      open_soundcloud_search,
+      get_love_sync_status,
+      sync_loves,
+      get_track_tags,
+      update_track_tags,
+      get_auth_options,
+      begin_lastfm_auth,
+      start_desktop_auth,
+      await_desktop_auth,
+      cancel_desktop_auth,
+      login_lastfm_mobile
     ])
     .setup(move |app| {
+      let proxy = read_store().scrobble_config.proxy;
+      let http_client = build_http_client(app.handle(), proxy.as_deref())?;
+      app.manage(http_client);
+      app.manage(EventHub::default());
       app.manage(Arc::new(Mutex::new(ScrobbleState::default())));
+      app.manage(Mutex::new(ControlServer::default()));
+      app.manage(Mutex::new(MqttPublisher::new()));
+      // Debug builds without a configured callback use the loopback server, which needs no registration.
+      app.manage(Arc::new(Mutex::new(AuthState {
+        callback_registered: cfg!(debug_assertions),
+        ..AuthState::default()
+      })));
       let scrobble_state = app.state::<Arc<Mutex<ScrobbleState>>>();
-      if let Some(url) = start_playback_server(app.handle().clone(), scrobble_state.inner().clone()) {
+      // The overlay uses IPC unless the HTTP fallback is enabled; an empty playback
+      // URL tells it which.
+      if !read_store().scrobble_config.local_server {
+        log::info!("[Last.fm] Overlay uses IPC; local playback server disabled");
+      } else if let Some(url) = start_playback_server(
+        app.handle().clone(),
+        scrobble_state.inner().clone(),
+        server_token_for_setup.clone(),
+      ) {
         if let Ok(mut w) = playback_url_for_setup.lock() {
           *w = url.clone();
         }
@@ -1939,6 +5417,11 @@ pub fn run() {
       } else {
         log::warn!("[Last.fm] Failed to start playback server");
       }
+      tauri::async_runtime::spawn(restart_control_server(app.handle().clone()));
+      tauri::async_runtime::spawn(restart_mqtt(app.handle().clone()));
+      start_mqtt_refresh(app.handle().clone());
+      start_scrobble_verifier(app.handle().clone(), scrobble_state.inner().clone());
+      start_session_monitor(app.handle().clone(), scrobble_state.inner().clone());
       // Create the main window manually so we can set the WebView data directory for portable use.
       if let Some(conf) = app.config().app.windows.get(0).cloned() {
         let mut builder = tauri::WebviewWindowBuilder::from_config(app.handle(), &conf)?;
@@ -1952,15 +5435,24 @@ pub fn run() {
         }
         builder.build()?;
       }
-      #[cfg(debug_assertions)]
-      {
-        start_dev_callback_server(app.handle().clone());
-      }
       #[cfg(desktop)]
       {
         let handle = app.handle().clone();
         let deep = handle.deep_link();
-        let _ = deep.register_all();
+        let registered = match deep.register_all() {
+          Ok(()) => true,
+          Err(err) => {
+            log::warn!("[Last.fm] Failed to register mscd:// handler: {}", err);
+            false
+          }
+        };
+        // macOS registers the scheme from the bundle; a custom http callback needs no handler.
+        if !cfg!(debug_assertions) {
+          let registered = registered
+            || cfg!(target_os = "macos")
+            || !lastfm_callback().is_some_and(|cb| cb.starts_with("mscd://"));
+          handle.state::<Arc<Mutex<AuthState>>>().lock().unwrap().callback_registered = registered;
+        }
 
         if let Ok(Some(urls)) = deep.get_current() {
           log::info!("[Last.fm] deep_link get_current: {:?}", urls);
@@ -1994,11 +5486,24 @@ pub fn run() {
       let key_for_overlay = key_for_overlay.clone();
       let lastfm_cb = lastfm_cb.clone();
       let version = version.clone();
-      move |window, _| {
+      move |window, payload| {
+        if matches!(payload.event(), PageLoadEvent::Started) {
+          // Navigation tears down the old overlay; the new one reattaches via `take_toasts`.
+          if let Some(state) = window.try_state::<Arc<Mutex<ScrobbleState>>>() {
+            state.lock().unwrap().overlay_attached = false;
+          }
+        }
         let playback_url = playback_url_for_load.lock().unwrap().clone();
         let initial_cfg = load_scrobble_config(&window.app_handle());
-        let initial_settings = serde_json::to_string(&initial_cfg).unwrap_or_else(|_| "{}".to_string());
-        let script = build_overlay_script(&key_for_overlay, &lastfm_cb, &version, &playback_url, &initial_settings);
+        let initial_settings = serde_json::to_string(&redacted(initial_cfg)).unwrap_or_else(|_| "{}".to_string());
+        let script = build_overlay_script(
+          &key_for_overlay,
+          &lastfm_cb,
+          &version,
+          &playback_url,
+          &server_token,
+          &initial_settings,
+        );
       let _ = window.eval(&script);
       }
     });
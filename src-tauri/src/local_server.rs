@@ -0,0 +1,1308 @@
+// Loopback HTTP servers: the one the overlay talks to (playback reports, settings,
+// and a Server-Sent Events stream of toasts and state changes) and the opt-in
+// control API for scripts and stream decks. Routing, limits and status codes live
+// here; lib.rs supplies the handler that does the work for each route.
+//
+// Every request must carry the server's token (the per-launch one handed to the
+// overlay script, or the stored control API token), and browser requests must come
+// from a SoundCloud page. Only CORS preflights, which cannot carry custom headers,
+// are exempt from the token.
+//
+// Slow or oversized clients are cut off (see `Limits`): headers and bodies must
+// arrive within a timeout, idle keep-alive connections are closed, header and body
+// sizes are capped, connections and event streams beyond their caps are refused,
+// and playback reports are rate limited.
+
+use std::convert::Infallible;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use http_body_util::combinators::UnsyncBoxBody;
+use http_body_util::{BodyExt, Channel, Full, LengthLimitError, Limited};
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{
+  HeaderName, HeaderValue, ALLOW, AUTHORIZATION, CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE, ORIGIN,
+  RETRY_AFTER, VARY,
+};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::{TokioIo, TokioTimer};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
+use tokio::task::JoinSet;
+
+// Playback reports and settings are a few hundred bytes; anything near this is not ours.
+pub const MAX_BODY_BYTES: usize = 64 * 1024;
+pub const TOKEN_HEADER: &str = "x-mscd-token";
+// Comment lines on idle streams, so dropped clients are noticed and proxies keep them open.
+const STREAM_KEEPALIVE: Duration = Duration::from_secs(20);
+const EVENT_BUFFER: usize = 64;
+
+/// Per-server limits; `Limits::default()` is what the app runs with.
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+  /// Time to send a request's headers. Also closes keep-alive connections idle this
+  /// long; event streams are exempt because no request is read while they run.
+  pub header_timeout: Duration,
+  /// Time to send a request body once the headers are in.
+  pub body_timeout: Duration,
+  /// Request line plus headers, enforced by `HeadLimit`.
+  pub max_header_bytes: usize,
+  /// Open connections, not counting those serving an event stream.
+  pub max_connections: usize,
+  /// Open event streams, capped separately so subscribers can't starve requests.
+  pub max_streams: usize,
+  /// Playback reports allowed at once, and refilled per second. The overlay sends
+  /// one every 2 s.
+  pub playback_burst: u32,
+  pub playback_per_sec: u32,
+}
+
+impl Default for Limits {
+  fn default() -> Self {
+    Self {
+      header_timeout: Duration::from_secs(10),
+      body_timeout: Duration::from_secs(10),
+      max_header_bytes: 16 * 1024,
+      max_connections: 32,
+      max_streams: 8,
+      playback_burst: 20,
+      playback_per_sec: 5,
+    }
+  }
+}
+
+/// Token bucket: `burst` requests at once, refilled at `per_sec`.
+#[derive(Debug)]
+pub struct RateLimiter {
+  burst: f64,
+  per_sec: f64,
+  tokens: f64,
+  last: Instant,
+}
+
+impl RateLimiter {
+  pub fn new(burst: u32, per_sec: u32, now: Instant) -> Self {
+    Self {
+      burst: burst as f64,
+      per_sec: per_sec as f64,
+      tokens: burst as f64,
+      last: now,
+    }
+  }
+
+  pub fn try_take(&mut self, now: Instant) -> bool {
+    let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+    self.last = now;
+    self.tokens = (self.tokens + elapsed * self.per_sec).min(self.burst);
+    if self.tokens >= 1.0 {
+      self.tokens -= 1.0;
+      true
+    } else {
+      false
+    }
+  }
+}
+
+/// Which server a listener runs; each has its own routes and token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Api {
+  /// Per-launch server the overlay uses when the IPC transport is turned off.
+  Overlay,
+  /// Control API on the configured port, enabled from settings.
+  Control,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Route {
+  Playback,
+  GetSettings,
+  UpdateSettings,
+  Events,
+  NowPlaying,
+  Status,
+  Config,
+  Widget,
+  WidgetFeed,
+  Control(Control),
+}
+
+/// Player actions accepted on `POST /control/<action>`; the overlay performs them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Control {
+  Play,
+  Pause,
+  Toggle,
+  Next,
+  Prev,
+  Seek,
+  Like,
+}
+
+impl Control {
+  pub fn parse(action: &str) -> Option<Self> {
+    Some(match action {
+      "play" => Self::Play,
+      "pause" => Self::Pause,
+      "toggle" => Self::Toggle,
+      "next" => Self::Next,
+      "prev" => Self::Prev,
+      "seek" => Self::Seek,
+      "like" => Self::Like,
+      _ => return None,
+    })
+  }
+}
+
+const OVERLAY_ROUTES: &[(&str, &str)] = &[
+  ("/playback", "POST, OPTIONS"),
+  ("/settings", "GET, POST, OPTIONS"),
+  ("/events", "GET, OPTIONS"),
+];
+
+const CONTROL_ROUTES: &[(&str, &str)] = &[
+  ("/now-playing", "GET, OPTIONS"),
+  ("/status", "GET, OPTIONS"),
+  ("/config", "GET, OPTIONS"),
+  ("/widget", "GET, OPTIONS"),
+  ("/widget.json", "GET, OPTIONS"),
+];
+const CONTROL_PREFIX: &str = "/control/";
+
+/// Resolves a request to a route, or to the reply for unknown paths (404), wrong
+/// methods (405) and CORS preflights (204).
+pub fn route(api: Api, method: &Method, path: &str) -> Result<Route, Reply> {
+  let routes = match api {
+    Api::Overlay => OVERLAY_ROUTES,
+    Api::Control => CONTROL_ROUTES,
+  };
+  let control = match api {
+    Api::Control => path.strip_prefix(CONTROL_PREFIX).and_then(Control::parse),
+    Api::Overlay => None,
+  };
+  let allow = match routes.iter().find(|(p, _)| *p == path) {
+    Some((_, allow)) => *allow,
+    None if control.is_some() => "POST, OPTIONS",
+    None => return Err(Reply::error(StatusCode::NOT_FOUND, "not found")),
+  };
+  if let (Some(action), &Method::POST) = (control, method) {
+    return Ok(Route::Control(action));
+  }
+  match (api, path, method) {
+    (_, _, &Method::OPTIONS) => Err(Reply::no_content().with_allow(allow)),
+    (Api::Overlay, "/playback", &Method::POST) => Ok(Route::Playback),
+    (Api::Overlay, "/settings", &Method::GET) => Ok(Route::GetSettings),
+    (Api::Overlay, "/settings", &Method::POST) => Ok(Route::UpdateSettings),
+    (Api::Overlay, "/events", &Method::GET) => Ok(Route::Events),
+    (Api::Control, "/now-playing", &Method::GET) => Ok(Route::NowPlaying),
+    (Api::Control, "/status", &Method::GET) => Ok(Route::Status),
+    (Api::Control, "/config", &Method::GET) => Ok(Route::Config),
+    (Api::Control, "/widget", &Method::GET) => Ok(Route::Widget),
+    (Api::Control, "/widget.json", &Method::GET) => Ok(Route::WidgetFeed),
+    _ => Err(Reply::error(StatusCode::METHOD_NOT_ALLOWED, "method not allowed").with_allow(allow)),
+  }
+}
+
+/// One Server-Sent Event; `data` is JSON on a single line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerEvent {
+  pub name: &'static str,
+  pub data: String,
+}
+
+impl ServerEvent {
+  pub fn new<T: serde::Serialize>(name: &'static str, data: &T) -> Self {
+    Self {
+      name,
+      data: serde_json::to_string(data).unwrap_or_else(|_| "null".to_string()),
+    }
+  }
+
+  fn to_sse(&self) -> String {
+    format!("event: {}\ndata: {}\n\n", self.name, self.data)
+  }
+}
+
+/// Fan-out to every open event stream.
+#[derive(Clone)]
+pub struct EventHub {
+  tx: broadcast::Sender<ServerEvent>,
+}
+
+impl Default for EventHub {
+  fn default() -> Self {
+    Self {
+      tx: broadcast::channel(EVENT_BUFFER).0,
+    }
+  }
+}
+
+impl EventHub {
+  /// Returns false when no stream is open, so callers can queue what must not be lost.
+  pub fn publish(&self, event: ServerEvent) -> bool {
+    self.tx.send(event).is_ok()
+  }
+
+  pub fn subscribe(&self) -> broadcast::Receiver<ServerEvent> {
+    self.tx.subscribe()
+  }
+}
+
+enum Body {
+  Full(Bytes),
+  // The slot, once `dispatch` attaches it, is released when the stream ends.
+  Stream(Channel<Bytes>, Option<StreamSlot>),
+}
+
+// One open event stream, counted against `Limits::max_streams` until dropped.
+struct StreamSlot(Arc<AtomicUsize>);
+
+impl StreamSlot {
+  fn take(open: &Arc<AtomicUsize>, max: usize) -> Option<Self> {
+    open
+      .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| (n < max).then_some(n + 1))
+      .ok()
+      .map(|_| Self(open.clone()))
+  }
+}
+
+impl Drop for StreamSlot {
+  fn drop(&mut self) {
+    self.0.fetch_sub(1, Ordering::SeqCst);
+  }
+}
+
+pub struct Reply {
+  pub status: StatusCode,
+  content_type: Option<&'static str>,
+  headers: Vec<(HeaderName, &'static str)>,
+  body: Body,
+}
+
+impl Reply {
+  pub fn json<T: serde::Serialize>(value: &T) -> Self {
+    match serde_json::to_vec(value) {
+      Ok(body) => Self {
+        status: StatusCode::OK,
+        content_type: Some("application/json"),
+        headers: Vec::new(),
+        body: Body::Full(body.into()),
+      },
+      Err(err) => Self::error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string()),
+    }
+  }
+
+  pub fn no_content() -> Self {
+    Self {
+      status: StatusCode::NO_CONTENT,
+      content_type: None,
+      headers: Vec::new(),
+      body: Body::Full(Bytes::new()),
+    }
+  }
+
+  pub fn html(page: &'static str) -> Self {
+    Self {
+      status: StatusCode::OK,
+      content_type: Some("text/html; charset=utf-8"),
+      headers: Vec::new(),
+      body: Body::Full(Bytes::from_static(page.as_bytes())),
+    }
+  }
+
+  /// 202: the request was handed on (to the overlay) but hasn't happened yet.
+  pub fn accepted() -> Self {
+    Self {
+      status: StatusCode::ACCEPTED,
+      ..Self::no_content()
+    }
+  }
+
+  pub fn error(status: StatusCode, message: &str) -> Self {
+    Self {
+      status,
+      content_type: Some("application/json"),
+      headers: Vec::new(),
+      body: Body::Full(serde_json::json!({ "error": message }).to_string().into()),
+    }
+  }
+
+  /// SSE response: `backlog` first, then everything published to `events` until the
+  /// client goes away.
+  pub fn event_stream(backlog: Vec<ServerEvent>, mut events: broadcast::Receiver<ServerEvent>) -> Self {
+    let (mut tx, body) = Channel::<Bytes>::new(16);
+    tokio::spawn(async move {
+      for event in backlog {
+        if tx.send_data(event.to_sse().into()).await.is_err() {
+          return;
+        }
+      }
+      let mut keepalive = tokio::time::interval(STREAM_KEEPALIVE);
+      loop {
+        let chunk = tokio::select! {
+          event = events.recv() => match event {
+            Ok(event) => event.to_sse(),
+            Err(broadcast::error::RecvError::Lagged(n)) => {
+              log::warn!("[Server] Event stream fell behind; dropped {} events", n);
+              continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+          },
+          _ = keepalive.tick() => ": keepalive\n\n".to_string(),
+        };
+        if tx.send_data(chunk.into()).await.is_err() {
+          return;
+        }
+      }
+    });
+    Self {
+      status: StatusCode::OK,
+      content_type: Some("text/event-stream"),
+      headers: Vec::new(),
+      body: Body::Stream(body, None),
+    }
+  }
+
+  fn with_slot(mut self, slot: Option<StreamSlot>) -> Self {
+    if let Body::Stream(_, held) = &mut self.body {
+      *held = slot;
+    }
+    self
+  }
+
+  fn with_header(mut self, name: HeaderName, value: &'static str) -> Self {
+    self.headers.push((name, value));
+    self
+  }
+
+  fn with_allow(self, allow: &'static str) -> Self {
+    self.with_header(ALLOW, allow)
+  }
+
+  // CORS headers only name an allowed origin, never `*`.
+  fn into_response(self, origin: Option<HeaderValue>) -> Response<UnsyncBoxBody<Bytes, Infallible>> {
+    let streaming = matches!(self.body, Body::Stream(..));
+    let body = match self.body {
+      Body::Full(bytes) => Full::new(bytes).boxed_unsync(),
+      Body::Stream(channel, slot) => channel
+        .map_frame(move |frame| {
+          let _held = &slot;
+          frame
+        })
+        .boxed_unsync(),
+    };
+    let mut res = Response::new(body);
+    *res.status_mut() = self.status;
+    let headers = res.headers_mut();
+    if streaming {
+      headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    }
+    headers.insert(VARY, HeaderValue::from_static("Origin"));
+    if let Some(origin) = origin {
+      headers.insert("Access-Control-Allow-Origin", origin);
+      headers.insert(
+        "Access-Control-Allow-Methods",
+        HeaderValue::from_static("GET, POST, OPTIONS"),
+      );
+      headers.insert(
+        "Access-Control-Allow-Headers",
+        HeaderValue::from_static("Content-Type, Authorization, X-MSCD-Token"),
+      );
+    }
+    if let Some(content_type) = self.content_type {
+      headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    }
+    for (name, value) in self.headers {
+      headers.insert(name, HeaderValue::from_static(value));
+    }
+    res
+  }
+}
+
+/// https://soundcloud.com and its subdomains.
+pub fn origin_allowed(origin: &str) -> bool {
+  match url::Url::parse(origin) {
+    Ok(url) => {
+      url.scheme() == "https"
+        && url
+          .host_str()
+          .is_some_and(|host| host == "soundcloud.com" || host.ends_with(".soundcloud.com"))
+    }
+    Err(_) => false,
+  }
+}
+
+// Constant-time, so the token can't be guessed byte by byte from response timings.
+fn token_matches(given: &[u8], expected: &[u8]) -> bool {
+  given.len() == expected.len() && given.iter().zip(expected).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// Decodes a JSON body, answering 400 when it doesn't match `T`.
+pub fn parse_json<T: serde::de::DeserializeOwned>(body: &[u8]) -> Result<T, Reply> {
+  serde_json::from_slice(body).map_err(|err| Reply::error(StatusCode::BAD_REQUEST, &err.to_string()))
+}
+
+// Works for Content-Length and chunked bodies alike; oversized ones get 413.
+async fn read_body(req: Request<Incoming>) -> Result<Bytes, Reply> {
+  let too_large = || Reply::error(StatusCode::PAYLOAD_TOO_LARGE, "request body too large");
+  let declared = req
+    .headers()
+    .get(CONTENT_LENGTH)
+    .and_then(|v| v.to_str().ok())
+    .and_then(|v| v.parse::<usize>().ok());
+  if declared.is_some_and(|len| len > MAX_BODY_BYTES) {
+    return Err(too_large());
+  }
+  match Limited::new(req.into_body(), MAX_BODY_BYTES).collect().await {
+    Ok(collected) => Ok(collected.to_bytes()),
+    Err(err) if err.downcast_ref::<LengthLimitError>().is_some() => Err(too_large()),
+    Err(err) => Err(Reply::error(StatusCode::BAD_REQUEST, &err.to_string())),
+  }
+}
+
+// The token travels in `X-MSCD-Token` or as `Authorization: Bearer <token>`. GET
+// requests may also pass `?token=`, for browser sources that can only take a URL.
+fn given_token(req: &Request<Incoming>) -> Option<Vec<u8>> {
+  if let Some(token) = req.headers().get(TOKEN_HEADER) {
+    return Some(token.as_bytes().to_vec());
+  }
+  if let Some(token) = req
+    .headers()
+    .get(AUTHORIZATION)
+    .and_then(|v| v.as_bytes().strip_prefix(b"Bearer "))
+  {
+    return Some(token.to_vec());
+  }
+  if req.method() != Method::GET {
+    return None;
+  }
+  url::form_urlencoded::parse(req.uri().query()?.as_bytes())
+    .find(|(name, _)| name == "token")
+    .map(|(_, value)| value.into_owned().into_bytes())
+}
+
+// Rejects foreign origins (403) and missing or wrong tokens (401), logging both.
+fn authorize(req: &Request<Incoming>, token: &str) -> Result<(), Reply> {
+  let reject = |status: StatusCode, reason: &str| {
+    log::warn!(
+      "[Server] Rejected {} {} (origin {:?}): {}",
+      req.method(),
+      req.uri().path(),
+      req.headers().get(ORIGIN),
+      reason
+    );
+    Reply::error(status, reason)
+  };
+  if let Some(origin) = req.headers().get(ORIGIN) {
+    if !origin.to_str().is_ok_and(origin_allowed) {
+      return Err(reject(StatusCode::FORBIDDEN, "origin not allowed"));
+    }
+  }
+  if req.method() == Method::OPTIONS {
+    return Ok(());
+  }
+  match given_token(req) {
+    None => Err(reject(StatusCode::UNAUTHORIZED, "missing token")),
+    Some(given) if !token_matches(&given, token.as_bytes()) => {
+      Err(reject(StatusCode::UNAUTHORIZED, "invalid token"))
+    }
+    Some(_) => Ok(()),
+  }
+}
+
+// State shared by every connection of one server.
+struct Shared<H> {
+  api: Api,
+  token: Arc<str>,
+  limits: Limits,
+  playback: Mutex<RateLimiter>,
+  streams: Arc<AtomicUsize>,
+  handler: H,
+}
+
+async fn dispatch<H, Fut>(req: Request<Incoming>, shared: &Shared<H>) -> Reply
+where
+  H: Fn(Route, Bytes) -> Fut,
+  Fut: Future<Output = Reply>,
+{
+  if let Err(reply) = authorize(&req, &shared.token) {
+    return reply;
+  }
+  let route = match route(shared.api, req.method(), req.uri().path()) {
+    Ok(route) => route,
+    Err(reply) => return reply,
+  };
+  if route == Route::Playback && !shared.playback.lock().unwrap().try_take(Instant::now()) {
+    log::debug!("[Server] Playback report rate limited");
+    return Reply::error(StatusCode::TOO_MANY_REQUESTS, "too many requests").with_header(RETRY_AFTER, "1");
+  }
+  let slot = match route {
+    Route::Events => match StreamSlot::take(&shared.streams, shared.limits.max_streams) {
+      Some(slot) => Some(slot),
+      None => {
+        log::warn!("[Server] Refused event stream: {} already open", shared.limits.max_streams);
+        return Reply::error(StatusCode::SERVICE_UNAVAILABLE, "too many event streams");
+      }
+    },
+    _ => None,
+  };
+  match tokio::time::timeout(shared.limits.body_timeout, read_body(req)).await {
+    Ok(Ok(body)) => (shared.handler)(route, body).await.with_slot(slot),
+    Ok(Err(reply)) => reply,
+    Err(_) => Reply::error(StatusCode::REQUEST_TIMEOUT, "request body timed out"),
+  }
+}
+
+// Request framing as seen by `HeadLimit`.
+enum Framing {
+  // Request line and headers, kept until the blank line to find the body length.
+  Head(Vec<u8>),
+  Body(u64),
+  ChunkSize(Vec<u8>),
+  // Chunk data plus its trailing CRLF.
+  ChunkData(u64),
+  Trailers(Vec<u8>),
+}
+
+// Caps every request head on a connection at `max` bytes. hyper's read buffer limit
+// doesn't reliably reject long heads, so bytes are checked as they are read: a head
+// runs to its blank line, then the body (Content-Length or chunked) is skipped up to
+// the next head. Going over fails the read, which drops the connection.
+struct HeadLimit<S> {
+  inner: S,
+  max: usize,
+  framing: Framing,
+}
+
+fn ends_line_block(buf: &[u8]) -> bool {
+  buf == b"\r\n" || buf == b"\n" || buf.ends_with(b"\n\r\n") || buf.ends_with(b"\n\n")
+}
+
+// What follows a complete head; invalid lengths are left for hyper to reject.
+fn body_framing(head: &[u8]) -> Framing {
+  let head = String::from_utf8_lossy(head);
+  let mut framing = Framing::Head(Vec::new());
+  for line in head.lines().skip(1) {
+    let (name, value) = match line.split_once(':') {
+      Some(pair) => pair,
+      None => continue,
+    };
+    let name = name.trim();
+    if name.eq_ignore_ascii_case("transfer-encoding") && value.to_ascii_lowercase().contains("chunked") {
+      return Framing::ChunkSize(Vec::new());
+    }
+    if name.eq_ignore_ascii_case("content-length") {
+      if let Ok(len) = value.trim().parse::<u64>() {
+        if len > 0 {
+          framing = Framing::Body(len);
+        }
+      }
+    }
+  }
+  framing
+}
+
+impl<S> HeadLimit<S> {
+  fn new(inner: S, max: usize) -> Self {
+    Self {
+      inner,
+      max,
+      framing: Framing::Head(Vec::new()),
+    }
+  }
+
+  fn feed(&mut self, mut data: &[u8]) -> io::Result<()> {
+    while !data.is_empty() {
+      let next = match &mut self.framing {
+        Framing::Body(left) | Framing::ChunkData(left) => {
+          let take = (*left).min(data.len() as u64);
+          *left -= take;
+          data = &data[take as usize..];
+          match self.framing {
+            Framing::Body(0) => Some(Framing::Head(Vec::new())),
+            Framing::ChunkData(0) => Some(Framing::ChunkSize(Vec::new())),
+            _ => None,
+          }
+        }
+        Framing::Head(buf) | Framing::ChunkSize(buf) | Framing::Trailers(buf) => {
+          buf.push(data[0]);
+          data = &data[1..];
+          if buf.len() > self.max {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "request head too large"));
+          }
+          match &self.framing {
+            // Blank lines between requests are allowed.
+            Framing::Head(buf) if buf == b"\r\n" || buf == b"\n" => Some(Framing::Head(Vec::new())),
+            Framing::Head(buf) if ends_line_block(buf) => Some(body_framing(buf)),
+            Framing::ChunkSize(buf) if buf.ends_with(b"\n") => {
+              let line = String::from_utf8_lossy(buf);
+              let size = line.split(';').next().unwrap_or("").trim();
+              match u64::from_str_radix(size, 16) {
+                Ok(0) => Some(Framing::Trailers(Vec::new())),
+                Ok(size) => Some(Framing::ChunkData(size.saturating_add(2))),
+                Err(_) => return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid chunk size")),
+              }
+            }
+            Framing::Trailers(buf) if ends_line_block(buf) => Some(Framing::Head(Vec::new())),
+            _ => None,
+          }
+        }
+      };
+      if let Some(next) = next {
+        self.framing = next;
+      }
+    }
+    Ok(())
+  }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for HeadLimit<S> {
+  fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+    let this = self.get_mut();
+    let before = buf.filled().len();
+    match Pin::new(&mut this.inner).poll_read(cx, buf) {
+      Poll::Ready(Ok(())) => Poll::Ready(this.feed(&buf.filled()[before..])),
+      other => other,
+    }
+  }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for HeadLimit<S> {
+  fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+    Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+  }
+
+  fn poll_write_vectored(
+    self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+    bufs: &[io::IoSlice<'_>],
+  ) -> Poll<io::Result<usize>> {
+    Pin::new(&mut self.get_mut().inner).poll_write_vectored(cx, bufs)
+  }
+
+  fn is_write_vectored(&self) -> bool {
+    self.inner.is_write_vectored()
+  }
+
+  fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+    Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+  }
+
+  fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+    Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+  }
+}
+
+/// Accepts connections until the listener fails; each connection is served on its
+/// own task with HTTP/1.1 keep-alive. Dropping (or aborting) the returned future
+/// closes every connection it accepted. `token` is the secret requests must carry in
+/// the `X-MSCD-Token` header (or as a bearer token).
+pub async fn serve<H, Fut>(listener: TcpListener, api: Api, token: String, handler: H)
+where
+  H: Fn(Route, Bytes) -> Fut + Send + Sync + 'static,
+  Fut: Future<Output = Reply> + Send + 'static,
+{
+  serve_with(listener, api, token, Limits::default(), handler).await
+}
+
+/// `serve` with explicit limits.
+pub async fn serve_with<H, Fut>(listener: TcpListener, api: Api, token: String, limits: Limits, handler: H)
+where
+  H: Fn(Route, Bytes) -> Fut + Send + Sync + 'static,
+  Fut: Future<Output = Reply> + Send + 'static,
+{
+  let shared = Arc::new(Shared {
+    api,
+    token: token.into(),
+    limits,
+    playback: Mutex::new(RateLimiter::new(limits.playback_burst, limits.playback_per_sec, Instant::now())),
+    streams: Arc::new(AtomicUsize::new(0)),
+    handler,
+  });
+  let mut connections = JoinSet::new();
+  loop {
+    let stream = match listener.accept().await {
+      Ok((stream, _)) => stream,
+      Err(err) => {
+        log::warn!("[Server] Accept failed: {}", err);
+        continue;
+      }
+    };
+    while connections.try_join_next().is_some() {}
+    // Connections serving an event stream fall under `max_streams` instead.
+    let open = connections.len().saturating_sub(shared.streams.load(Ordering::SeqCst));
+    if open >= limits.max_connections {
+      log::warn!("[Server] Dropped connection: {} already open", open);
+      continue;
+    }
+    let shared = shared.clone();
+    connections.spawn(async move {
+      let service = service_fn(move |req: Request<Incoming>| {
+        let shared = shared.clone();
+        let origin = req
+          .headers()
+          .get(ORIGIN)
+          .filter(|o| o.to_str().is_ok_and(origin_allowed))
+          .cloned();
+        async move { Ok::<_, Infallible>(dispatch(req, &shared).await.into_response(origin)) }
+      });
+      if let Err(err) = http1::Builder::new()
+        .timer(TokioTimer::new())
+        .header_read_timeout(limits.header_timeout)
+        .max_buf_size(limits.max_header_bytes)
+        .serve_connection(TokioIo::new(HeadLimit::new(stream, limits.max_header_bytes)), service)
+        .await
+      {
+        log::debug!("[Server] Connection closed with error: {}", err);
+      }
+    });
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::Mutex;
+  use tokio::io::{AsyncReadExt, AsyncWriteExt};
+  use tokio::net::TcpStream;
+
+  const TOKEN: &str = "0123456789abcdef";
+
+  #[derive(serde::Deserialize)]
+  struct Update {
+    threshold: f32,
+  }
+
+  async fn start() -> (std::net::SocketAddr, Arc<Mutex<Vec<String>>>, EventHub) {
+    start_with(Limits::default()).await
+  }
+
+  async fn start_with(limits: Limits) -> (std::net::SocketAddr, Arc<Mutex<Vec<String>>>, EventHub) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let played = Arc::new(Mutex::new(Vec::new()));
+    let log = played.clone();
+    let hub = EventHub::default();
+    let server_hub = hub.clone();
+    tokio::spawn(serve_with(listener, Api::Overlay, TOKEN.to_string(), limits, move |route, body: Bytes| {
+      let log = log.clone();
+      let hub = server_hub.clone();
+      async move {
+        match route {
+          Route::Playback => {
+            log.lock().unwrap().push(String::from_utf8_lossy(&body).into_owned());
+            Reply::no_content()
+          }
+          Route::GetSettings => Reply::json(&serde_json::json!({ "threshold": 0.5 })),
+          Route::UpdateSettings => match parse_json::<Update>(&body) {
+            Ok(update) => Reply::json(&serde_json::json!({ "threshold": update.threshold })),
+            Err(reply) => reply,
+          },
+          Route::Events => Reply::event_stream(
+            vec![ServerEvent::new("settings", &serde_json::json!({ "threshold": 0.5 }))],
+            hub.subscribe(),
+          ),
+          _ => Reply::error(StatusCode::INTERNAL_SERVER_ERROR, "not an overlay route"),
+        }
+      }
+    }));
+    (addr, played, hub)
+  }
+
+  async fn start_control() -> (std::net::SocketAddr, Arc<Mutex<Vec<Control>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let actions = Arc::new(Mutex::new(Vec::new()));
+    let log = actions.clone();
+    tokio::spawn(serve(listener, Api::Control, TOKEN.to_string(), move |route, _body: Bytes| {
+      let log = log.clone();
+      async move {
+        match route {
+          Route::NowPlaying => Reply::json(&serde_json::json!({ "title": "a" })),
+          Route::Config => Reply::json(&serde_json::json!({ "threshold": 0.5 })),
+          Route::Widget => Reply::html("<p>widget</p>"),
+          Route::Control(action) => {
+            log.lock().unwrap().push(action);
+            Reply::accepted()
+          }
+          _ => Reply::error(StatusCode::INTERNAL_SERVER_ERROR, "not a control route"),
+        }
+      }
+    }));
+    (addr, actions)
+  }
+
+  async fn read_until(stream: &mut TcpStream, out: &mut String, needle: &str) {
+    let mut buf = [0u8; 1024];
+    while !out.contains(needle) {
+      let n = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buf))
+        .await
+        .expect("timed out waiting for stream data")
+        .unwrap();
+      assert!(n > 0, "stream closed before {:?} arrived", needle);
+      out.push_str(&String::from_utf8_lossy(&buf[..n]));
+    }
+  }
+
+  // Waits for the server to close the connection and returns what it sent first.
+  async fn closed_within(stream: &mut TcpStream, wait: Duration) -> String {
+    let mut out = Vec::new();
+    match tokio::time::timeout(wait, stream.read_to_end(&mut out)).await {
+      Ok(_) => String::from_utf8_lossy(&out).into_owned(),
+      Err(_) => panic!("connection still open after {:?}", wait),
+    }
+  }
+
+  fn tight() -> Limits {
+    Limits {
+      header_timeout: Duration::from_millis(200),
+      body_timeout: Duration::from_millis(200),
+      ..Limits::default()
+    }
+  }
+
+  async fn send(addr: std::net::SocketAddr, raw: &str) -> String {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(raw.as_bytes()).await.unwrap();
+    let mut out = String::new();
+    stream.read_to_string(&mut out).await.unwrap();
+    out
+  }
+
+  fn post(path: &str, body: &str) -> String {
+    format!(
+      "POST {} HTTP/1.1\r\nHost: x\r\nX-MSCD-Token: 0123456789abcdef\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{}",
+      path,
+      body.len(),
+      body
+    )
+  }
+
+  #[tokio::test]
+  async fn serves_each_endpoint() {
+    let (addr, played, _) = start().await;
+
+    let res = send(
+      addr,
+      "GET /settings HTTP/1.1\r\nHost: x\r\nX-MSCD-Token: 0123456789abcdef\r\nOrigin: https://soundcloud.com\r\nConnection: close\r\n\r\n",
+    )
+    .await;
+    assert!(res.starts_with("HTTP/1.1 200"));
+    assert!(res.contains("access-control-allow-origin: https://soundcloud.com"));
+    assert!(res.ends_with(r#"{"threshold":0.5}"#));
+
+    let res = send(addr, &post("/settings", r#"{"threshold":0.75}"#)).await;
+    assert!(res.starts_with("HTTP/1.1 200"));
+    assert!(res.ends_with(r#"{"threshold":0.75}"#));
+
+    let res = send(addr, &post("/playback", r#"{"title":"a"}"#)).await;
+    assert!(res.starts_with("HTTP/1.1 204"));
+    assert_eq!(played.lock().unwrap().as_slice(), [r#"{"title":"a"}"#]);
+  }
+
+  #[tokio::test]
+  async fn rejects_bad_requests() {
+    let (addr, _, _) = start().await;
+
+    let res = send(addr, "GET /nope HTTP/1.1\r\nHost: x\r\nX-MSCD-Token: 0123456789abcdef\r\nConnection: close\r\n\r\n").await;
+    assert!(res.starts_with("HTTP/1.1 404"));
+
+    let res = send(addr, "DELETE /settings HTTP/1.1\r\nHost: x\r\nX-MSCD-Token: 0123456789abcdef\r\nConnection: close\r\n\r\n").await;
+    assert!(res.starts_with("HTTP/1.1 405"));
+    assert!(res.contains("allow: GET, POST, OPTIONS"));
+
+    let res = send(addr, "OPTIONS /playback HTTP/1.1\r\nHost: x\r\nX-MSCD-Token: 0123456789abcdef\r\nConnection: close\r\n\r\n").await;
+    assert!(res.starts_with("HTTP/1.1 204"));
+
+    let res = send(addr, &post("/settings", "{not json")).await;
+    assert!(res.starts_with("HTTP/1.1 400"));
+
+    let res = send(addr, "GET /settings HTTP/1.1\r\nHost: x\r\nX-MSCD-Token: 0123456789abcdef\r\nBad Header\r\n\r\n").await;
+    assert!(res.starts_with("HTTP/1.1 400"));
+  }
+
+  #[tokio::test]
+  async fn requires_token_and_soundcloud_origin() {
+    let (addr, played, _) = start().await;
+
+    let res = send(addr, "POST /settings HTTP/1.1\r\nHost: x\r\nConnection: close\r\nContent-Length: 2\r\n\r\n{}").await;
+    assert!(res.starts_with("HTTP/1.1 401"));
+    let res = send(addr, "POST /playback HTTP/1.1\r\nHost: x\r\nX-MSCD-Token: 0123456789abcdee\r\nConnection: close\r\nContent-Length: 2\r\n\r\n{}").await;
+    assert!(res.starts_with("HTTP/1.1 401"));
+    assert!(played.lock().unwrap().is_empty());
+
+    let res = send(
+      addr,
+      "GET /settings HTTP/1.1\r\nHost: x\r\nX-MSCD-Token: 0123456789abcdef\r\nOrigin: https://evil.example\r\nConnection: close\r\n\r\n",
+    )
+    .await;
+    assert!(res.starts_with("HTTP/1.1 403"));
+    assert!(!res.contains("access-control-allow-origin"));
+
+    // Preflights can't carry the token but still need a SoundCloud origin.
+    let res = send(
+      addr,
+      "OPTIONS /settings HTTP/1.1\r\nHost: x\r\nOrigin: https://m.soundcloud.com\r\nConnection: close\r\n\r\n",
+    )
+    .await;
+    assert!(res.starts_with("HTTP/1.1 204"));
+    assert!(res.contains("access-control-allow-headers: Content-Type, Authorization, X-MSCD-Token"));
+    let res = send(
+      addr,
+      "OPTIONS /settings HTTP/1.1\r\nHost: x\r\nOrigin: http://soundcloud.com.evil.example\r\nConnection: close\r\n\r\n",
+    )
+    .await;
+    assert!(res.starts_with("HTTP/1.1 403"));
+  }
+
+  #[tokio::test]
+  async fn serves_control_api() {
+    let (addr, actions) = start_control().await;
+
+    let res = send(addr, "GET /now-playing HTTP/1.1\r\nHost: x\r\nAuthorization: Bearer 0123456789abcdef\r\nConnection: close\r\n\r\n").await;
+    assert!(res.starts_with("HTTP/1.1 200"));
+    assert!(res.ends_with(r#"{"title":"a"}"#));
+    let res = send(addr, "GET /config HTTP/1.1\r\nHost: x\r\nAuthorization: Bearer 0123456789abcdee\r\nConnection: close\r\n\r\n").await;
+    assert!(res.starts_with("HTTP/1.1 401"));
+
+    assert!(send(addr, &post("/control/next", "")).await.starts_with("HTTP/1.1 202"));
+    assert!(send(addr, &post("/control/seek", r#"{"position_ms":1000}"#)).await.starts_with("HTTP/1.1 202"));
+    assert_eq!(actions.lock().unwrap().as_slice(), [Control::Next, Control::Seek]);
+
+    assert!(send(addr, &post("/control/rewind", "")).await.starts_with("HTTP/1.1 404"));
+    let res = send(addr, "GET /control/play HTTP/1.1\r\nHost: x\r\nX-MSCD-Token: 0123456789abcdef\r\nConnection: close\r\n\r\n").await;
+    assert!(res.starts_with("HTTP/1.1 405"));
+    assert!(res.contains("allow: POST, OPTIONS"));
+    let res = send(addr, "GET /widget?token=0123456789abcdef HTTP/1.1\r\nHost: x\r\nConnection: close\r\n\r\n").await;
+    assert!(res.starts_with("HTTP/1.1 200"));
+    assert!(res.contains("content-type: text/html; charset=utf-8"));
+    let res = send(addr, "POST /control/next?token=0123456789abcdef HTTP/1.1\r\nHost: x\r\nConnection: close\r\nContent-Length: 0\r\n\r\n").await;
+    assert!(res.starts_with("HTTP/1.1 401"), "query tokens are for GET only");
+
+    // Each server only answers its own routes.
+    assert!(send(addr, &post("/playback", "{}")).await.starts_with("HTTP/1.1 404"));
+    assert_eq!(actions.lock().unwrap().len(), 2);
+  }
+
+  #[tokio::test]
+  async fn aborting_closes_open_connections() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(serve(listener, Api::Control, TOKEN.to_string(), |_, _| async {
+      Reply::accepted()
+    }));
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+      .write_all(b"POST /control/play HTTP/1.1\r\nHost: x\r\nX-MSCD-Token: 0123456789abcdef\r\nContent-Length: 0\r\n\r\n")
+      .await
+      .unwrap();
+    let mut out = String::new();
+    read_until(&mut stream, &mut out, "\r\n\r\n").await;
+    assert!(out.starts_with("HTTP/1.1 202"));
+
+    server.abort();
+    let mut rest = Vec::new();
+    let closed = tokio::time::timeout(Duration::from_secs(5), stream.read_to_end(&mut rest)).await;
+    assert!(closed.is_ok(), "connection stayed open after the server stopped");
+  }
+
+  #[tokio::test]
+  async fn times_out_slow_headers_and_idle_connections() {
+    let (addr, _, _) = start_with(tight()).await;
+
+    // Slowloris: headers trickle in but never finish.
+    let mut slow = TcpStream::connect(addr).await.unwrap();
+    slow.write_all(b"GET /settings HTTP/1.1\r\nHost: x\r\n").await.unwrap();
+    for _ in 0..3 {
+      tokio::time::sleep(Duration::from_millis(100)).await;
+      let _ = slow.write_all(b"X-Pad: 1\r\n").await;
+    }
+    let res = closed_within(&mut slow, Duration::from_secs(2)).await;
+    assert!(!res.contains(" 200 "));
+
+    let mut idle = TcpStream::connect(addr).await.unwrap();
+    idle
+      .write_all(b"GET /settings HTTP/1.1\r\nHost: x\r\nX-MSCD-Token: 0123456789abcdef\r\n\r\n")
+      .await
+      .unwrap();
+    let mut out = String::new();
+    read_until(&mut idle, &mut out, r#"{"threshold":0.5}"#).await;
+    closed_within(&mut idle, Duration::from_secs(2)).await;
+  }
+
+  #[tokio::test]
+  async fn times_out_slow_bodies() {
+    let (addr, played, _) = start_with(tight()).await;
+    let mut slow = TcpStream::connect(addr).await.unwrap();
+    slow
+      .write_all(b"POST /playback HTTP/1.1\r\nHost: x\r\nX-MSCD-Token: 0123456789abcdef\r\nContent-Length: 10\r\n\r\n{\"a")
+      .await
+      .unwrap();
+    let res = closed_within(&mut slow, Duration::from_secs(2)).await;
+    assert!(res.starts_with("HTTP/1.1 408"));
+    assert!(played.lock().unwrap().is_empty());
+  }
+
+  #[tokio::test]
+  async fn rejects_oversized_headers() {
+    let (addr, _, _) = start().await;
+    let raw = format!(
+      "GET /settings HTTP/1.1\r\nHost: x\r\nX-MSCD-Token: 0123456789abcdef\r\nX-Pad: {}\r\n\r\n",
+      "a".repeat(Limits::default().max_header_bytes)
+    );
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let _ = stream.write_all(raw.as_bytes()).await;
+    let mut out = Vec::new();
+    // The server may reset the connection with our bytes still unread.
+    let _ = tokio::time::timeout(Duration::from_secs(2), stream.read_to_end(&mut out)).await;
+    let res = String::from_utf8_lossy(&out);
+    assert!(res.is_empty() || res.starts_with("HTTP/1.1 431"), "{}", res);
+
+    let res = send(addr, "GET /settings HTTP/1.1\r\nHost: x\r\nX-MSCD-Token: 0123456789abcdef\r\nConnection: close\r\n\r\n").await;
+    assert!(res.starts_with("HTTP/1.1 200"));
+  }
+
+  #[tokio::test]
+  async fn rejects_oversized_heads_on_kept_alive_connections() {
+    let (addr, played, _) = start().await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let body = r#"{"title":"a"}"#;
+    let first = format!(
+      "POST /playback HTTP/1.1\r\nHost: x\r\nX-MSCD-Token: 0123456789abcdef\r\nContent-Length: {}\r\n\r\n{}",
+      body.len(),
+      body
+    );
+    stream.write_all(first.as_bytes()).await.unwrap();
+    let mut out = String::new();
+    read_until(&mut stream, &mut out, "HTTP/1.1 204").await;
+    let second = format!(
+      "GET /settings HTTP/1.1\r\nHost: x\r\nX-MSCD-Token: 0123456789abcdef\r\nX-Pad: {}\r\n\r\n",
+      "a".repeat(Limits::default().max_header_bytes)
+    );
+    let _ = stream.write_all(second.as_bytes()).await;
+    let rest = closed_within(&mut stream, Duration::from_secs(2)).await;
+    assert!(!rest.contains("HTTP/1.1 200"), "{}", rest);
+    assert_eq!(played.lock().unwrap().len(), 1);
+  }
+
+  #[test]
+  fn tracks_request_framing() {
+    let mut limit = HeadLimit::new((), 64);
+    // Body bytes never count toward the head limit, however they are split.
+    let body = "x".repeat(200);
+    let request = format!("POST /p HTTP/1.1\r\nContent-Length: 200\r\n\r\n{}", body);
+    for chunk in request.as_bytes().chunks(7) {
+      limit.feed(chunk).unwrap();
+    }
+    assert!(matches!(limit.framing, Framing::Head(ref buf) if buf.is_empty()));
+    let chunked = format!("POST /p HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n64\r\n{}\r\n0\r\n\r\n", "y".repeat(100));
+    limit.feed(chunked.as_bytes()).unwrap();
+    assert!(matches!(limit.framing, Framing::Head(ref buf) if buf.is_empty()));
+    assert!(limit.feed(format!("GET / HTTP/1.1\r\nX: {}\r\n\r\n", "z".repeat(64)).as_bytes()).is_err());
+  }
+
+  #[tokio::test]
+  async fn caps_event_streams_separately() {
+    let (addr, _, _) = start_with(Limits {
+      max_connections: 1,
+      max_streams: 2,
+      ..Limits::default()
+    })
+    .await;
+    let mut streams = Vec::new();
+    for _ in 0..2 {
+      let mut stream = TcpStream::connect(addr).await.unwrap();
+      stream
+        .write_all(b"GET /events HTTP/1.1\r\nHost: x\r\nX-MSCD-Token: 0123456789abcdef\r\n\r\n")
+        .await
+        .unwrap();
+      let mut out = String::new();
+      read_until(&mut stream, &mut out, "event: settings\n").await;
+      streams.push(stream);
+    }
+    // Open streams leave the connection cap to requests.
+    let res = send(addr, "GET /settings HTTP/1.1\r\nHost: x\r\nX-MSCD-Token: 0123456789abcdef\r\nConnection: close\r\n\r\n").await;
+    assert!(res.starts_with("HTTP/1.1 200"));
+    let res = send(addr, "GET /events HTTP/1.1\r\nHost: x\r\nX-MSCD-Token: 0123456789abcdef\r\nConnection: close\r\n\r\n").await;
+    assert!(res.starts_with("HTTP/1.1 503"), "{}", res);
+
+    // Closing a stream frees its slot.
+    drop(streams.pop());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+      .write_all(b"GET /events HTTP/1.1\r\nHost: x\r\nX-MSCD-Token: 0123456789abcdef\r\n\r\n")
+      .await
+      .unwrap();
+    let mut out = String::new();
+    read_until(&mut stream, &mut out, "event: settings\n").await;
+  }
+
+  #[tokio::test]
+  async fn caps_open_connections() {
+    let (addr, _, _) = start_with(Limits {
+      max_connections: 2,
+      ..Limits::default()
+    })
+    .await;
+    let mut open = Vec::new();
+    for _ in 0..2 {
+      let mut stream = TcpStream::connect(addr).await.unwrap();
+      stream
+        .write_all(b"GET /settings HTTP/1.1\r\nHost: x\r\nX-MSCD-Token: 0123456789abcdef\r\n\r\n")
+        .await
+        .unwrap();
+      let mut out = String::new();
+      read_until(&mut stream, &mut out, r#"{"threshold":0.5}"#).await;
+      open.push(stream);
+    }
+
+    let mut extra = TcpStream::connect(addr).await.unwrap();
+    assert_eq!(closed_within(&mut extra, Duration::from_secs(2)).await, "");
+
+    drop(open.pop());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let res = send(addr, "GET /settings HTTP/1.1\r\nHost: x\r\nX-MSCD-Token: 0123456789abcdef\r\nConnection: close\r\n\r\n").await;
+    assert!(res.starts_with("HTTP/1.1 200"));
+  }
+
+  #[tokio::test]
+  async fn rate_limits_playback_reports() {
+    let (addr, played, _) = start_with(Limits {
+      playback_burst: 3,
+      playback_per_sec: 1,
+      ..Limits::default()
+    })
+    .await;
+    for _ in 0..3 {
+      assert!(send(addr, &post("/playback", "{}")).await.starts_with("HTTP/1.1 204"));
+    }
+    let res = send(addr, &post("/playback", "{}")).await;
+    assert!(res.starts_with("HTTP/1.1 429"));
+    assert!(res.contains("retry-after: 1"));
+    assert_eq!(played.lock().unwrap().len(), 3);
+    // Other routes aren't limited.
+    assert!(send(addr, &post("/settings", r#"{"threshold":0.75}"#)).await.starts_with("HTTP/1.1 200"));
+  }
+
+  #[test]
+  fn refills_rate_limiter() {
+    let start = Instant::now();
+    let mut limiter = RateLimiter::new(2, 4, start);
+    assert!(limiter.try_take(start));
+    assert!(limiter.try_take(start));
+    assert!(!limiter.try_take(start));
+    assert!(limiter.try_take(start + Duration::from_millis(250)));
+    assert!(!limiter.try_take(start + Duration::from_millis(260)));
+    // Refills stop at the burst size.
+    let later = start + Duration::from_secs(60);
+    assert!(limiter.try_take(later));
+    assert!(limiter.try_take(later));
+    assert!(!limiter.try_take(later));
+  }
+
+  #[test]
+  fn routes_per_api() {
+    assert_eq!(route(Api::Overlay, &Method::POST, "/playback").ok(), Some(Route::Playback));
+    assert!(route(Api::Overlay, &Method::GET, "/now-playing").is_err());
+    assert!(route(Api::Overlay, &Method::POST, "/control/play").is_err());
+    assert_eq!(route(Api::Control, &Method::GET, "/config").ok(), Some(Route::Config));
+    assert_eq!(route(Api::Control, &Method::GET, "/status").ok(), Some(Route::Status));
+    assert!(route(Api::Overlay, &Method::GET, "/status").is_err());
+    assert_eq!(
+      route(Api::Control, &Method::POST, "/control/toggle").ok(),
+      Some(Route::Control(Control::Toggle))
+    );
+    assert_eq!(route(Api::Control, &Method::POST, "/control/").map(|_| ()).unwrap_err().status, StatusCode::NOT_FOUND);
+  }
+
+  #[test]
+  fn matches_soundcloud_origins_only() {
+    assert!(origin_allowed("https://soundcloud.com"));
+    assert!(origin_allowed("https://secure.soundcloud.com"));
+    assert!(!origin_allowed("http://soundcloud.com"));
+    assert!(!origin_allowed("https://notsoundcloud.com"));
+    assert!(!origin_allowed("https://soundcloud.com.evil.example"));
+    assert!(!origin_allowed("null"));
+    assert!(token_matches(b"abc", b"abc"));
+    assert!(!token_matches(b"abd", b"abc"));
+    assert!(!token_matches(b"ab", b"abc"));
+  }
+
+  #[tokio::test]
+  async fn enforces_body_limit() {
+    let (addr, played, _) = start().await;
+
+    let declared = format!(
+      "POST /playback HTTP/1.1\r\nHost: x\r\nX-MSCD-Token: 0123456789abcdef\r\nConnection: close\r\nContent-Length: {}\r\n\r\n",
+      MAX_BODY_BYTES + 1
+    );
+    assert!(send(addr, &declared).await.starts_with("HTTP/1.1 413"));
+
+    let chunk = "x".repeat(16 * 1024);
+    let mut chunked = String::from("POST /playback HTTP/1.1\r\nHost: x\r\nX-MSCD-Token: 0123456789abcdef\r\nConnection: close\r\nTransfer-Encoding: chunked\r\n\r\n");
+    for _ in 0..5 {
+      chunked.push_str(&format!("{:x}\r\n{}\r\n", chunk.len(), chunk));
+    }
+    chunked.push_str("0\r\n\r\n");
+    assert!(send(addr, &chunked).await.starts_with("HTTP/1.1 413"));
+    assert!(played.lock().unwrap().is_empty());
+  }
+
+  #[tokio::test]
+  async fn supports_chunked_bodies_and_keep_alive() {
+    let (addr, played, _) = start().await;
+    let raw = concat!(
+      "POST /playback HTTP/1.1\r\nHost: x\r\nX-MSCD-Token: 0123456789abcdef\r\nTransfer-Encoding: chunked\r\n\r\n",
+      "4\r\nab\r\n\r\n2\r\ncd\r\n0\r\n\r\n",
+      "GET /settings HTTP/1.1\r\nHost: x\r\nX-MSCD-Token: 0123456789abcdef\r\nConnection: close\r\n\r\n",
+    );
+    let res = send(addr, raw).await;
+    assert_eq!(res.matches("HTTP/1.1 ").count(), 2);
+    assert!(res.starts_with("HTTP/1.1 204"));
+    assert_eq!(played.lock().unwrap().as_slice(), ["ab\r\ncd"]);
+  }
+
+  #[tokio::test]
+  async fn streams_backlog_then_published_events() {
+    let (addr, _, hub) = start().await;
+    assert!(!hub.publish(ServerEvent::new("toast", &"nobody listening")));
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+      .write_all(b"GET /events HTTP/1.1\r\nHost: x\r\nX-MSCD-Token: 0123456789abcdef\r\n\r\n")
+      .await
+      .unwrap();
+    let mut out = String::new();
+    read_until(&mut stream, &mut out, "data: {\"threshold\":0.5}\n\n").await;
+    assert!(out.starts_with("HTTP/1.1 200"));
+    assert!(out.contains("content-type: text/event-stream"));
+    assert!(out.contains("event: settings\n"));
+
+    assert!(hub.publish(ServerEvent::new("toast", &serde_json::json!({ "title": "a" }))));
+    read_until(&mut stream, &mut out, "event: toast\ndata: {\"title\":\"a\"}\n\n").await;
+  }
+
+  #[tokio::test]
+  async fn streams_outlive_idle_timeout() {
+    let (addr, _, hub) = start_with(tight()).await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+      .write_all(b"GET /events HTTP/1.1\r\nHost: x\r\nX-MSCD-Token: 0123456789abcdef\r\n\r\n")
+      .await
+      .unwrap();
+    let mut out = String::new();
+    read_until(&mut stream, &mut out, "event: settings\n").await;
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    assert!(hub.publish(ServerEvent::new("toast", &serde_json::json!({ "title": "late" }))));
+    read_until(&mut stream, &mut out, "\"late\"").await;
+  }
+}
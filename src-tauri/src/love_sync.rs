@@ -0,0 +1,159 @@
+// Diff between SoundCloud likes (read by the overlay from api-v2 `/me/track_likes`)
+// and Last.fm loved tracks. SoundCloud metadata is messier than Last.fm's, so both
+// sides are reduced to normalized artist/title keys before comparing.
+
+use std::collections::HashSet;
+
+use crate::backfill::normalize;
+use crate::lastfm::LovedTrack;
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LikedTrack {
+  pub track_id: String,
+  pub title: String,
+  pub artist: String,
+  // SoundCloud's like `created_at`, unix milliseconds.
+  #[serde(default)]
+  pub liked_at: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct LoveDiff {
+  // Liked on SoundCloud, not loved on Last.fm, oldest like first.
+  pub to_love: Vec<LikedTrack>,
+  // Loved on Last.fm with no matching SoundCloud like.
+  pub missing_on_soundcloud: Vec<LovedTrack>,
+  pub already_loved: usize,
+}
+
+// Drops bracketed promo noise ("[Free Download]", "[OUT NOW]") but keeps
+// parentheses, which usually carry the mix or remix name.
+fn strip_brackets(title: &str) -> String {
+  let mut out = String::with_capacity(title.len());
+  let mut depth = 0u32;
+  for c in title.chars() {
+    match c {
+      '[' => depth += 1,
+      ']' if depth > 0 => depth -= 1,
+      _ if depth == 0 => out.push(c),
+      _ => {}
+    }
+  }
+  out
+}
+
+fn key(artist: &str, title: &str) -> String {
+  format!("{}\u{1f}{}", normalize(artist), normalize(&strip_brackets(title)))
+}
+
+// Splits "Artist - Title" uploads, common on label and repost channels.
+fn split_title(title: &str) -> Option<(&str, &str)> {
+  [" - ", " – ", " — "].iter().find_map(|sep| {
+    let (left, right) = title.split_once(sep)?;
+    let (left, right) = (left.trim(), right.trim());
+    if left.is_empty() || right.is_empty() {
+      None
+    } else {
+      Some((left, right))
+    }
+  })
+}
+
+/// Keys a SoundCloud like may match on Last.fm: as uploaded, and with an
+/// "Artist - Title" title split into its parts.
+pub fn like_keys(like: &LikedTrack) -> Vec<String> {
+  let mut keys = vec![key(&like.artist, &like.title)];
+  if let Some((artist, title)) = split_title(&like.title) {
+    keys.push(key(artist, title));
+  }
+  keys
+}
+
+/// Artist and title to send with `track.love`: "Artist - Title" uploads are split,
+/// anything else is sent as uploaded.
+pub fn love_metadata(like: &LikedTrack) -> (String, String) {
+  match split_title(&like.title) {
+    Some((artist, title)) => (artist.to_string(), strip_brackets(title).trim().to_string()),
+    None => (like.artist.trim().to_string(), like.title.trim().to_string()),
+  }
+}
+
+pub fn diff(likes: &[LikedTrack], loved: &[LovedTrack]) -> LoveDiff {
+  let loved_keys: HashSet<String> = loved.iter().map(|t| key(&t.artist, &t.title)).collect();
+  let liked_keys: HashSet<String> = likes.iter().flat_map(like_keys).collect();
+
+  let mut out = LoveDiff::default();
+  let mut seen = HashSet::new();
+  let mut likes: Vec<&LikedTrack> = likes
+    .iter()
+    .filter(|l| !l.title.trim().is_empty() && !l.artist.trim().is_empty())
+    .collect();
+  likes.sort_by_key(|l| l.liked_at);
+  for like in likes {
+    if !seen.insert(like.track_id.as_str()) {
+      continue;
+    }
+    if like_keys(like).iter().any(|k| loved_keys.contains(k)) {
+      out.already_loved += 1;
+    } else {
+      out.to_love.push(like.clone());
+    }
+  }
+  out.missing_on_soundcloud = loved
+    .iter()
+    .filter(|t| !liked_keys.contains(&key(&t.artist, &t.title)))
+    .cloned()
+    .collect();
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn like(id: &str, artist: &str, title: &str, liked_at: u64) -> LikedTrack {
+    LikedTrack {
+      track_id: id.to_string(),
+      title: title.to_string(),
+      artist: artist.to_string(),
+      liked_at,
+    }
+  }
+
+  fn loved(artist: &str, title: &str) -> LovedTrack {
+    LovedTrack {
+      artist: artist.to_string(),
+      title: title.to_string(),
+      loved_at: None,
+    }
+  }
+
+  #[test]
+  fn matches_after_normalization_and_title_split() {
+    let likes = vec![
+      like("/1", "Ninja Tune", "Bonobo - Kerala [Free Download]", 3),
+      like("/2", "BICEP", "Glue!", 2),
+      like("/3", "Overmono", "So U Kno", 1),
+      like("/3", "Overmono", "So U Kno", 1),
+    ];
+    let loves = vec![loved("Bonobo", "Kerala"), loved("Bicep", "Glue"), loved("Four Tet", "Baby")];
+    let out = diff(&likes, &loves);
+    assert_eq!(out.already_loved, 2);
+    assert_eq!(out.to_love.len(), 1);
+    assert_eq!(out.to_love[0].track_id, "/3");
+    assert_eq!(out.missing_on_soundcloud, vec![loved("Four Tet", "Baby")]);
+  }
+
+  #[test]
+  fn love_metadata_splits_artist_title_uploads() {
+    assert_eq!(
+      love_metadata(&like("/1", "Ninja Tune", "Bonobo - Kerala [OUT NOW]", 0)),
+      ("Bonobo".to_string(), "Kerala".to_string())
+    );
+    assert_eq!(
+      love_metadata(&like("/2", "Bicep", " Atlas ", 0)),
+      ("Bicep".to_string(), "Atlas".to_string())
+    );
+  }
+}
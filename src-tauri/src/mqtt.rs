@@ -0,0 +1,764 @@
+// Optional MQTT publisher for home automation: retained now-playing state (one JSON
+// topic or separate state/artist/title/album topics), an availability topic backed
+// by the broker's last will, and a command topic for player actions.
+//
+// This is a minimal MQTT 3.1.1 client. Everything goes out at QoS 0, which is all
+// retained state and fire-and-forget commands need, so there are no acks to track.
+
+use std::io;
+use std::time::{Duration, Instant};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, watch};
+
+use crate::local_server::Control;
+
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+// Commands and acks are tiny; this only guards against a broken broker.
+const MAX_PACKET_BYTES: usize = 256 * 1024;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MqttFormat {
+  /// One retained JSON document on `<prefix>/now_playing`.
+  #[default]
+  Json,
+  /// Plain strings on `<prefix>/state`, `/artist`, `/title` and `/album`.
+  Topics,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct MqttConfig {
+  pub enabled: bool,
+  // mqtt://host[:1883] or mqtts://host[:8883]
+  pub broker_url: String,
+  pub username: Option<String>,
+  pub password: Option<String>,
+  pub topic_prefix: String,
+  pub format: MqttFormat,
+}
+
+impl Default for MqttConfig {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      broker_url: "mqtt://127.0.0.1:1883".to_string(),
+      username: None,
+      password: None,
+      topic_prefix: "mscd".to_string(),
+      format: MqttFormat::Json,
+    }
+  }
+}
+
+impl MqttConfig {
+  pub fn topic(&self, leaf: &str) -> String {
+    format!("{}/{}", self.topic_prefix.trim().trim_end_matches('/'), leaf)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Broker {
+  pub tls: bool,
+  pub host: String,
+  pub port: u16,
+}
+
+pub fn parse_broker(raw: &str) -> Result<Broker, String> {
+  let url = url::Url::parse(raw.trim()).map_err(|e| format!("Invalid broker URL: {}", e))?;
+  let tls = match url.scheme() {
+    "mqtt" | "tcp" => false,
+    "mqtts" | "ssl" => true,
+    other => return Err(format!("Unsupported broker scheme {}", other)),
+  };
+  let host = url
+    .host_str()
+    .filter(|h| !h.is_empty())
+    .ok_or("Invalid broker URL: missing host")?
+    .trim_start_matches('[')
+    .trim_end_matches(']')
+    .to_string();
+  Ok(Broker {
+    tls,
+    host,
+    port: url.port().unwrap_or(if tls { 8883 } else { 1883 }),
+  })
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlayerState {
+  Playing,
+  Paused,
+  #[default]
+  Idle,
+}
+
+impl PlayerState {
+  fn as_str(self) -> &'static str {
+    match self {
+      Self::Playing => "playing",
+      Self::Paused => "paused",
+      Self::Idle => "idle",
+    }
+  }
+}
+
+/// Published state. Position is left out so it only changes with the track or the
+/// play state, not every report.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct NowState {
+  pub state: PlayerState,
+  pub title: Option<String>,
+  pub artist: Option<String>,
+  pub album: Option<String>,
+  pub artwork_url: Option<String>,
+  pub duration_ms: u64,
+}
+
+/// Retained (topic, payload) pairs for a state.
+pub fn state_messages(cfg: &MqttConfig, state: &NowState) -> Vec<(String, Vec<u8>)> {
+  match cfg.format {
+    MqttFormat::Json => vec![(
+      cfg.topic("now_playing"),
+      serde_json::to_vec(state).unwrap_or_default(),
+    )],
+    MqttFormat::Topics => {
+      let text = |v: &Option<String>| v.clone().unwrap_or_default().into_bytes();
+      vec![
+        (cfg.topic("state"), state.state.as_str().as_bytes().to_vec()),
+        (cfg.topic("artist"), text(&state.artist)),
+        (cfg.topic("title"), text(&state.title)),
+        (cfg.topic("album"), text(&state.album)),
+      ]
+    }
+  }
+}
+
+/// Command topic payloads: play, pause, toggle, next, prev or like. Seeking needs a
+/// position and is only offered by the control API.
+pub fn parse_command(payload: &[u8]) -> Option<Control> {
+  let text = std::str::from_utf8(payload).ok()?.trim().to_ascii_lowercase();
+  Control::parse(&text).filter(|c| *c != Control::Seek)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Packet {
+  Connect {
+    client_id: String,
+    username: Option<String>,
+    password: Option<String>,
+    keep_alive: u16,
+    // Topic and payload the broker publishes (retained) if we vanish.
+    will: Option<(String, Vec<u8>)>,
+  },
+  ConnAck {
+    code: u8,
+  },
+  Publish {
+    topic: String,
+    payload: Vec<u8>,
+    retain: bool,
+  },
+  Subscribe {
+    id: u16,
+    topic: String,
+  },
+  SubAck {
+    id: u16,
+  },
+  PingReq,
+  PingResp,
+  Disconnect,
+}
+
+fn put_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+  out.extend_from_slice(&(bytes.len() as u16).to_be_bytes());
+  out.extend_from_slice(bytes);
+}
+
+fn put_len(out: &mut Vec<u8>, mut len: usize) {
+  loop {
+    let mut byte = (len % 128) as u8;
+    len /= 128;
+    if len > 0 {
+      byte |= 0x80;
+    }
+    out.push(byte);
+    if len == 0 {
+      return;
+    }
+  }
+}
+
+impl Packet {
+  pub fn encode(&self) -> Vec<u8> {
+    let mut body = Vec::new();
+    let header = match self {
+      Self::Connect {
+        client_id,
+        username,
+        password,
+        keep_alive,
+        will,
+      } => {
+        put_bytes(&mut body, b"MQTT");
+        body.push(4);
+        // Clean session; a will is always retained, at QoS 0.
+        let mut flags = 0x02;
+        if will.is_some() {
+          flags |= 0x04 | 0x20;
+        }
+        if username.is_some() {
+          flags |= 0x80;
+        }
+        if password.is_some() {
+          flags |= 0x40;
+        }
+        body.push(flags);
+        body.extend_from_slice(&keep_alive.to_be_bytes());
+        put_bytes(&mut body, client_id.as_bytes());
+        if let Some((topic, payload)) = will {
+          put_bytes(&mut body, topic.as_bytes());
+          put_bytes(&mut body, payload);
+        }
+        if let Some(username) = username {
+          put_bytes(&mut body, username.as_bytes());
+        }
+        if let Some(password) = password {
+          put_bytes(&mut body, password.as_bytes());
+        }
+        0x10
+      }
+      Self::ConnAck { code } => {
+        body.extend_from_slice(&[0, *code]);
+        0x20
+      }
+      Self::Publish { topic, payload, retain } => {
+        put_bytes(&mut body, topic.as_bytes());
+        body.extend_from_slice(payload);
+        0x30 | u8::from(*retain)
+      }
+      Self::Subscribe { id, topic } => {
+        body.extend_from_slice(&id.to_be_bytes());
+        put_bytes(&mut body, topic.as_bytes());
+        body.push(0);
+        0x82
+      }
+      Self::SubAck { id } => {
+        body.extend_from_slice(&id.to_be_bytes());
+        body.push(0);
+        0x90
+      }
+      Self::PingReq => 0xc0,
+      Self::PingResp => 0xd0,
+      Self::Disconnect => 0xe0,
+    };
+    let mut out = vec![header];
+    put_len(&mut out, body.len());
+    out.extend_from_slice(&body);
+    out
+  }
+}
+
+fn malformed(what: &str) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, format!("malformed MQTT packet: {}", what))
+}
+
+struct Reader<'a> {
+  buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+  fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+    if self.buf.len() < n {
+      return Err(malformed("truncated"));
+    }
+    let (head, rest) = self.buf.split_at(n);
+    self.buf = rest;
+    Ok(head)
+  }
+
+  fn u8(&mut self) -> io::Result<u8> {
+    Ok(self.take(1)?[0])
+  }
+
+  fn u16(&mut self) -> io::Result<u16> {
+    let b = self.take(2)?;
+    Ok(u16::from_be_bytes([b[0], b[1]]))
+  }
+
+  fn bytes(&mut self) -> io::Result<Vec<u8>> {
+    let len = self.u16()? as usize;
+    Ok(self.take(len)?.to_vec())
+  }
+
+  fn string(&mut self) -> io::Result<String> {
+    String::from_utf8(self.bytes()?).map_err(|_| malformed("invalid UTF-8"))
+  }
+}
+
+fn decode(header: u8, body: &[u8]) -> io::Result<Packet> {
+  let mut r = Reader { buf: body };
+  Ok(match header >> 4 {
+    1 => {
+      if r.string()? != "MQTT" {
+        return Err(malformed("unknown protocol"));
+      }
+      r.u8()?;
+      let flags = r.u8()?;
+      let keep_alive = r.u16()?;
+      let client_id = r.string()?;
+      let will = if flags & 0x04 != 0 {
+        Some((r.string()?, r.bytes()?))
+      } else {
+        None
+      };
+      let username = if flags & 0x80 != 0 { Some(r.string()?) } else { None };
+      let password = if flags & 0x40 != 0 { Some(r.string()?) } else { None };
+      Packet::Connect {
+        client_id,
+        username,
+        password,
+        keep_alive,
+        will,
+      }
+    }
+    2 => {
+      r.u8()?;
+      Packet::ConnAck { code: r.u8()? }
+    }
+    3 => {
+      let topic = r.string()?;
+      // QoS 1/2 deliveries carry a packet id; we subscribe at QoS 0, so skip it.
+      if (header >> 1) & 0x03 != 0 {
+        r.u16()?;
+      }
+      Packet::Publish {
+        topic,
+        payload: r.buf.to_vec(),
+        retain: header & 0x01 != 0,
+      }
+    }
+    8 => {
+      let id = r.u16()?;
+      Packet::Subscribe { id, topic: r.string()? }
+    }
+    9 => Packet::SubAck { id: r.u16()? },
+    12 => Packet::PingReq,
+    13 => Packet::PingResp,
+    14 => Packet::Disconnect,
+    other => return Err(malformed(&format!("unsupported type {}", other))),
+  })
+}
+
+pub async fn read_packet<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<Packet> {
+  let header = reader.read_u8().await?;
+  let mut len = 0usize;
+  for shift in [0, 7, 14, 21] {
+    let byte = reader.read_u8().await?;
+    len |= ((byte & 0x7f) as usize) << shift;
+    if byte & 0x80 == 0 {
+      if len > MAX_PACKET_BYTES {
+        return Err(malformed("too large"));
+      }
+      let mut body = vec![0; len];
+      reader.read_exact(&mut body).await?;
+      return decode(header, &body);
+    }
+  }
+  Err(malformed("remaining length"))
+}
+
+trait Io: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Io for T {}
+
+async fn connect(broker: &Broker) -> Result<Box<dyn Io>, String> {
+  let tcp = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect((broker.host.as_str(), broker.port)))
+    .await
+    .map_err(|_| "connection timed out".to_string())?
+    .map_err(|e| e.to_string())?;
+  if !broker.tls {
+    return Ok(Box::new(tcp));
+  }
+  let connector = tokio_native_tls::native_tls::TlsConnector::new().map_err(|e| e.to_string())?;
+  let tls = tokio_native_tls::TlsConnector::from(connector)
+    .connect(&broker.host, tcp)
+    .await
+    .map_err(|e| e.to_string())?;
+  Ok(Box::new(tls))
+}
+
+fn connack_error(code: u8) -> String {
+  match code {
+    1 => "broker refused the protocol version".to_string(),
+    2 => "broker rejected the client id".to_string(),
+    3 => "broker unavailable".to_string(),
+    4 => "bad username or password".to_string(),
+    5 => "not authorized".to_string(),
+    other => format!("broker refused the connection ({})", other),
+  }
+}
+
+async fn send<W: AsyncWrite + Unpin>(writer: &mut W, packet: Packet) -> Result<(), String> {
+  writer.write_all(&packet.encode()).await.map_err(|e| e.to_string())
+}
+
+async fn publish_state<W: AsyncWrite + Unpin>(writer: &mut W, cfg: &MqttConfig, state: &NowState) -> Result<(), String> {
+  for (topic, payload) in state_messages(cfg, state) {
+    send(writer, Packet::Publish { topic, payload, retain: true }).await?;
+  }
+  Ok(())
+}
+
+// One broker connection, until it fails (Err) or `states` closes (Ok).
+async fn session<F: Fn(Control)>(
+  cfg: &MqttConfig,
+  client_id: &str,
+  states: &mut watch::Receiver<NowState>,
+  on_command: &F,
+  connected: &mut bool,
+) -> Result<(), String> {
+  let broker = parse_broker(&cfg.broker_url)?;
+  let (mut reader, mut writer) = tokio::io::split(connect(&broker).await?);
+  let availability = cfg.topic("availability");
+  let commands = cfg.topic("command");
+  send(
+    &mut writer,
+    Packet::Connect {
+      client_id: client_id.to_string(),
+      username: cfg.username.clone().filter(|u| !u.is_empty()),
+      password: cfg.password.clone().filter(|p| !p.is_empty()),
+      keep_alive: KEEP_ALIVE.as_secs() as u16,
+      will: Some((availability.clone(), b"offline".to_vec())),
+    },
+  )
+  .await?;
+  match tokio::time::timeout(CONNECT_TIMEOUT, read_packet(&mut reader)).await {
+    Ok(Ok(Packet::ConnAck { code: 0 })) => {}
+    Ok(Ok(Packet::ConnAck { code })) => return Err(connack_error(code)),
+    Ok(Ok(other)) => return Err(format!("expected CONNACK, got {:?}", other)),
+    Ok(Err(err)) => return Err(err.to_string()),
+    Err(_) => return Err("no CONNACK from broker".to_string()),
+  }
+  *connected = true;
+  log::info!("[MQTT] Connected to {}:{}", broker.host, broker.port);
+
+  send(&mut writer, Packet::Subscribe { id: 1, topic: commands.clone() }).await?;
+  send(
+    &mut writer,
+    Packet::Publish {
+      topic: availability.clone(),
+      payload: b"online".to_vec(),
+      retain: true,
+    },
+  )
+  .await?;
+  let current = states.borrow_and_update().clone();
+  publish_state(&mut writer, cfg, &current).await?;
+
+  // Reads run on their own task so a half-read packet is never dropped by select!.
+  let (tx, mut incoming) = mpsc::channel(16);
+  let read_task = tokio::spawn(async move {
+    loop {
+      let packet = read_packet(&mut reader).await;
+      let failed = packet.is_err();
+      if tx.send(packet).await.is_err() || failed {
+        return;
+      }
+    }
+  });
+
+  let mut ping = tokio::time::interval(KEEP_ALIVE / 2);
+  ping.tick().await;
+  let mut last_seen = Instant::now();
+  let result = loop {
+    tokio::select! {
+      packet = incoming.recv() => {
+        last_seen = Instant::now();
+        match packet {
+          Some(Ok(Packet::Publish { topic, payload, .. })) if topic == commands => match parse_command(&payload) {
+            Some(action) => on_command(action),
+            None => log::warn!("[MQTT] Ignored command {:?}", String::from_utf8_lossy(&payload)),
+          },
+          Some(Ok(_)) => {}
+          Some(Err(err)) => break Err(err.to_string()),
+          None => break Err("connection closed".to_string()),
+        }
+      }
+      changed = states.changed() => {
+        if changed.is_err() {
+          // Shutting down: say so ourselves, since a clean disconnect skips the will.
+          let _ = send(&mut writer, Packet::Publish { topic: availability.clone(), payload: b"offline".to_vec(), retain: true }).await;
+          let _ = send(&mut writer, Packet::Disconnect).await;
+          break Ok(());
+        }
+        let state = states.borrow_and_update().clone();
+        if let Err(err) = publish_state(&mut writer, cfg, &state).await {
+          break Err(err);
+        }
+      }
+      _ = ping.tick() => {
+        if last_seen.elapsed() > KEEP_ALIVE * 3 / 2 {
+          break Err("broker stopped responding".to_string());
+        }
+        if let Err(err) = send(&mut writer, Packet::PingReq).await {
+          break Err(err);
+        }
+      }
+    }
+  };
+  read_task.abort();
+  result
+}
+
+/// Keeps a broker connection up, reconnecting with backoff, and publishes every
+/// state sent on `states`. Returns once the sender is dropped, after publishing
+/// "offline" and disconnecting if it was connected.
+pub async fn run<F: Fn(Control)>(
+  cfg: MqttConfig,
+  client_id: String,
+  mut states: watch::Receiver<NowState>,
+  on_command: F,
+) {
+  let mut backoff = Duration::from_secs(1);
+  loop {
+    let mut connected = false;
+    match session(&cfg, &client_id, &mut states, &on_command, &mut connected).await {
+      Ok(()) => return,
+      Err(err) => log::warn!("[MQTT] {}; reconnecting in {:?}", err, backoff),
+    }
+    if connected {
+      backoff = Duration::from_secs(1);
+    }
+    let wait = tokio::time::sleep(backoff);
+    tokio::pin!(wait);
+    loop {
+      tokio::select! {
+        _ = &mut wait => break,
+        changed = states.changed() => if changed.is_err() {
+          return;
+        },
+      }
+    }
+    backoff = (backoff * 2).min(MAX_BACKOFF);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::{Arc, Mutex};
+  use tokio::net::TcpListener;
+
+  fn playing() -> NowState {
+    NowState {
+      state: PlayerState::Playing,
+      title: Some("Glue".to_string()),
+      artist: Some("Bicep".to_string()),
+      duration_ms: 269_000,
+      ..NowState::default()
+    }
+  }
+
+  #[tokio::test]
+  async fn round_trips_packets() {
+    let packets = [
+      Packet::Connect {
+        client_id: "mscd-1".to_string(),
+        username: Some("user".to_string()),
+        password: Some("pass".to_string()),
+        keep_alive: 30,
+        will: Some(("mscd/availability".to_string(), b"offline".to_vec())),
+      },
+      Packet::ConnAck { code: 5 },
+      Packet::Publish {
+        topic: "mscd/title".to_string(),
+        payload: "x".repeat(300).into_bytes(),
+        retain: true,
+      },
+      Packet::Subscribe {
+        id: 1,
+        topic: "mscd/command".to_string(),
+      },
+      Packet::SubAck { id: 1 },
+      Packet::PingReq,
+      Packet::PingResp,
+      Packet::Disconnect,
+    ];
+    for packet in packets {
+      let bytes = packet.encode();
+      let decoded = read_packet(&mut bytes.as_slice()).await.unwrap();
+      assert_eq!(decoded, packet);
+    }
+    // 303 bytes of body (topic length, topic, payload) need two length bytes.
+    let long = Packet::Publish {
+      topic: "t".to_string(),
+      payload: vec![0; 300],
+      retain: false,
+    }
+    .encode();
+    assert_eq!(&long[..3], &[0x30, 0xaf, 0x02]);
+    assert_eq!(Packet::PingReq.encode(), [0xc0, 0x00]);
+  }
+
+  #[test]
+  fn parses_brokers_and_commands() {
+    assert_eq!(
+      parse_broker("mqtt://homeassistant.local"),
+      Ok(Broker {
+        tls: false,
+        host: "homeassistant.local".to_string(),
+        port: 1883
+      })
+    );
+    assert_eq!(parse_broker("mqtts://10.0.0.2:8884").map(|b| (b.tls, b.port)), Ok((true, 8884)));
+    assert!(parse_broker("http://broker").is_err());
+    assert!(parse_broker("broker:1883").is_err());
+    assert_eq!(parse_command(b" Next\n"), Some(Control::Next));
+    assert_eq!(parse_command(b"pause"), Some(Control::Pause));
+    assert_eq!(parse_command(b"seek"), None);
+    assert_eq!(parse_command(b"rewind"), None);
+  }
+
+  #[test]
+  fn formats_state_messages() {
+    let json = MqttConfig {
+      topic_prefix: "home/mscd/".to_string(),
+      ..MqttConfig::default()
+    };
+    let messages = state_messages(&json, &playing());
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].0, "home/mscd/now_playing");
+    let body: serde_json::Value = serde_json::from_slice(&messages[0].1).unwrap();
+    assert_eq!(body["state"], "playing");
+    assert_eq!(body["artist"], "Bicep");
+
+    let topics = MqttConfig {
+      format: MqttFormat::Topics,
+      ..MqttConfig::default()
+    };
+    let messages = state_messages(&topics, &NowState::default());
+    assert_eq!(
+      messages,
+      vec![
+        ("mscd/state".to_string(), b"idle".to_vec()),
+        ("mscd/artist".to_string(), Vec::new()),
+        ("mscd/title".to_string(), Vec::new()),
+        ("mscd/album".to_string(), Vec::new()),
+      ]
+    );
+  }
+
+  #[tokio::test]
+  async fn stops_while_waiting_to_reconnect() {
+    // Nothing listens on a port we just released, so every attempt fails fast.
+    let port = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().port();
+    let cfg = MqttConfig {
+      enabled: true,
+      broker_url: format!("mqtt://127.0.0.1:{}", port),
+      ..MqttConfig::default()
+    };
+    let (state_tx, state_rx) = watch::channel(NowState::default());
+    let client = tokio::spawn(run(cfg, "mscd-test".to_string(), state_rx, |_| {}));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    drop(state_tx);
+    tokio::time::timeout(Duration::from_millis(500), client).await.unwrap().unwrap();
+  }
+
+  #[tokio::test]
+  async fn publishes_state_and_receives_commands() {
+    // Broker stand-in: accepts one client and forwards what it publishes.
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let cfg = MqttConfig {
+      enabled: true,
+      broker_url: format!("mqtt://{}", listener.local_addr().unwrap()),
+      username: Some("ha".to_string()),
+      password: Some("secret".to_string()),
+      format: MqttFormat::Topics,
+      ..MqttConfig::default()
+    };
+    let (state_tx, state_rx) = watch::channel(playing());
+    let commands = Arc::new(Mutex::new(Vec::new()));
+    let seen = commands.clone();
+    let client = tokio::spawn(run(cfg, "mscd-test".to_string(), state_rx, move |c| {
+      seen.lock().unwrap().push(c)
+    }));
+
+    let (mut broker, _) = listener.accept().await.unwrap();
+    match read_packet(&mut broker).await.unwrap() {
+      Packet::Connect {
+        client_id,
+        username,
+        password,
+        will,
+        ..
+      } => {
+        assert_eq!(client_id, "mscd-test");
+        assert_eq!(username.as_deref(), Some("ha"));
+        assert_eq!(password.as_deref(), Some("secret"));
+        assert_eq!(will, Some(("mscd/availability".to_string(), b"offline".to_vec())));
+      }
+      other => panic!("expected CONNECT, got {:?}", other),
+    }
+    broker.write_all(&Packet::ConnAck { code: 0 }.encode()).await.unwrap();
+    assert_eq!(
+      read_packet(&mut broker).await.unwrap(),
+      Packet::Subscribe {
+        id: 1,
+        topic: "mscd/command".to_string()
+      }
+    );
+    broker.write_all(&Packet::SubAck { id: 1 }.encode()).await.unwrap();
+
+    let mut published = Vec::new();
+    for _ in 0..5 {
+      match read_packet(&mut broker).await.unwrap() {
+        Packet::Publish { topic, payload, retain } => {
+          assert!(retain);
+          published.push((topic, String::from_utf8(payload).unwrap()));
+        }
+        other => panic!("expected PUBLISH, got {:?}", other),
+      }
+    }
+    assert_eq!(published[0], ("mscd/availability".to_string(), "online".to_string()));
+    assert!(published.contains(&("mscd/state".to_string(), "playing".to_string())));
+    assert!(published.contains(&("mscd/title".to_string(), "Glue".to_string())));
+
+    let command = Packet::Publish {
+      topic: "mscd/command".to_string(),
+      payload: b"next".to_vec(),
+      retain: false,
+    };
+    broker.write_all(&command.encode()).await.unwrap();
+
+    state_tx.send(NowState::default()).unwrap();
+    assert_eq!(
+      read_packet(&mut broker).await.unwrap(),
+      Packet::Publish {
+        topic: "mscd/state".to_string(),
+        payload: b"idle".to_vec(),
+        retain: true
+      }
+    );
+    assert_eq!(commands.lock().unwrap().as_slice(), [Control::Next]);
+
+    // Dropping the sender shuts the client down cleanly.
+    drop(state_tx);
+    let mut rest = Vec::new();
+    while let Ok(packet) = tokio::time::timeout(Duration::from_secs(5), read_packet(&mut broker)).await.unwrap() {
+      rest.push(packet);
+    }
+    assert_eq!(rest.last(), Some(&Packet::Disconnect));
+    assert!(rest.contains(&Packet::Publish {
+      topic: "mscd/availability".to_string(),
+      payload: b"offline".to_vec(),
+      retain: true
+    }));
+    client.await.unwrap();
+  }
+}
@@ -0,0 +1,240 @@
+// "Now playing" outputs for stream overlays: a text file rendered from a user
+// template, and a JSON feed plus a self-refreshing HTML widget served by the
+// control API (`/widget.json`, `/widget`).
+
+use std::fs;
+use std::path::Path;
+
+pub const DEFAULT_TEMPLATE: &str = "{artist} – {title}";
+pub const DEFAULT_IDLE_TEXT: &str = "Nothing playing";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WidgetTheme {
+  #[default]
+  Dark,
+  Light,
+  // No background, for layering over a scene.
+  Transparent,
+}
+
+/// What the overlay last reported, whether or not it is being scrobbled.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Playing {
+  pub track_id: String,
+  pub title: String,
+  pub artist: String,
+  pub album: Option<String>,
+  pub artwork_url: Option<String>,
+  pub duration_ms: u64,
+  pub position_ms: u64,
+  pub paused: bool,
+}
+
+fn clock(ms: u64) -> String {
+  let secs = ms / 1000;
+  if secs >= 3600 {
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+  } else {
+    format!("{}:{:02}", secs / 60, secs % 60)
+  }
+}
+
+/// Fills `{artist}`, `{title}`, `{album}` and `{duration}`; other text is kept as is.
+pub fn render(template: &str, playing: &Playing) -> String {
+  template
+    .replace("{artist}", &playing.artist)
+    .replace("{title}", &playing.title)
+    .replace("{album}", playing.album.as_deref().unwrap_or(""))
+    .replace("{duration}", &clock(playing.duration_ms))
+}
+
+/// The text file's content: the rendered template, or the idle text when nothing
+/// plays (`active` is None for paused, stopped or closed players).
+pub fn text(active: Option<&Playing>, template: &str, idle_text: &str) -> String {
+  match active {
+    Some(playing) => render(template, playing),
+    None => idle_text.to_string(),
+  }
+}
+
+/// Checks a text file name from settings. Files are only written to the app's own
+/// now-playing folder, so settings name a plain `.txt` file rather than a path.
+pub fn file_name(raw: &str) -> Result<String, String> {
+  let name = raw.trim();
+  let valid = name.len() > 4
+    && name.len() <= 64
+    && name.ends_with(".txt")
+    && !name.starts_with('.')
+    && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ' '));
+  if valid {
+    Ok(name.to_string())
+  } else {
+    Err(format!("{:?} is not a plain .txt file name", name))
+  }
+}
+
+/// Replaces the file in one step so readers like OBS never see it half written.
+pub fn write_text(path: &Path, text: &str) -> Result<(), String> {
+  let mut tmp = path.as_os_str().to_owned();
+  tmp.push(".tmp");
+  fs::write(&tmp, text).map_err(|e| e.to_string())?;
+  fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct Feed {
+  pub playing: bool,
+  pub text: String,
+  pub title: Option<String>,
+  pub artist: Option<String>,
+  pub album: Option<String>,
+  pub artwork_url: Option<String>,
+  pub duration_ms: u64,
+  pub position_ms: u64,
+  pub theme: WidgetTheme,
+}
+
+pub fn feed(active: Option<&Playing>, template: &str, idle_text: &str, theme: WidgetTheme) -> Feed {
+  Feed {
+    playing: active.is_some(),
+    text: text(active, template, idle_text),
+    title: active.map(|p| p.title.clone()),
+    artist: active.map(|p| p.artist.clone()),
+    album: active.and_then(|p| p.album.clone()),
+    artwork_url: active.and_then(|p| p.artwork_url.clone()),
+    duration_ms: active.map_or(0, |p| p.duration_ms),
+    position_ms: active.map_or(0, |p| p.position_ms),
+    theme,
+  }
+}
+
+/// SoundCloud badge artwork comes in tiny sizes; ask its CDN for the 500px one.
+pub fn large_artwork(url: &str) -> String {
+  for size in ["-t50x50.", "-t120x120.", "-t200x200.", "-large."] {
+    if url.contains(size) {
+      return url.replacen(size, "-t500x500.", 1);
+    }
+  }
+  url.to_string()
+}
+
+/// Widget page for a browser source. It polls the feed with the token from its own
+/// URL (`/widget?token=...`) and follows the configured theme.
+pub const WIDGET_HTML: &str = r#"<!doctype html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Now playing</title>
+<style>
+  html, body { margin: 0; height: 100%; font-family: "Segoe UI", system-ui, sans-serif; }
+  body { display: flex; align-items: center; }
+  body.dark { background: #12151c; color: #e9ecf5; }
+  body.light { background: #f5f6fa; color: #1b202b; }
+  body.transparent { background: transparent; color: #fff; text-shadow: 0 1px 3px rgba(0,0,0,0.8); }
+  .card { display: flex; align-items: center; gap: 14px; padding: 12px 16px; }
+  img { width: 72px; height: 72px; border-radius: 6px; object-fit: cover; }
+  img[hidden] { display: none; }
+  .title { font-size: 20px; font-weight: 600; }
+  .artist { font-size: 16px; opacity: 0.8; }
+  .idle .artist { display: none; }
+</style>
+</head>
+<body class="dark">
+<div class="card" id="card">
+  <img id="art" alt="" hidden>
+  <div><div class="title" id="title"></div><div class="artist" id="artist"></div></div>
+</div>
+<script>
+  const token = new URLSearchParams(location.search).get('token') || '';
+  const $ = (id) => document.getElementById(id);
+  const show = (feed) => {
+    document.body.className = feed.theme || 'dark';
+    $('card').classList.toggle('idle', !feed.playing);
+    $('title').textContent = feed.playing ? feed.title : feed.text;
+    $('artist').textContent = feed.playing ? feed.artist : '';
+    const art = $('art');
+    if (feed.playing && feed.artwork_url && art.src !== feed.artwork_url) art.src = feed.artwork_url;
+    art.hidden = !(feed.playing && feed.artwork_url);
+  };
+  const poll = async () => {
+    try {
+      const res = await fetch('/widget.json', { cache: 'no-store', headers: { Authorization: 'Bearer ' + token } });
+      if (res.ok) show(await res.json());
+    } catch (err) {
+      // The app is closed or restarting; keep the last state and retry.
+    }
+    setTimeout(poll, 2000);
+  };
+  poll();
+</script>
+</body>
+</html>
+"#;
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn track() -> Playing {
+    Playing {
+      track_id: "/bicep/glue".to_string(),
+      title: "Glue".to_string(),
+      artist: "Bicep".to_string(),
+      album: None,
+      artwork_url: Some("https://i1.sndcdn.com/artworks-abc-t500x500.jpg".to_string()),
+      duration_ms: 269_000,
+      position_ms: 1_000,
+      paused: false,
+    }
+  }
+
+  #[test]
+  fn renders_templates() {
+    assert_eq!(render(DEFAULT_TEMPLATE, &track()), "Bicep – Glue");
+    assert_eq!(render("{title} [{duration}]{album}", &track()), "Glue [4:29]");
+    assert_eq!(render("{unknown}", &track()), "{unknown}");
+    assert_eq!(clock(3_725_000), "1:02:05");
+    assert_eq!(text(None, DEFAULT_TEMPLATE, "offline"), "offline");
+  }
+
+  #[test]
+  fn builds_feed() {
+    let playing = track();
+    let live = feed(Some(&playing), DEFAULT_TEMPLATE, DEFAULT_IDLE_TEXT, WidgetTheme::Light);
+    assert!(live.playing);
+    assert_eq!(live.text, "Bicep – Glue");
+    assert_eq!(live.artwork_url, playing.artwork_url);
+    let idle = feed(None, DEFAULT_TEMPLATE, DEFAULT_IDLE_TEXT, WidgetTheme::Dark);
+    assert!(!idle.playing);
+    assert_eq!(idle.text, DEFAULT_IDLE_TEXT);
+    assert_eq!(idle.artwork_url, None);
+  }
+
+  #[test]
+  fn upsizes_artwork() {
+    assert_eq!(
+      large_artwork("https://i1.sndcdn.com/artworks-abc-t50x50.jpg"),
+      "https://i1.sndcdn.com/artworks-abc-t500x500.jpg"
+    );
+    assert_eq!(large_artwork("https://example.com/a.png"), "https://example.com/a.png");
+  }
+
+  #[test]
+  fn accepts_plain_file_names_only() {
+    assert_eq!(file_name(" now-playing.txt "), Ok("now-playing.txt".to_string()));
+    assert!(file_name("Now Playing_2.txt").is_ok());
+    for bad in ["C:\\obs\\np.txt", "/tmp/np.txt", "../np.txt", "..\\np.txt", ".txt", ".hidden.txt", "np.json", "np.txt:stream", ""] {
+      assert!(file_name(bad).is_err(), "{}", bad);
+    }
+  }
+
+  #[test]
+  fn writes_text_atomically() {
+    let path = std::env::temp_dir().join(format!("mscd-now-playing-{}.txt", std::process::id()));
+    write_text(&path, "Bicep – Glue").unwrap();
+    write_text(&path, "Nothing playing").unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "Nothing playing");
+    let _ = fs::remove_file(&path);
+  }
+}
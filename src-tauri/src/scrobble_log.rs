@@ -0,0 +1,185 @@
+// Persisted log of recent scrobble submissions. A 2xx from track.scrobble does not
+// guarantee Last.fm kept the play, so entries stay `Pending` until they show up in
+// user.getRecentTracks (matched by timestamp) or are marked `Missing`.
+
+use std::collections::HashSet;
+
+use crate::lastfm::RecentTrack;
+
+const LOG_LIMIT: usize = 500;
+// Last.fm rejects scrobbles with timestamps older than 14 days.
+pub const MAX_SCROBBLE_AGE_SECS: u64 = 14 * 24 * 60 * 60;
+// Give Last.fm time to process a submission before expecting it in recent tracks.
+pub const VERIFY_GRACE_MS: u64 = 2 * 60 * 1000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubmissionStatus {
+  #[default]
+  Pending,
+  Confirmed,
+  Missing,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SubmittedScrobble {
+  pub artist: String,
+  pub title: String,
+  pub album: Option<String>,
+  pub duration_ms: u64,
+  // Unix seconds, as sent in timestamp[0].
+  pub timestamp: u64,
+  pub submitted_at: u64,
+  pub status: SubmissionStatus,
+  pub attempts: u32,
+}
+
+impl SubmittedScrobble {
+  fn same_play(&self, other: &SubmittedScrobble) -> bool {
+    self.timestamp == other.timestamp && self.artist == other.artist && self.title == other.title
+  }
+
+  pub fn is_expired(&self, now_ms: u64) -> bool {
+    self.timestamp.saturating_add(MAX_SCROBBLE_AGE_SECS) <= now_ms / 1000
+  }
+}
+
+pub fn prune(log: &mut Vec<SubmittedScrobble>, now_ms: u64) {
+  log.retain(|e| !e.is_expired(now_ms));
+  if log.len() > LOG_LIMIT {
+    let excess = log.len() - LOG_LIMIT;
+    log.drain(..excess);
+  }
+}
+
+/// Adds a submission, replacing an earlier attempt for the same play.
+pub fn record(log: &mut Vec<SubmittedScrobble>, mut entry: SubmittedScrobble, now_ms: u64) {
+  if let Some(existing) = log.iter_mut().find(|e| e.same_play(&entry)) {
+    entry.attempts = existing.attempts + 1;
+    *existing = entry;
+  } else {
+    entry.attempts = entry.attempts.max(1);
+    log.push(entry);
+  }
+  prune(log, now_ms);
+}
+
+/// Earliest timestamp among pending submissions that are past the grace period.
+pub fn awaiting_verification(log: &[SubmittedScrobble], now_ms: u64) -> Option<u64> {
+  log
+    .iter()
+    .filter(|e| e.status == SubmissionStatus::Pending && e.submitted_at + VERIFY_GRACE_MS <= now_ms)
+    .map(|e| e.timestamp)
+    .min()
+}
+
+/// Marks pending submissions as confirmed or missing against the plays Last.fm reports.
+/// `covered_from` is the oldest timestamp the fetched history is known to include;
+/// anything older stays pending. Returns entries that just became missing.
+pub fn reconcile(
+  log: &mut [SubmittedScrobble],
+  played: &[RecentTrack],
+  covered_from: u64,
+  now_ms: u64,
+) -> Vec<SubmittedScrobble> {
+  let seen: HashSet<u64> = played.iter().filter_map(|t| t.timestamp).collect();
+  let mut missing = Vec::new();
+  for entry in log.iter_mut() {
+    if entry.status != SubmissionStatus::Pending || entry.submitted_at + VERIFY_GRACE_MS > now_ms {
+      continue;
+    }
+    if seen.contains(&entry.timestamp) {
+      entry.status = SubmissionStatus::Confirmed;
+    } else if entry.timestamp >= covered_from {
+      entry.status = SubmissionStatus::Missing;
+      missing.push(entry.clone());
+    }
+  }
+  missing
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const NOW_MS: u64 = 1_700_000_000_000;
+
+  fn submission(timestamp: u64, submitted_at: u64) -> SubmittedScrobble {
+    SubmittedScrobble {
+      artist: "Bicep".into(),
+      title: format!("Track {}", timestamp),
+      timestamp,
+      submitted_at,
+      ..Default::default()
+    }
+  }
+
+  fn played(timestamp: u64) -> RecentTrack {
+    RecentTrack {
+      artist: "Bicep".into(),
+      // Last.fm autocorrections may change the title; only the timestamp matters.
+      title: "Corrected".into(),
+      album: None,
+      timestamp: Some(timestamp),
+      now_playing: false,
+    }
+  }
+
+  #[test]
+  fn record_replaces_previous_attempt() {
+    let mut log = Vec::new();
+    record(&mut log, submission(1_699_999_000, NOW_MS - 10), NOW_MS);
+    let mut again = submission(1_699_999_000, NOW_MS);
+    again.status = SubmissionStatus::Pending;
+    record(&mut log, again, NOW_MS);
+    assert_eq!(log.len(), 1);
+    assert_eq!(log[0].attempts, 2);
+    assert_eq!(log[0].submitted_at, NOW_MS);
+  }
+
+  #[test]
+  fn prune_drops_expired_and_caps_length() {
+    let mut log = vec![submission(NOW_MS / 1000 - MAX_SCROBBLE_AGE_SECS - 1, 0)];
+    for i in 0..(LOG_LIMIT as u64 + 5) {
+      log.push(submission(NOW_MS / 1000 - 1000 + i, 0));
+    }
+    prune(&mut log, NOW_MS);
+    assert_eq!(log.len(), LOG_LIMIT);
+    assert_eq!(log[0].timestamp, NOW_MS / 1000 - 1000 + 5);
+  }
+
+  #[test]
+  fn reconcile_confirms_and_reports_missing() {
+    let old = NOW_MS - VERIFY_GRACE_MS - 1;
+    let mut log = vec![
+      submission(1_699_990_000, old),
+      submission(1_699_991_000, old),
+      // Too recent to judge.
+      submission(1_699_999_900, NOW_MS - 1_000),
+      // Older than the fetched window.
+      submission(1_699_000_000, old),
+    ];
+    let missing = reconcile(&mut log, &[played(1_699_990_000)], 1_699_989_000, NOW_MS);
+    assert_eq!(log[0].status, SubmissionStatus::Confirmed);
+    assert_eq!(log[1].status, SubmissionStatus::Missing);
+    assert_eq!(log[2].status, SubmissionStatus::Pending);
+    assert_eq!(log[3].status, SubmissionStatus::Pending);
+    assert_eq!(missing.len(), 1);
+    assert_eq!(missing[0].timestamp, 1_699_991_000);
+
+    // Already-missing entries are not reported twice.
+    assert!(reconcile(&mut log, &[], 0, NOW_MS).len() == 1);
+    assert_eq!(log[3].status, SubmissionStatus::Missing);
+  }
+
+  #[test]
+  fn awaiting_verification_ignores_fresh_and_settled_entries() {
+    let old = NOW_MS - VERIFY_GRACE_MS;
+    let mut confirmed = submission(10, old);
+    confirmed.status = SubmissionStatus::Confirmed;
+    let log = vec![confirmed, submission(20, old), submission(5, NOW_MS)];
+    assert_eq!(awaiting_verification(&log, NOW_MS), Some(20));
+    assert_eq!(awaiting_verification(&log[..1], NOW_MS), None);
+  }
+}
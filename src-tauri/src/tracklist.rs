@@ -0,0 +1,343 @@
+// Timestamped tracklist parsing for DJ mixes ("00:00 Artist - Title" lines) and
+// per-sub-track listen accounting so each entry can be scrobbled on its own.
+
+const MIN_ENTRIES: usize = 2;
+// Last.fm ignores scrobbles for tracks shorter than 30 seconds.
+const MIN_SUBTRACK_MS: u64 = 30_000;
+const SEPARATORS: [&str; 3] = [" - ", " – ", " — "];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TracklistEntry {
+  pub offset_ms: u64,
+  pub artist: String,
+  pub title: String,
+}
+
+impl TracklistEntry {
+  // "ID - ID" style placeholders mark unreleased/unknown tracks; they still bound
+  // a time window but should never be scrobbled.
+  pub fn is_identified(&self) -> bool {
+    let unknown = |s: &str| {
+      let s = s.trim();
+      s.is_empty() || s.eq_ignore_ascii_case("id") || s == "?" || s == "???"
+    };
+    !unknown(&self.artist) && !unknown(&self.title)
+  }
+}
+
+fn parse_timestamp(raw: &str) -> Option<u64> {
+  let parts: Vec<&str> = raw.split(':').collect();
+  if parts.len() < 2 || parts.len() > 3 {
+    return None;
+  }
+  let mut total: u64 = 0;
+  for (i, part) in parts.iter().enumerate() {
+    if part.is_empty() || part.len() > 2 || !part.chars().all(|c| c.is_ascii_digit()) {
+      return None;
+    }
+    let v: u64 = part.parse().ok()?;
+    // Minutes and seconds after the leading component must be two digits below 60.
+    if i > 0 && (part.len() != 2 || v >= 60) {
+      return None;
+    }
+    total = total * 60 + v;
+  }
+  Some(total * 1000)
+}
+
+// Strips a timestamp token, optionally wrapped in brackets, from the start of `s`.
+fn take_leading_timestamp(s: &str) -> Option<(u64, &str)> {
+  let (inner, rest) = match s.chars().next()? {
+    '[' => s[1..].split_once(']')?,
+    '(' => s[1..].split_once(')')?,
+    _ => {
+      let end = s.find(|c: char| !(c.is_ascii_digit() || c == ':')).unwrap_or(s.len());
+      (&s[..end], &s[end..])
+    }
+  };
+  Some((parse_timestamp(inner.trim())?, rest))
+}
+
+fn take_trailing_timestamp(s: &str) -> Option<(u64, &str)> {
+  let trimmed = s.trim_end();
+  let (inner, rest) = if let Some(stripped) = trimmed.strip_suffix(']') {
+    let open = stripped.rfind('[')?;
+    (&stripped[open + 1..], &stripped[..open])
+  } else if let Some(stripped) = trimmed.strip_suffix(')') {
+    let open = stripped.rfind('(')?;
+    (&stripped[open + 1..], &stripped[..open])
+  } else {
+    let start = trimmed
+      .rfind(|c: char| !(c.is_ascii_digit() || c == ':'))
+      .map(|i| i + trimmed[i..].chars().next().map(|c| c.len_utf8()).unwrap_or(1))
+      .unwrap_or(0);
+    (&trimmed[start..], &trimmed[..start])
+  };
+  Some((parse_timestamp(inner.trim())?, rest))
+}
+
+fn strip_index(s: &str) -> &str {
+  // "01. ", "1) ", "#3 " style numbering before the timestamp.
+  let s = s.trim_start_matches('#');
+  let digits = s.chars().take_while(|c| c.is_ascii_digit()).count();
+  if digits == 0 || digits > 3 {
+    return s;
+  }
+  let rest = &s[digits..];
+  match rest.chars().next() {
+    Some('.') | Some(')') => rest[1..].trim_start(),
+    _ => s,
+  }
+}
+
+fn trim_decoration(s: &str) -> &str {
+  s.trim_matches(|c: char| c.is_whitespace() || matches!(c, '-' | '–' | '—' | '|' | ':' | '.' | '•' | '>'))
+}
+
+fn split_artist_title(s: &str) -> Option<(String, String)> {
+  let (idx, sep) = SEPARATORS
+    .iter()
+    .filter_map(|sep| s.find(sep).map(|i| (i, *sep)))
+    .min_by_key(|(i, _)| *i)?;
+  let artist = trim_decoration(&s[..idx]);
+  let mut title = trim_decoration(&s[idx + sep.len()..]);
+  // Drop a trailing "[Label]" credit; keep parenthesised mix names.
+  if let Some(stripped) = title.strip_suffix(']') {
+    if let Some(open) = stripped.rfind('[') {
+      let candidate = stripped[..open].trim_end();
+      if !candidate.is_empty() {
+        title = candidate;
+      }
+    }
+  }
+  if artist.is_empty() || title.is_empty() {
+    return None;
+  }
+  Some((artist.to_string(), title.to_string()))
+}
+
+fn parse_line(line: &str) -> Option<TracklistEntry> {
+  let line = strip_index(line.trim());
+  if line.is_empty() {
+    return None;
+  }
+  let (offset_ms, rest) = match take_leading_timestamp(line) {
+    Some(v) => v,
+    None => take_trailing_timestamp(line)?,
+  };
+  let (artist, title) = split_artist_title(trim_decoration(rest))?;
+  Some(TracklistEntry { offset_ms, artist, title })
+}
+
+/// Extracts a tracklist from free text (description, comments). Returns `None`
+/// unless at least two entries with strictly increasing timestamps are found.
+/// Once a list is found, the first line that does not continue it ends the list.
+pub fn parse_tracklist(text: &str) -> Option<Vec<TracklistEntry>> {
+  let mut entries: Vec<TracklistEntry> = Vec::new();
+  for entry in text.lines().filter_map(parse_line) {
+    match entries.last() {
+      Some(prev) if entry.offset_ms <= prev.offset_ms => {
+        // Comments follow the description: a repeated list or a timestamped
+        // comment ("12:30 Bicep - this drop") ends the list instead of voiding it.
+        if entries.len() >= MIN_ENTRIES {
+          break;
+        }
+        return None;
+      }
+      _ => entries.push(entry),
+    }
+  }
+  if entries.len() < MIN_ENTRIES {
+    return None;
+  }
+  Some(entries)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubTrackScrobble {
+  pub index: usize,
+  pub artist: String,
+  pub title: String,
+  pub duration_ms: u64,
+  pub started_at: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct MixProgress {
+  entries: Vec<TracklistEntry>,
+  mix_duration_ms: u64,
+  listened_ms: Vec<u64>,
+  started_at: Vec<Option<u64>>,
+  scrobbled: Vec<bool>,
+}
+
+impl MixProgress {
+  pub fn new(entries: Vec<TracklistEntry>, mix_duration_ms: u64) -> Self {
+    let n = entries.len();
+    Self {
+      entries,
+      mix_duration_ms,
+      listened_ms: vec![0; n],
+      started_at: vec![None; n],
+      scrobbled: vec![false; n],
+    }
+  }
+
+  pub fn entry_duration_ms(&self, idx: usize) -> u64 {
+    let start = self.entries[idx].offset_ms;
+    let end = self
+      .entries
+      .get(idx + 1)
+      .map(|e| e.offset_ms)
+      .unwrap_or(self.mix_duration_ms);
+    end.saturating_sub(start)
+  }
+
+  pub fn index_at(&self, position_ms: u64) -> Option<usize> {
+    self.entries.iter().rposition(|e| e.offset_ms <= position_ms)
+  }
+
+  /// Credits `increment_ms` of real listening to the sub-track under `position_ms`
+  /// and returns it if that sub-track just crossed its own threshold.
+  pub fn advance(&mut self, position_ms: u64, increment_ms: u64, threshold: f32, now_ms: u64) -> Option<SubTrackScrobble> {
+    let idx = self.index_at(position_ms)?;
+    let entry = &self.entries[idx];
+    if self.started_at[idx].is_none() {
+      self.started_at[idx] = Some(now_ms.saturating_sub(position_ms - entry.offset_ms));
+    }
+    self.listened_ms[idx] = self.listened_ms[idx].saturating_add(increment_ms);
+
+    let duration_ms = self.entry_duration_ms(idx);
+    if self.scrobbled[idx] || !entry.is_identified() || duration_ms < MIN_SUBTRACK_MS {
+      return None;
+    }
+    let threshold_ms = (duration_ms as f32 * threshold).round() as u64;
+    if self.listened_ms[idx] < threshold_ms {
+      return None;
+    }
+    self.scrobbled[idx] = true;
+    Some(SubTrackScrobble {
+      index: idx,
+      artist: entry.artist.clone(),
+      title: entry.title.clone(),
+      duration_ms,
+      started_at: self.started_at[idx].unwrap_or(now_ms),
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn entry(offset_s: u64, artist: &str, title: &str) -> TracklistEntry {
+    TracklistEntry {
+      offset_ms: offset_s * 1000,
+      artist: artist.to_string(),
+      title: title.to_string(),
+    }
+  }
+
+  #[test]
+  fn parses_basic_tracklist() {
+    let text = "Recorded live!\n\n00:00 Bicep - Glue\n04:12 Overmono – So U Kno\n1:02:03 Floating Points — Last Bloom\n";
+    let list = parse_tracklist(text).unwrap();
+    assert_eq!(
+      list,
+      vec![
+        entry(0, "Bicep", "Glue"),
+        entry(252, "Overmono", "So U Kno"),
+        entry(3723, "Floating Points", "Last Bloom"),
+      ]
+    );
+  }
+
+  #[test]
+  fn parses_numbered_bracketed_and_trailing_timestamps() {
+    let text = "1. [00:00] Bicep - Glue [Ninja Tune]\n2) (03:30) Four Tet - Baby (Extended Mix)\nSkee Mask - Rollin Fog 07:45\n";
+    let list = parse_tracklist(text).unwrap();
+    assert_eq!(
+      list,
+      vec![
+        entry(0, "Bicep", "Glue"),
+        entry(210, "Four Tet", "Baby (Extended Mix)"),
+        entry(465, "Skee Mask", "Rollin Fog"),
+      ]
+    );
+  }
+
+  #[test]
+  fn rejects_text_without_a_real_tracklist() {
+    assert!(parse_tracklist("").is_none());
+    assert!(parse_tracklist("00:00 Only - One").is_none());
+    assert!(parse_tracklist("Out now on all platforms - link below\nfollow me - @dj").is_none());
+    assert!(parse_tracklist("00:00 Intro\n01:00 More words").is_none());
+    assert!(parse_tracklist("00:75 Bad - Seconds\n01:00 A - B").is_none());
+  }
+
+  #[test]
+  fn rejects_out_of_order_timestamps() {
+    assert!(parse_tracklist("05:00 A - One\n02:00 B - Two\n08:00 C - Three").is_none());
+  }
+
+  #[test]
+  fn stops_at_repeated_list() {
+    let text = "00:00 A - One\n03:00 B - Two\n\n00:00 A - One\n03:00 B - Two";
+    assert_eq!(parse_tracklist(text).unwrap().len(), 2);
+  }
+
+  #[test]
+  fn stops_at_a_timestamped_comment_after_the_list() {
+    let text = "00:00 A - One\n03:00 B - Two\n20:00 C - Three\n12:30 B - Two this drop!!";
+    let list = parse_tracklist(text).unwrap();
+    assert_eq!(list.len(), 3);
+    assert_eq!(list[2], entry(1200, "C", "Three"));
+  }
+
+  #[test]
+  fn id_placeholders_are_not_identified() {
+    let list = parse_tracklist("00:00 ID - ID\n03:00 Bicep - ID\n06:00 Bicep - Glue").unwrap();
+    assert!(!list[0].is_identified());
+    assert!(!list[1].is_identified());
+    assert!(list[2].is_identified());
+  }
+
+  #[test]
+  fn entry_windows_use_next_offset_or_mix_end() {
+    let mix = MixProgress::new(vec![entry(0, "A", "One"), entry(180, "B", "Two")], 600_000);
+    assert_eq!(mix.entry_duration_ms(0), 180_000);
+    assert_eq!(mix.entry_duration_ms(1), 420_000);
+    assert_eq!(mix.index_at(179_999), Some(0));
+    assert_eq!(mix.index_at(180_000), Some(1));
+  }
+
+  #[test]
+  fn scrobbles_each_sub_track_at_its_own_threshold() {
+    let mut mix = MixProgress::new(vec![entry(0, "A", "One"), entry(100, "B", "Two")], 400_000);
+    let now = 1_000_000;
+    // 49s into a 100s sub-track: below the 50% threshold.
+    assert_eq!(mix.advance(49_000, 49_000, 0.5, now), None);
+    let hit = mix.advance(50_000, 1_000, 0.5, now + 1_000).unwrap();
+    assert_eq!(hit.index, 0);
+    assert_eq!(hit.title, "One");
+    assert_eq!(hit.duration_ms, 100_000);
+    assert_eq!(hit.started_at, now - 49_000);
+    // Already scrobbled; further listening does not repeat it.
+    assert_eq!(mix.advance(60_000, 10_000, 0.5, now + 11_000), None);
+    // The second window needs 150s of its own.
+    assert_eq!(mix.advance(200_000, 100_000, 0.5, now), None);
+    let hit = mix.advance(250_000, 50_000, 0.5, now).unwrap();
+    assert_eq!((hit.index, hit.artist.as_str()), (1, "B"));
+  }
+
+  #[test]
+  fn skips_short_and_unidentified_sub_tracks() {
+    let mut mix = MixProgress::new(
+      vec![entry(0, "A", "Short"), entry(20, "ID", "ID"), entry(200, "C", "Three")],
+      300_000,
+    );
+    assert_eq!(mix.advance(19_000, 19_000, 0.5, 0), None);
+    assert_eq!(mix.advance(199_000, 179_000, 0.5, 0), None);
+    assert!(mix.advance(299_000, 99_000, 0.5, 0).is_some());
+  }
+}